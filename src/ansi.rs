@@ -201,6 +201,22 @@ impl ANSIParser {
         let fragments = std::mem::take(&mut self.fragments);
         AnsiString::new_string(stripped, fragments)
     }
+
+    /// the bytes stripped as `parse_ansi` strips the text, the bytes that are not valid UTF-8 are kept
+    pub fn strip_ansi_bytes(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut state_machine = vte::Parser::new();
+        let mut stripped = Vec::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            state_machine.advance(self, chunk.valid().as_bytes());
+            self.save_str();
+            stripped.extend_from_slice(std::mem::take(&mut self.stripped).as_bytes());
+            stripped.extend_from_slice(chunk.invalid());
+        }
+
+        self.stripped_char_count = 0;
+        self.fragments.clear();
+        stripped
+    }
 }
 
 /// A String that contains ANSI state (e.g. colors)
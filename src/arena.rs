@@ -0,0 +1,239 @@
+//! Compact storage for plain text items.
+//!
+//! Instead of allocating an `Arc<dyn SkimItem>` for every line, the lines of an `ItemChunk` live
+//! in one contiguous buffer together with a table of their end offsets. A chunk travels through
+//! the item channel as a single `SkimItem`, the `ItemPool` stores it as is, and the matcher
+//! matches its lines in place. An item is only allocated for a line (`ChunkLine`) when it is
+//! displayed, selected or output.
+//!
+//! The readers of this crate (e.g. `SkimItemReader`) send chunks, so a consumer of their
+//! `SkimItemReceiver` that needs the items one by one unpacks them with `ItemChunk::unpack`.
+use std::borrow::Cow;
+use std::sync::{Arc, Weak};
+
+use crate::SkimItem;
+
+//------------------------------------------------------------------------------
+#[derive(Clone)]
+pub struct ChunkData {
+    text: Box<str>,
+    // end offset of every line in `text`, the line separators are not stored
+    ends: Box<[u32]>,
+    // the label of the source of all the lines
+    source: Option<Arc<str>>,
+}
+
+impl ChunkData {
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    fn line(&self, idx: usize) -> &str {
+        let start = if idx == 0 { 0 } else { self.ends[idx - 1] as usize };
+        &self.text[start..self.ends[idx] as usize]
+    }
+}
+
+//------------------------------------------------------------------------------
+/// Lines stored in one contiguous buffer, cheap to clone. It is a single `SkimItem` in the item
+/// channel, whose text is all the lines joined by `\n`, but every line is an item of its own.
+#[derive(Clone)]
+pub struct ItemChunk {
+    data: Arc<ChunkData>,
+}
+
+impl ItemChunk {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the text of the `idx`th line of the chunk
+    pub fn line(&self, idx: usize) -> &str {
+        self.data.line(idx)
+    }
+
+    /// the `idx`th line of the chunk as a standalone item
+    pub fn get(&self, idx: usize) -> ChunkLine {
+        ChunkLine {
+            data: self.data.clone(),
+            idx: idx as u32,
+        }
+    }
+
+    /// the lines of the chunk as standalone items
+    pub fn lines(&self) -> impl Iterator<Item = ChunkLine> + '_ {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+
+    /// the items received from a `SkimItemReceiver`, with the lines of the chunks as items of
+    /// their own, in order
+    pub fn unpack(items: Vec<Arc<dyn SkimItem>>) -> Vec<Arc<dyn SkimItem>> {
+        let mut unpacked = Vec::with_capacity(items.len());
+        for item in items {
+            match item.as_any().downcast_ref::<ItemChunk>() {
+                Some(chunk) => unpacked.extend(chunk.lines().map(|line| Arc::new(line) as Arc<dyn SkimItem>)),
+                None => unpacked.push(item),
+            }
+        }
+        unpacked
+    }
+
+    /// a weak reference to the `idx`th line of the chunk
+    pub fn downgrade(&self, idx: usize) -> (Weak<ChunkData>, u32) {
+        (Arc::downgrade(&self.data), idx as u32)
+    }
+
+    /// label the lines with their source, the lines are copied if the chunk is shared
+    pub fn with_source(mut self, source: Arc<str>) -> Self {
+        Arc::make_mut(&mut self.data).source = Some(source);
+        self
+    }
+}
+
+impl SkimItem for ItemChunk {
+    // only used by consumers of the item channel that are not aware of chunks
+    fn text(&self) -> Cow<'_, str> {
+        let lines: Vec<&str> = (0..self.len()).map(|idx| self.line(idx)).collect();
+        Cow::Owned(lines.join("\n"))
+    }
+
+    fn source(&self) -> Option<&str> {
+        self.data.source.as_deref()
+    }
+}
+
+//------------------------------------------------------------------------------
+pub struct ItemChunkBuilder {
+    text: String,
+    ends: Vec<u32>,
+}
+
+impl ItemChunkBuilder {
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            text: String::with_capacity(bytes),
+            ends: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// append a line, return false if the chunk cannot hold it
+    pub fn push(&mut self, line: &str) -> bool {
+        if self.text.len() + line.len() > u32::MAX as usize {
+            return false;
+        }
+
+        self.text.push_str(line);
+        self.ends.push(self.text.len() as u32);
+        true
+    }
+
+    pub fn build(self) -> ItemChunk {
+        ItemChunk {
+            data: Arc::new(ChunkData {
+                text: self.text.into_boxed_str(),
+                ends: self.ends.into_boxed_slice(),
+                source: None,
+            }),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+/// A line of an `ItemChunk`, which keeps the whole chunk alive
+#[derive(Clone)]
+pub struct ChunkLine {
+    data: Arc<ChunkData>,
+    idx: u32,
+}
+
+impl ChunkLine {
+    pub fn upgrade(data: &Weak<ChunkData>, idx: u32) -> Option<Self> {
+        data.upgrade().map(|data| Self { data, idx })
+    }
+
+    /// whether both refer to the same line of the same chunk
+    pub fn same_line(&self, other: &ChunkLine) -> bool {
+        Arc::ptr_eq(&self.data, &other.data) && self.idx == other.idx
+    }
+}
+
+impl SkimItem for ChunkLine {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.data.line(self.idx as usize))
+    }
+
+    fn source(&self) -> Option<&str> {
+        self.data.source.as_deref()
+    }
+}
+
+#[cfg(test)]
+impl ItemChunk {
+    /// a chunk of all the `lines`, for the tests
+    pub fn from_lines(lines: &[&str]) -> Self {
+        let mut builder = ItemChunkBuilder::with_capacity(64);
+        lines.iter().for_each(|line| assert!(builder.push(line)));
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_chunk() {
+        let chunk = ItemChunk::from_lines(&["foo", "", "bar baz", "ünïcode"]);
+        assert_eq!(chunk.len(), 4);
+        assert_eq!(chunk.line(0), "foo");
+        assert_eq!(chunk.line(1), "");
+        assert_eq!(chunk.line(2), "bar baz");
+        assert_eq!(chunk.line(3), "ünïcode");
+        assert_eq!(chunk.text(), "foo\n\nbar baz\nünïcode");
+
+        assert_eq!(chunk.get(3).text(), "ünïcode");
+        assert!(chunk.get(2).same_line(&chunk.clone().get(2)));
+        assert!(!chunk.get(2).same_line(&chunk.get(1)));
+
+        let (weak, idx) = chunk.downgrade(2);
+        assert_eq!(ChunkLine::upgrade(&weak, idx).unwrap().text(), "bar baz");
+        drop(chunk);
+        assert!(ChunkLine::upgrade(&weak, idx).is_none());
+    }
+
+    #[test]
+    fn test_unpack_chunks() {
+        let items: Vec<Arc<dyn SkimItem>> = vec![
+            Arc::new(ItemChunk::from_lines(&["a", "b"])),
+            Arc::new("c".to_string()),
+            Arc::new(ItemChunk::from_lines(&["d"])),
+        ];
+        let texts: Vec<_> = ItemChunk::unpack(items)
+            .iter()
+            .map(|item| item.text().into_owned())
+            .collect();
+        assert_eq!(texts, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_item_chunk_source() {
+        let unlabeled = ItemChunk::from_lines(&["foo", "bar"]);
+        assert_eq!(unlabeled.get(1).source(), None);
+
+        let shared = unlabeled.clone();
+        let labeled = unlabeled.with_source("recent".into());
+        assert_eq!(labeled.source(), Some("recent"));
+        assert_eq!(labeled.get(1).source(), Some("recent"));
+        assert_eq!(labeled.get(1).text(), "bar");
+        // the other clones keep their lines unlabeled
+        assert_eq!(shared.get(1).source(), None);
+    }
+}
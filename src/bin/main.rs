@@ -8,7 +8,8 @@ extern crate skim;
 use derive_builder::Builder;
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, IsTerminal, Read, Write};
+use std::path::PathBuf;
 
 use clap::{crate_version, App, Arg, ArgMatches};
 use skim::prelude::*;
@@ -28,14 +29,26 @@ Usage: sk [options]
   Search
     --tac                reverse the order of search result
     --no-sort            Do not sort the result
-    -t, --tiebreak [score,begin,end,-score,length...]
+    -t, --tiebreak [score,begin,end,-score,length,field:N...]
 
                          comma seperated criteria
+    --min-score=N        Drop the matches scoring less than N
+    --max-results=N      Only keep the best N matches
+    --sort-by=FIELD[:num|:lex][:desc],..
+                         sort the matches by a field instead of the score,
+                         toggle-sort switches to the next one
     -n, --nth 1,2..5     specify the fields to be matched
     --with-nth 1,2..5    specify the fields to be transformed
     -d, --delimiter \\t  specify the delimiter(in REGEX) for fields
+    --field-names=NAMES  comma seperated names of the fields, so that
+                         'name:foo' searches for 'foo' in the named field
+    --input-format=FMT   parse the input records into fields: [text|jsonl|csv|tsv]
+                         the columns are named by the header row or JSON keys
     -e, --exact          start skim in exact mode
     --regex              use regex instead of fuzzy match
+    --literal            Do not normalize latin script letters before matching
+    --translit=SCRIPTS   also match the romanized text of the items written
+                         in the given scripts: [ru,pinyin]
     --algo=TYPE          Fuzzy matching algorithm:
                          [skim_v1|skim_v2|clangd] (default: skim_v2)
     --case [respect,ignore,smart] (default: smart)
@@ -49,6 +62,11 @@ Usage: sk [options]
     --no-mouse           Disable mouse events
     -c, --cmd ag         command to invoke dynamically
     -i, --interactive    Start skim in interactive(command) mode
+    --cmd-debounce=MS    Run the command once it is not edited for MS
+                         milliseconds in interactive mode (default: 0)
+    --walker=OPTS        List the files with the built-in walker when input is tty
+                         [file][,dir][,follow][,hidden][,no-ignore][,depth:N]
+                         (default: file,follow,hidden)
     --color [BASE][,COLOR:ANSI]
                          change color theme
     --no-hscroll         Disable horizontal scroll
@@ -57,6 +75,8 @@ Usage: sk [options]
     --no-clear-if-empty  Do not clear previous items if command returns empty result
     --no-clear-start     Do not clear on start
     --show-cmd-error     Send command error message if command fails
+    --with-shell=SHELL   Run the commands of the sources, execute and preview
+                         with SHELL, e.g. 'bash -c' (default: $SHELL -c)
 
   Layout
     --layout=LAYOUT      Choose layout: [default|reverse|reverse-list]
@@ -75,6 +95,8 @@ Usage: sk [options]
     --inline-info        Display info next to query
     --header=STR         Display STR next to info
     --header-lines=N     The first N lines of the input are treated as header
+    --multi-line=N       Display items with newlines (e.g. with --read0)
+                         on up to N rows (default: 1)
 
   History
     --history=FILE       History file
@@ -85,6 +107,9 @@ Usage: sk [options]
   Preview
     --preview=COMMAND    command to preview current highlighted line ({})
                          We can specify the fields. e.g. ({1}, {..3}, {0..})
+    --preview-argv=COMMAND
+                         same as --preview, without a shell: COMMAND is split
+                         into arguments and the fields are not quoted
     --preview-window=OPT Preview window layout (default: right:50%)
                          [up|down|left|right][:SIZE[%]][:hidden][:+SCROLL[-OFFSET]]
 
@@ -93,12 +118,27 @@ Usage: sk [options]
     --cmd-query \"\"       specify the initial query for interactive mode
     --expect KEYS        comma seperated keys that can be used to complete skim
     --read0              Read input delimited by ASCII NUL(\\0) characters
+    --follow=FILE        Read FILE and keep reading the lines appended to it,
+                         across truncation and rotation (instead of stdin)
+    --tail=N             Only keep the newest N items, the older ones are dropped
+    --unique[=KEEP]      Drop the lines read already, keep the first or the last
+                         of the same lines [first|last] (default: first).
+                         'last' shows the lines once the input ends, not
+                         with --follow
+    --unique-key=FIELDS  The lines are the same if these fields are, e.g. '2'
+    --source=[LABEL:]SRC Read the items from SRC, a command, '@FILE' or '-' for
+                         stdin (instead of stdin). Repeat it to read several
+                         sources at once, the items are labeled with LABEL
+                         (default: the number of the source), matched by
+                         'src:LABEL' and passed to commands as {src}
     --print0             Print output delimited by ASCII NUL(\\0) characters
     --no-clear-start     Do not clear screen on start
     --no-clear           Do not clear screen on exit
     --print-query        Print query as the first line
     --print-cmd          Print command query as the first line (after --print-query)
     --print-score        Print matching score in filter output (with --filter)
+    -f, --filter=STR     Filter mode, print the items matching STR and exit
+    --filter-limit=N     Only print the best N matches (with --filter)
     -1, --select-1       Automatically select the only match
     -0, --exit-0         Exit immediately when there's no match
     --sync               Synchronous search for multi-staged filtering
@@ -111,7 +151,8 @@ Usage: sk [options]
                          Pre-select the items read from file
 
   Environment variables
-    SKIM_DEFAULT_COMMAND Default command to use when input is tty
+    SKIM_DEFAULT_COMMAND Default command to use when input is tty, instead of
+                         the built-in walker
     SKIM_DEFAULT_OPTIONS Default options (e.g. '--ansi --regex')
                          You should not include other environment variables
                          (e.g. '-c \"$HOME/bin/ag\"')
@@ -121,7 +162,6 @@ Usage: sk [options]
 
   Reserved (not used for now)
     --extended
-    --literal
     --cycle
     --hscroll-off=COL
     --filepath-word
@@ -183,15 +223,20 @@ fn real_main() -> Result<i32, std::io::Error> {
         .arg(Arg::with_name("expect").long("expect").multiple(true).takes_value(true))
         .arg(Arg::with_name("tac").long("tac").multiple(true))
         .arg(Arg::with_name("tiebreak").long("tiebreak").short('t').multiple(true).takes_value(true).default_value("score,index,-length"))
+        .arg(Arg::with_name("sort-by").long("sort-by").multiple(true).takes_value(true))
         .arg(Arg::with_name("ansi").long("ansi").multiple(true))
         .arg(Arg::with_name("exact").long("exact").short('e').multiple(true))
         .arg(Arg::with_name("disabled").long("disabled").multiple(true))
         .arg(Arg::with_name("cmd").long("cmd").short('c').multiple(true).takes_value(true))
         .arg(Arg::with_name("interactive").long("interactive").short('i').multiple(true))
+        .arg(Arg::with_name("cmd-debounce").long("cmd-debounce").multiple(true).takes_value(true))
+        .arg(Arg::with_name("walker").long("walker").multiple(true).takes_value(true))
         .arg(Arg::with_name("query").long("query").short('q').multiple(true).takes_value(true))
         .arg(Arg::with_name("cmd-query").long("cmd-query").multiple(true).takes_value(true))
         .arg(Arg::with_name("regex").long("regex").multiple(true))
         .arg(Arg::with_name("delimiter").long("delimiter").short('d').multiple(true).takes_value(true))
+        .arg(Arg::with_name("field-names").long("field-names").multiple(true).takes_value(true))
+        .arg(Arg::with_name("input-format").long("input-format").multiple(true).takes_value(true))
         .arg(Arg::with_name("nth").long("nth").short('n').multiple(true).takes_value(true))
         .arg(Arg::with_name("with-nth").long("with-nth").multiple(true).takes_value(true))
         .arg(Arg::with_name("replstr").short('I').multiple(true).takes_value(true))
@@ -204,11 +249,20 @@ fn real_main() -> Result<i32, std::io::Error> {
         .arg(Arg::with_name("no-clear-start").long("no-clear-start").multiple(true))
         .arg(Arg::with_name("no-mouse").long("no-mouse").multiple(true))
         .arg(Arg::with_name("preview").long("preview").multiple(true).takes_value(true))
+        .arg(Arg::with_name("preview-argv").long("preview-argv").multiple(true).takes_value(true))
         .arg(Arg::with_name("preview-window").long("preview-window").multiple(true).takes_value(true).default_value("right:50%"))
         .arg(Arg::with_name("reverse").long("reverse").multiple(true))
         .arg(Arg::with_name("algo").long("algo").multiple(false).takes_value(true))
         .arg(Arg::with_name("case").long("case").multiple(true).takes_value(true).default_value("smart"))
         .arg(Arg::with_name("literal").long("literal").multiple(true))
+        .arg(Arg::with_name("translit").long("translit").multiple(true).takes_value(true))
+        .arg(Arg::with_name("min-score").long("min-score").multiple(true).takes_value(true))
+        .arg(Arg::with_name("max-results").long("max-results").multiple(true).takes_value(true))
+        .arg(Arg::with_name("follow").long("follow").multiple(true).takes_value(true))
+        .arg(Arg::with_name("tail").long("tail").multiple(true).takes_value(true))
+        .arg(Arg::with_name("unique").long("unique").multiple(true).takes_value(true).min_values(0).require_equals(true).default_missing_value("first"))
+        .arg(Arg::with_name("unique-key").long("unique-key").multiple(true).takes_value(true))
+        .arg(Arg::with_name("source").long("source").multiple(true).takes_value(true))
         .arg(Arg::with_name("cycle").long("cycle").multiple(true))
         .arg(Arg::with_name("no-hscroll").long("no-hscroll").multiple(true))
         .arg(Arg::with_name("hscroll-off").long("hscroll-off").multiple(true).takes_value(true).default_value("10"))
@@ -218,6 +272,7 @@ fn real_main() -> Result<i32, std::io::Error> {
         .arg(Arg::with_name("inline-info").long("inline-info").multiple(true))
         .arg(Arg::with_name("header").long("header").multiple(true).takes_value(true).default_value(""))
         .arg(Arg::with_name("header-lines").long("header-lines").multiple(true).takes_value(true).default_value("0"))
+        .arg(Arg::with_name("multi-line").long("multi-line").multiple(true).takes_value(true).default_value("1"))
         .arg(Arg::with_name("tabstop").long("tabstop").multiple(true).takes_value(true).default_value("8"))
         .arg(Arg::with_name("no-bold").long("no-bold").multiple(true))
         .arg(Arg::with_name("history").long("history").multiple(true).takes_value(true))
@@ -235,6 +290,7 @@ fn real_main() -> Result<i32, std::io::Error> {
         .arg(Arg::with_name("select-1").long("select-1").short('1').multiple(true))
         .arg(Arg::with_name("exit-0").long("exit-0").short('0').multiple(true))
         .arg(Arg::with_name("filter").long("filter").short('f').takes_value(true).multiple(true))
+        .arg(Arg::with_name("filter-limit").long("filter-limit").multiple(true).takes_value(true))
         .arg(Arg::with_name("layout").long("layout").multiple(true).takes_value(true).default_value("default"))
         .arg(Arg::with_name("keep-right").long("keep-right").multiple(true))
         .arg(Arg::with_name("skip-to-pattern").long("skip-to-pattern").multiple(true).takes_value(true).default_value(""))
@@ -244,6 +300,7 @@ fn real_main() -> Result<i32, std::io::Error> {
         .arg(Arg::with_name("pre-select-file").long("pre-select-file").multiple(true).takes_value(true).default_value(""))
         .arg(Arg::with_name("no-clear-if-empty").long("no-clear-if-empty").multiple(true))
         .arg(Arg::with_name("show-cmd-error").long("show-cmd-error").multiple(true))
+        .arg(Arg::with_name("with-shell").long("with-shell").multiple(true).takes_value(true))
         .get_matches_from(args);
 
     if opts.is_present("help") {
@@ -257,15 +314,87 @@ fn real_main() -> Result<i32, std::io::Error> {
     let preview_window_joined = opts.values_of("preview-window").map(|x| x.collect::<Vec<_>>().join(":"));
     options.preview_window = preview_window_joined.as_deref();
 
+    // the shell that runs the commands of the sources, of execute and of the preview
+    if let Some(with_shell) = opts.values_of("with-shell").and_then(|vals| vals.last()) {
+        match Shell::parse(with_shell) {
+            Some(shell) => options.shell = shell,
+            None => {
+                eprintln!("invalid --with-shell: {:?}, expected e.g. \"sh -c\"", with_shell);
+                return Ok(2);
+            }
+        }
+    }
+
+    //------------------------------------------------------------------------------
+    // structured input, the names of the columns are read from the header row or the first JSON
+    // object, unless given
+    let input_format = opts.values_of("input-format").and_then(|vals| vals.last()).and_then(InputFormat::from_str).unwrap_or_default();
+    let line_ending = if opts.is_present("read0") { b'\0' } else { b'\n' };
+    // several sources are read at once, their items labeled with the sources
+    let sources: Vec<ItemSource> = opts.values_of("source").map(|vals| vals.enumerate().map(|(idx, spec)| ItemSource::parse(spec, &(idx + 1).to_string())).collect()).unwrap_or_default();
+    let follow = opts.values_of("follow").and_then(|vals| vals.last());
+    let mut source: Option<Box<dyn BufRead + Send>> = match follow {
+        _ if !sources.is_empty() => None,
+        Some(path) => match FollowReader::open(path) {
+            Ok(reader) => Some(Box::new(BufReader::with_capacity(READ_BUFFER_CAPACITY, reader))),
+            Err(err) => {
+                eprintln!("cannot follow {}: {}", path, err);
+                return Ok(2);
+            }
+        },
+        None if !std::io::stdin().is_terminal() => Some(Box::new(BufReader::with_capacity(READ_BUFFER_CAPACITY, std::io::stdin()))),
+        None => None,
+    };
+
+    let mut column_names = String::new();
+    if input_format.is_structured() {
+        options.delimiter = Some(InputFormat::DELIMITER);
+    }
+    let read_columns = input_format.is_structured() && options.field_names.is_none();
+    if let Some(mut reader) = source.take_if(|_| read_columns) {
+        let mut first_record = Vec::new();
+        reader.read_until(line_ending, &mut first_record)?;
+        let record = String::from_utf8_lossy(&first_record);
+        column_names = input_format.columns(record.trim_end_matches(line_ending as char)).join(",");
+        // put the record back, the reader skips it if it is a header
+        source = Some(Box::new(Cursor::new(first_record).chain(reader)));
+    }
+    if !column_names.is_empty() {
+        options.field_names = Some(&column_names);
+    }
+
     //------------------------------------------------------------------------------
     // initialize collector
+    // a key implies that the lines are unique
+    let unique = match opts.values_of("unique").and_then(|vals| vals.last()) {
+        Some(keep) => match UniqueKeep::from_str(keep) {
+            Some(keep) => Some(keep),
+            None => {
+                eprintln!("invalid --unique: {}, expected first or last", keep);
+                return Ok(2);
+            }
+        },
+        None if opts.is_present("unique-key") => Some(UniqueKeep::First),
+        None => None,
+    };
+    // the last lines are only known at the end of the input, which a followed file never reaches
+    if unique == Some(UniqueKeep::Last) && follow.is_some() {
+        eprintln!("--unique=last cannot be used with --follow, the input must end");
+        return Ok(2);
+    }
     let item_reader_option = SkimItemReaderOption::default()
         .ansi(opts.is_present("ansi"))
         .delimiter(opts.values_of("delimiter").and_then(|vals| vals.last()).unwrap_or(""))
+        .input_format(input_format)
+        .field_names(options.field_names.unwrap_or(""))
         .with_nth(opts.values_of("with-nth").and_then(|vals| vals.last()).unwrap_or(""))
         .nth(opts.values_of("nth").and_then(|vals| vals.last()).unwrap_or(""))
         .read0(opts.is_present("read0"))
+        .translit(opts.values_of("translit").and_then(|vals| vals.last()).unwrap_or(""))
         .show_error(opts.is_present("show-cmd-error"))
+        .shell(options.shell.clone())
+        .unique(unique)
+        .unique_key(opts.values_of("unique-key").and_then(|vals| vals.last()).unwrap_or(""))
         .build();
 
     let cmd_collector = Rc::new(RefCell::new(SkimItemReader::new(item_reader_option)));
@@ -311,6 +440,7 @@ fn real_main() -> Result<i32, std::io::Error> {
     //------------------------------------------------------------------------------
     let bin_options = BinOptionsBuilder::default()
         .filter(opts.values_of("filter").and_then(|vals| vals.last()))
+        .filter_limit(opts.values_of("filter-limit").and_then(|vals| vals.last()).and_then(|s| s.parse().ok()))
         .print_query(opts.is_present("print-query"))
         .print_cmd(opts.is_present("print-cmd"))
         .output_ending(if opts.is_present("print0") { "\0" } else { "\n" })
@@ -320,8 +450,16 @@ fn real_main() -> Result<i32, std::io::Error> {
     //------------------------------------------------------------------------------
     // read from pipe or command
 
-    let (rx_item, opt_ingest_handle) = if !std::io::stdin().is_terminal() {
-            let (rx_item, opt_ingest_handle) = cmd_collector.borrow().of_bufread(Box::new(BufReader::with_capacity(READ_BUFFER_CAPACITY, std::io::stdin())));
+    let (rx_item, opt_ingest_handle) = if !sources.is_empty() {
+            match cmd_collector.borrow().of_sources(sources) {
+                Ok(rx_item) => (Some(rx_item), None),
+                Err(err) => {
+                    eprintln!("cannot read source {}", err);
+                    return Ok(2);
+                }
+            }
+        } else if let Some(source) = source {
+            let (rx_item, opt_ingest_handle) = cmd_collector.borrow().of_bufread(source);
             (Some(rx_item),  opt_ingest_handle)
         } else {
          (None, None)
@@ -368,7 +506,8 @@ fn real_main() -> Result<i32, std::io::Error> {
     }
 
     for item in output.selected_items.iter() {
-        write!(stdout, "{}{}", item.output(), bin_options.output_ending)?;
+        stdout.write_all(&item.output_bytes())?;
+        write!(stdout, "{}", bin_options.output_ending)?;
     }
 
     //------------------------------------------------------------------------------
@@ -406,11 +545,24 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
         .no_height(options.is_present("no-height"))
         .height(options.values_of("height").and_then(|vals| vals.last()))
         .margin(options.values_of("margin").and_then(|vals| vals.last()))
-        .preview(options.values_of("preview").and_then(|vals| vals.last()))
+        .preview(
+            options
+                .values_of("preview-argv")
+                .or_else(|| options.values_of("preview"))
+                .and_then(|vals| vals.last()),
+        )
+        .preview_argv(options.is_present("preview-argv"))
         .cmd(options.values_of("cmd").and_then(|vals| vals.last()))
         .query(options.values_of("query").and_then(|vals| vals.last()))
         .cmd_query(options.values_of("cmd-query").and_then(|vals| vals.last()))
         .interactive(options.is_present("interactive"))
+        .cmd_debounce(
+            options
+                .values_of("cmd-debounce")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+        )
         .prompt(options.values_of("prompt").and_then(|vals| vals.last()))
         .cmd_prompt(options.values_of("cmd-prompt").and_then(|vals| vals.last()))
         .bind(
@@ -435,10 +587,31 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
         .tiebreak(options.values_of("tiebreak").map(|x| x.collect::<Vec<_>>().join(",")))
         .tac(options.is_present("tac"))
         .nosort(options.is_present("no-sort"))
+        .sort_by(options.values_of("sort-by").and_then(|vals| vals.last()))
         .exact(options.is_present("exact"))
+        .literal(options.is_present("literal"))
+        .min_score(
+            options
+                .values_of("min-score")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
+        .max_results(
+            options
+                .values_of("max-results")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
+        .tail(
+            options
+                .values_of("tail")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
         .disabled(options.is_present("disabled"))
         .regex(options.is_present("regex"))
         .delimiter(options.values_of("delimiter").and_then(|vals| vals.last()))
+        .field_names(options.values_of("field-names").and_then(|vals| vals.last()))
         .inline_info(options.is_present("inline-info"))
         .header(options.values_of("header").and_then(|vals| vals.last()))
         .header_lines(
@@ -448,6 +621,13 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
                 .map(|s| s.parse::<usize>().unwrap_or(0))
                 .unwrap_or(0),
         )
+        .multi_line(
+            options
+                .values_of("multi-line")
+                .and_then(|vals| vals.last())
+                .map(|s| s.parse::<usize>().unwrap_or(1))
+                .unwrap_or(1),
+        )
         .layout(options.values_of("layout").and_then(|vals| vals.last()).unwrap_or(""))
         .algorithm(match std::env::var("SKIM_ALGORITHM").ok() {
             Some(algo) if options.value_of("algo").is_none() => FuzzyAlgorithm::of(&algo),
@@ -469,6 +649,7 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
         .exit0(options.is_present("exit-0"))
         .sync(options.is_present("sync"))
         .no_clear_if_empty(options.is_present("no-clear-if-empty"))
+        .walker(options.values_of("walker").and_then(|vals| vals.last()))
         .build()
         .unwrap()
 }
@@ -509,6 +690,7 @@ fn write_history_to_file(
 #[derive(Builder)]
 pub struct BinOptions<'a> {
     filter: Option<&'a str>,
+    filter_limit: Option<usize>,
     output_ending: &'a str,
     print_query: bool,
     print_cmd: bool,
@@ -521,10 +703,7 @@ pub fn filter(
 ) -> Result<i32, std::io::Error> {
     let mut stdout = std::io::stdout();
 
-    let default_command = match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
-        Ok("") | Err(_) => "find .".to_owned(),
-        Ok(val) => val.to_owned(),
-    };
+    let default_command = default_command();
     let query = bin_option.filter.unwrap_or("");
     let cmd = options.cmd.unwrap_or(&default_command);
 
@@ -537,41 +716,31 @@ pub fn filter(
         write!(stdout, "{}{}", cmd, bin_option.output_ending)?;
     }
 
-    //------------------------------------------------------------------------------
-    // matcher
-    let engine_factory: Box<dyn MatchEngineFactory> = if options.regex {
-        Box::new(RegexEngineFactory::builder())
-    } else {
-        let fuzzy_engine_factory = ExactOrFuzzyEngineFactory::builder()
-            .fuzzy_algorithm(options.algorithm)
-            .exact_mode(options.exact)
-            .build();
-        Box::new(AndOrEngineFactory::new(Box::new(fuzzy_engine_factory)))
-    };
-
-    let engine = engine_factory.create_engine_with_case(query, options.case);
-
     //------------------------------------------------------------------------------
     // start
     let components_to_stop = Arc::new(AtomicUsize::new(0));
 
     let stream_of_item = source.unwrap_or_else(|| {
         let cmd_collector = options.cmd_collector.clone();
-        let (stream_of_item, _control, _ingest_handle) = cmd_collector.borrow_mut().invoke(cmd, components_to_stop);
+        let mut cmd_collector = cmd_collector.borrow_mut();
+        let walked = default_walker(options)
+            .and_then(|walker| cmd_collector.walk_files(vec![PathBuf::from(".")], walker, components_to_stop.clone()));
+        let (stream_of_item, _control, _ingest_handle) =
+            walked.unwrap_or_else(|| cmd_collector.invoke(cmd, components_to_stop));
         stream_of_item
     });
 
-    let mut num_matched = 0;
-
-    stream_of_item
-        .into_iter()
-        .flatten()
-        .enumerate()
-        .filter_map(|(idx, item)| engine.match_item(item.as_ref(), idx).map(|result| (item, result)))
-        .try_for_each(|(item, _match_result)| {
-            num_matched += 1;
-            write!(stdout, "{}{}", item.output(), bin_option.output_ending)
-        })?;
+    let num_matched = match Skim::filter(options, query, stream_of_item, bin_option.filter_limit, |item| {
+        stdout.write_all(&item.output_bytes())?;
+        write!(stdout, "{}", bin_option.output_ending)
+    }) {
+        Ok(num_matched) => num_matched,
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidInput => {
+            eprintln!("invalid query: {}", err);
+            return Ok(2);
+        }
+        Err(err) => return Err(err),
+    };
 
     Ok(if num_matched == 0 { 1 } else { 0 })
 }
@@ -29,9 +29,12 @@ impl MatchAllEngine {
 
 impl MatchEngine for MatchAllEngine {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
-        let item_len = item.text().len();
+        self.match_text(&item.text(), None, item_idx)
+    }
+
+    fn match_text(&self, text: &str, _ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
         Some(MatchResult {
-            rank: self.rank_builder.build_rank(0, 0, 0, item_len, item_idx),
+            rank: self.rank_builder.build_rank(0, 0, 0, text, item_idx),
             matched_range: MatchRange::ByteRange(0, 0),
         })
     }
@@ -1,5 +1,7 @@
 use std::fmt::{Display, Error, Formatter};
+use std::sync::Arc;
 
+use crate::item::RankBuilder;
 use crate::{MatchEngine, MatchRange, MatchResult, SkimItem};
 
 //------------------------------------------------------------------------------
@@ -27,6 +29,12 @@ impl MatchEngine for OrEngine {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
         self.engines.iter().find_map(|engine| engine.match_item(item, item_idx))
     }
+
+    fn match_text(&self, text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        self.engines
+            .iter()
+            .find_map(|engine| engine.match_text(text, ranges, item_idx))
+    }
 }
 
 impl Display for OrEngine {
@@ -64,11 +72,11 @@ impl AndEngine {
     }
 
     fn merge_matched_items(&self, items: Vec<MatchResult>, text: &str) -> MatchResult {
-        let rank = items[0].rank;
+        let rank = items[0].rank.clone();
         let mut ranges = vec![];
         for item in items {
             match item.matched_range {
-                MatchRange::ByteRange(..) => {
+                MatchRange::ByteRange(..) | MatchRange::Groups(_) => {
                     ranges.extend(item.range_char_indices(text));
                 }
                 MatchRange::Chars(vec) => {
@@ -101,6 +109,20 @@ impl MatchEngine for AndEngine {
             Some(self.merge_matched_items(results, &item.text()))
         }
     }
+
+    fn match_text(&self, text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        let mut results = vec![];
+        for engine in &self.engines {
+            let result = engine.match_text(text, ranges, item_idx)?;
+            results.push(result);
+        }
+
+        if results.is_empty() {
+            None
+        } else {
+            Some(self.merge_matched_items(results, text))
+        }
+    }
 }
 
 impl Display for AndEngine {
@@ -116,3 +138,58 @@ impl Display for AndEngine {
         )
     }
 }
+
+//------------------------------------------------------------------------------
+// NotEngine, a combinator that matches the items the inner engine rejects
+pub struct NotEngine {
+    engine: Box<dyn MatchEngine>,
+    rank_builder: Arc<RankBuilder>,
+}
+
+impl NotEngine {
+    pub fn builder(engine: Box<dyn MatchEngine>) -> Self {
+        Self {
+            engine,
+            rank_builder: Default::default(),
+        }
+    }
+
+    pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
+        self.rank_builder = rank_builder;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    fn not_matched(&self, text: &str, item_idx: usize) -> Option<MatchResult> {
+        Some(MatchResult {
+            rank: self.rank_builder.build_rank(0, 0, 0, text, item_idx),
+            matched_range: MatchRange::ByteRange(0, 0),
+        })
+    }
+}
+
+impl MatchEngine for NotEngine {
+    fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        // the inner engine may match more than the text, e.g. the source of the item
+        match self.engine.match_item(item, item_idx) {
+            Some(_) => None,
+            None => self.not_matched(&item.text(), item_idx),
+        }
+    }
+
+    fn match_text(&self, text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        match self.engine.match_text(text, ranges, item_idx) {
+            Some(_) => None,
+            None => self.not_matched(text, item_idx),
+        }
+    }
+}
+
+impl Display for NotEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(Not: {})", self.engine)
+    }
+}
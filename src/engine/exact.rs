@@ -1,4 +1,4 @@
-use crate::engine::util::{contains_upper, regex_match};
+use crate::engine::util::{FoldedText, contains_upper, fold_diacritics, regex_match};
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine, MatchRange, MatchResult, SkimItem};
 use regex::{escape, Regex};
@@ -13,7 +13,10 @@ pub struct ExactMatchingParam {
     pub prefix: bool,
     pub postfix: bool,
     pub inverse: bool,
+    /// only match occurrences delimited by non-word characters
+    pub word: bool,
     pub case: CaseMatching,
+    pub normalize: bool,
     __non_exhaustive: bool,
 }
 
@@ -24,10 +27,19 @@ pub struct ExactEngine {
     query_regex: Option<Regex>,
     rank_builder: Arc<RankBuilder>,
     inverse: bool,
+    word: bool,
+    normalize: bool,
 }
 
 impl ExactEngine {
     pub fn builder(query: &str, param: ExactMatchingParam) -> Self {
+        let query = if param.normalize {
+            fold_diacritics(query)
+        } else {
+            query.into()
+        };
+        let query = query.as_ref();
+
         let case_sensitive = match param.case {
             CaseMatching::Respect => true,
             CaseMatching::Ignore => false,
@@ -60,6 +72,8 @@ impl ExactEngine {
             query_regex,
             rank_builder: Default::default(),
             inverse: param.inverse,
+            word: param.word,
+            normalize: param.normalize,
         }
     }
 
@@ -71,39 +85,71 @@ impl ExactEngine {
     pub fn build(self) -> Self {
         self
     }
+
+    fn find(&self, text: &str) -> Option<(usize, usize)> {
+        if !self.word {
+            return regex_match(text, &self.query_regex);
+        }
+
+        // the leftmost occurrence delimited by non-word characters, occurrences may overlap
+        let regex = self.query_regex.as_ref()?;
+        let mut from = 0;
+        while let Some(mat) = regex.find_at(text, from) {
+            let before = text[..mat.start()].chars().next_back();
+            let after = text[mat.end()..].chars().next();
+            if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+                return Some((mat.start(), mat.end()));
+            }
+            from = mat.start() + text[mat.start()..].chars().next().map_or(1, char::len_utf8);
+        }
+        None
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
 }
 
 impl MatchEngine for ExactEngine {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
-        let item_text = item.text();
+        self.match_text(&item.text(), item.get_matching_ranges(), item_idx)
+    }
+
+    fn match_text(&self, item_text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
         let item_len = item_text.len();
-        let query_regex = &self.query_regex;
         let default_range = [(0, item_text.len())];
 
-        let matched_result = item
-            .get_matching_ranges()
-            .unwrap_or(&default_range)
-            .iter()
-            .find_map(|(start, end)| {
-                let start = min(*start, item_len);
-                let end = min(*end, item_len);
-                if self.query_regex.is_none() {
-                    return Some((0, 0));
-                }
+        let matched_result = ranges.unwrap_or(&default_range).iter().find_map(|(start, end)| {
+            let start = min(*start, item_len);
+            let end = min(*end, item_len);
+            if self.query_regex.is_none() {
+                return Some((0, 0));
+            }
 
-                let res = regex_match(&item_text[start..end], &query_regex).map(|(s, e)| (s + start, e + start));
+            let choice_range = &item_text[start..end];
+            let folded = if self.normalize {
+                FoldedText::new(choice_range)
+            } else {
+                None
+            };
 
-                if self.inverse {
-                    res.xor(Some((0, 0)))
-                } else {
-                    res
-                }
-            });
+            let res = match folded {
+                Some(folded) => self.find(&folded.text).map(|(s, e)| folded.orig_byte_range(s, e)),
+                None => self.find(choice_range),
+            }
+            .map(|(s, e)| (s + start, e + start));
+
+            if self.inverse {
+                res.xor(Some((0, 0)))
+            } else {
+                res
+            }
+        });
 
         let (begin, end) = matched_result?;
         let score = (end - begin) as i32;
         Some(MatchResult {
-            rank: self.rank_builder.build_rank(score, begin, end, item_len, item_idx),
+            rank: self.rank_builder.build_rank(score, begin, end, item_text, item_idx),
             matched_range: MatchRange::ByteRange(begin, end),
         })
     }
@@ -111,12 +157,20 @@ impl MatchEngine for ExactEngine {
 
 impl Display for ExactEngine {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(f, "(Exact|{}{})", if self.inverse { "!" } else { "" }, {
-            if let Some(regex) = &self.query_regex {
-                regex.as_str()
-            } else {
-                self.query.as_str()
-            }
-        })
+        let word = if self.word { "'" } else { "" };
+        write!(
+            f,
+            "(Exact|{}{}{}{})",
+            if self.inverse { "!" } else { "" },
+            word,
+            {
+                if let Some(regex) = &self.query_regex {
+                    regex.as_str()
+                } else {
+                    self.query.as_str()
+                }
+            },
+            word
+        )
     }
 }
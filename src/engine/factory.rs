@@ -1,16 +1,21 @@
 use crate::engine::all::MatchAllEngine;
-use crate::engine::andor::{AndEngine, OrEngine};
+use crate::engine::andor::{AndEngine, NotEngine, OrEngine};
 use crate::engine::exact::{ExactEngine, ExactMatchingParam};
+use crate::engine::field::FieldEngine;
 use crate::engine::fuzzy::{FuzzyAlgorithm, FuzzyEngine};
+use crate::engine::glob::GlobEngine;
 use crate::engine::regexp::RegexEngine;
+use crate::engine::source::SourceEngine;
+use crate::engine::util::contains_upper;
+use crate::field::FieldRange;
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine, MatchEngineFactory};
 use regex::Regex;
 use std::sync::Arc;
-use std::sync::LazyLock;
 
-static RE_AND: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([^ |]+( +\| +[^ |]*)+)|( +)").unwrap());
-static RE_OR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" +\| +").unwrap());
+const DELIMITER_STR: &str = r"[\t\n ]+";
+// the prefix of the glob terms, e.g. `glob:*.rs`
+const GLOB_PREFIX: &str = "glob:";
 
 //------------------------------------------------------------------------------
 // Exact engine factory
@@ -18,6 +23,7 @@ pub struct ExactOrFuzzyEngineFactory {
     exact_mode: bool,
     fuzzy_algorithm: FuzzyAlgorithm,
     rank_builder: Arc<RankBuilder>,
+    normalize: bool,
 }
 
 impl ExactOrFuzzyEngineFactory {
@@ -26,6 +32,7 @@ impl ExactOrFuzzyEngineFactory {
             exact_mode: false,
             fuzzy_algorithm: FuzzyAlgorithm::default(),
             rank_builder: Default::default(),
+            normalize: false,
         }
     }
 
@@ -44,6 +51,12 @@ impl ExactOrFuzzyEngineFactory {
         self
     }
 
+    /// ignore diacritics, e.g. "resume" matches "résumé"
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
@@ -52,6 +65,8 @@ impl ExactOrFuzzyEngineFactory {
 impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
     fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
         // 'abc => match exact "abc"
+        // 'abc' => match exact "abc" as a whole word
+        // !'abc' => items not containing the word "abc"
         // ^abc => starts with "abc"
         // abc$ => ends with "abc"
         // ^abc$ => match exact "abc"
@@ -63,6 +78,22 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
         let mut exact = false;
         let mut param = ExactMatchingParam::default();
         param.case = case;
+        param.normalize = self.normalize;
+
+        let word_query = query.strip_prefix('!').unwrap_or(query);
+        if let Some(word) = word_query
+            .strip_prefix('\'')
+            .and_then(|word| word.strip_suffix('\''))
+            .filter(|word| !word.is_empty())
+        {
+            param.inverse = word_query.len() != query.len();
+            param.word = true;
+            return Box::new(
+                ExactEngine::builder(word, param)
+                    .rank_builder(self.rank_builder.clone())
+                    .build(),
+            );
+        }
 
         if query.starts_with('\'') {
             if self.exact_mode {
@@ -72,6 +103,7 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
                         .algorithm(self.fuzzy_algorithm)
                         .case(case)
                         .rank_builder(self.rank_builder.clone())
+                        .normalize(self.normalize)
                         .build(),
                 );
             } else {
@@ -124,6 +156,7 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
                     .algorithm(self.fuzzy_algorithm)
                     .case(case)
                     .rank_builder(self.rank_builder.clone())
+                    .normalize(self.normalize)
                     .build(),
             )
         }
@@ -131,68 +164,363 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
 }
 
 //------------------------------------------------------------------------------
+// The extended search syntax:
+//
+//   query := or_expr*                  whitespace means AND
+//   or_expr := unary ('|' unary)*       `|` binds tighter than AND
+//   unary := '(' query ')'             grouping
+//          | '!(' query ')'            negation of a sub-expression
+//          | term                      handled by `create_term`
+//
+// `\ `, `\(` and `\)` are matched literally, in `glob:` terms `\*` and `\?` too. A `)` that
+// closes no group is part of the term.
+//
+// Smart case is decided for every term on its own, `foo\c` ignores case and `foo\C` respects it.
 pub struct AndOrEngineFactory {
     inner: Box<dyn MatchEngineFactory>,
+    delimiter: Regex,
+    field_names: Vec<String>,
+    rank_builder: Arc<RankBuilder>,
 }
 
 impl AndOrEngineFactory {
     pub fn new(factory: Box<dyn MatchEngineFactory>) -> Self {
-        Self { inner: factory }
+        Self {
+            inner: factory,
+            delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            field_names: Vec::new(),
+            rank_builder: Default::default(),
+        }
     }
 
-    // we want to treat `\ ` as plain white space
-    // regex crate doesn't support look around, so I use a LazyLock workaround
-    // that replace `\ ` with `\0` ahead of split and replace it back afterwards
-    fn parse_or(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
-        if query.trim().is_empty() {
-            self.inner.create_engine_with_case(query, case)
-        } else {
-            let engines = RE_OR
-                .split(&self.mask_escape_space(query))
-                .map(|q| self.parse_and(q, case))
-                .collect();
-            Box::new(OrEngine::builder().engines(engines).build())
+    /// the delimiter used to split the text into fields for field scoped terms
+    pub fn delimiter(mut self, delimiter: Regex) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// names of the fields, so that `name:foo` is the same as `N:foo` where `name` is the Nth name
+    pub fn field_names(mut self, field_names: Vec<String>) -> Self {
+        self.field_names = field_names;
+        self
+    }
+
+    /// rank builder of the engines created by the parser itself, e.g. negations
+    pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
+        self.rank_builder = rank_builder;
+        self
+    }
+
+    fn parse(&self, query: &str, case: CaseMatching) -> Result<Box<dyn MatchEngine>, String> {
+        let tokens = tokenize(query);
+        if tokens.is_empty() {
+            return Ok(self.inner.create_engine_with_case(query, case));
+        }
+
+        let mut parser = QueryParser {
+            factory: self,
+            case,
+            tokens,
+            pos: 0,
+        };
+
+        let engine = parser.parse_and()?;
+        match parser.next() {
+            None => Ok(engine),
+            Some(_) => Err("unexpected ')'".to_string()),
         }
     }
 
-    fn parse_and(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
-        let query_trim = query.trim_matches(|c| c == ' ' || c == '|');
-        let mut engines = vec![];
-        let mut last = 0;
-        for mat in RE_AND.find_iter(query_trim) {
-            let (start, end) = (mat.start(), mat.end());
-            let term = query_trim[last..start].trim_matches(|c| c == ' ' || c == '|');
-            let term = self.unmask_escape_space(term);
-            if !term.is_empty() {
-                engines.push(self.inner.create_engine_with_case(&term, case));
+    // !~foo => negate the term as it is, i.e. fuzzy unless quoted
+    // @2:foo => field 2 matches foo, `10:30` is a plain term without `@`
+    // @2..3:foo, @-1:foo => fields as in `--nth`
+    // @name:foo, name:foo => the named field matches foo, `@` is optional if name is known
+    // !@2:foo => field 2 does not contain foo
+    // src:foo, !src:foo => the label of the source of the item matches foo, or does not
+    // foo\c, foo\C => ignore/respect case for this term, whatever the case matching
+    fn create_term(&self, term: &str, case: CaseMatching) -> Result<Box<dyn MatchEngine>, String> {
+        if let Some(rest) = term.strip_suffix("\\c").filter(|rest| !rest.is_empty()) {
+            return self.create_term(rest, CaseMatching::Ignore);
+        }
+
+        if let Some(rest) = term.strip_suffix("\\C").filter(|rest| !rest.is_empty()) {
+            return self.create_term(rest, CaseMatching::Respect);
+        }
+
+        if let Some(rest) = term.strip_prefix("!~").filter(|rest| !rest.is_empty()) {
+            return Ok(self.negate(self.create_term(rest, case)?));
+        }
+
+        let (bang, unbanged) = match term.strip_prefix('!') {
+            Some(rest) => ("!", rest),
+            None => ("", term),
+        };
+
+        if let Some((field, rest)) = unbanged.split_once(':').filter(|(_, rest)| !rest.is_empty()) {
+            // the indices of the fields need the `@`, so that e.g. `10:30` is matched as it is
+            let sigiled = field.strip_prefix('@');
+            let name = sigiled.unwrap_or(field);
+            let opt_field = self
+                .field_names
+                .iter()
+                .position(|field_name| field_name == name)
+                .map(|idx| FieldRange::Single(idx as i32 + 1))
+                .or_else(|| sigiled.and_then(FieldRange::from_str));
+
+            if let Some(field) = opt_field {
+                let engine = self.create_atom(&format!("{}{}", bang, rest), case)?;
+                return Ok(Box::new(
+                    FieldEngine::builder(field, engine)
+                        .delimiter(self.delimiter.clone())
+                        .build(),
+                ));
             }
 
-            if !mat.as_str().trim().is_empty() {
-                engines.push(self.parse_or(mat.as_str().trim(), case));
+            if field == "src" {
+                let engine = self.create_atom(&format!("{}{}", bang, rest), case)?;
+                return Ok(Box::new(
+                    SourceEngine::builder(engine)
+                        .rank_builder(self.rank_builder.clone())
+                        .build(),
+                ));
             }
-            last = end;
         }
 
-        let term = query_trim[last..].trim_matches(|c| c == ' ' || c == '|');
-        let term = self.unmask_escape_space(term);
-        if !term.is_empty() {
-            engines.push(self.inner.create_engine_with_case(&term, case));
-        }
-        Box::new(AndEngine::builder().engines(engines).build())
+        self.create_atom(term, case)
     }
 
-    fn mask_escape_space(&self, string: &str) -> String {
-        string.replace("\\ ", "\0")
+    // /regex/ => regular expression
+    // glob:*.rs, glob:src/?? => glob, matches the whole text. `foo*` is a plain term
+    // !/regex/, !glob:*.rs => negated
+    // the rest is handled by the inner factory
+    fn create_atom(&self, term: &str, case: CaseMatching) -> Result<Box<dyn MatchEngine>, String> {
+        let (inverse, unbanged) = match term.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, term),
+        };
+
+        let regex_pattern = unbanged
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+            .filter(|pattern| !pattern.is_empty());
+
+        // smart case is decided by every term on its own, the letters of the escapes of a regex,
+        // e.g. `\S` or `\B`, are not matched
+        let has_upper = match regex_pattern {
+            Some(pattern) => contains_upper(&strip_escapes(pattern)),
+            None => contains_upper(unbanged),
+        };
+        let case = match case {
+            CaseMatching::Smart if has_upper => CaseMatching::Respect,
+            CaseMatching::Smart => CaseMatching::Ignore,
+            case => case,
+        };
+
+        let engine: Box<dyn MatchEngine> = if let Some(pattern) = regex_pattern {
+            if let Err(err) = Regex::new(pattern) {
+                debug!("invalid regex {}: {}", pattern, err);
+                return Err(format!("invalid regex /{}/", pattern));
+            }
+
+            Box::new(
+                RegexEngine::builder(pattern, case)
+                    .rank_builder(self.rank_builder.clone())
+                    .build(),
+            )
+        } else if let Some(pattern) = unbanged.strip_prefix(GLOB_PREFIX).filter(|pattern| !pattern.is_empty()) {
+            Box::new(
+                GlobEngine::builder(pattern, case)
+                    .rank_builder(self.rank_builder.clone())
+                    .build(),
+            )
+        } else {
+            let term = term.replace("\\(", "(").replace("\\)", ")");
+            return Ok(self.inner.create_engine_with_case(&term, case));
+        };
+
+        if inverse { Ok(self.negate(engine)) } else { Ok(engine) }
     }
 
-    fn unmask_escape_space(&self, string: &str) -> String {
-        string.replace('\0', " ")
+    fn negate(&self, engine: Box<dyn MatchEngine>) -> Box<dyn MatchEngine> {
+        Box::new(
+            NotEngine::builder(engine)
+                .rank_builder(self.rank_builder.clone())
+                .build(),
+        )
     }
 }
 
 impl MatchEngineFactory for AndOrEngineFactory {
     fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
-        self.parse_or(query, case)
+        // an invalid query matches nothing
+        self.parse(query, case)
+            .unwrap_or_else(|_| self.negate(Box::new(MatchAllEngine::builder().build())))
+    }
+
+    fn try_create_engine_with_case(&self, query: &str, case: CaseMatching) -> Result<Box<dyn MatchEngine>, String> {
+        self.parse(query, case)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    NotOpen,
+    Close,
+    Or,
+    Term(String),
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    // split into words of (char, escaped)
+    let mut words: Vec<Vec<(char, bool)>> = vec![];
+    let mut word = vec![];
+    let mut chars = query.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if matches!(chars.peek(), Some(' ' | '(' | ')')) => word.push((chars.next().unwrap(), true)),
+            ' ' if !word.is_empty() => words.push(std::mem::take(&mut word)),
+            ' ' => {}
+            _ => word.push((ch, false)),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut tokens = vec![];
+    let mut depth = 0;
+    for word in words {
+        if word == [('|', false)] {
+            tokens.push(Token::Or);
+            continue;
+        }
+
+        let mut rest = &word[..];
+        loop {
+            match rest {
+                [('(', false), tail @ ..] => {
+                    tokens.push(Token::Open);
+                    rest = tail;
+                }
+                [('!', false), ('(', false), tail @ ..] => {
+                    tokens.push(Token::NotOpen);
+                    rest = tail;
+                }
+                _ => break,
+            }
+            depth += 1;
+        }
+
+        let mut closes = 0;
+        while let [head @ .., (')', false)] = rest {
+            if closes == depth {
+                break;
+            }
+            closes += 1;
+            rest = head;
+        }
+
+        if !rest.is_empty() {
+            // keep escaped parentheses escaped, regex terms need them
+            let term = rest
+                .iter()
+                .map(|&(ch, escaped)| match (ch, escaped) {
+                    ('(' | ')', true) => format!("\\{}", ch),
+                    _ => ch.to_string(),
+                })
+                .collect();
+            tokens.push(Token::Term(term));
+        }
+
+        depth -= closes;
+        tokens.extend((0..closes).map(|_| Token::Close));
+    }
+
+    tokens
+}
+
+// without the backslashes and the chars they escape
+fn strip_escapes(pattern: &str) -> String {
+    let mut stripped = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else {
+            stripped.push(ch);
+        }
+    }
+    stripped
+}
+
+struct QueryParser<'a> {
+    factory: &'a AndOrEngineFactory,
+    case: CaseMatching,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl QueryParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn parse_and(&mut self) -> Result<Box<dyn MatchEngine>, String> {
+        let mut engines = vec![];
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) => break,
+                // a dangling `|`, e.g. while typing
+                Some(Token::Or) => self.pos += 1,
+                _ => engines.push(self.parse_or()?),
+            }
+        }
+
+        Ok(Box::new(AndEngine::builder().engines(engines).build()))
+    }
+
+    fn parse_or(&mut self) -> Result<Box<dyn MatchEngine>, String> {
+        let mut engines = vec![self.parse_unary()?];
+        while let Some(Token::Or) = self.peek() {
+            self.pos += 1;
+            if matches!(self.peek(), Some(Token::Term(_) | Token::Open | Token::NotOpen)) {
+                engines.push(self.parse_unary()?);
+            }
+        }
+
+        if engines.len() == 1 {
+            Ok(engines.pop().unwrap())
+        } else {
+            Ok(Box::new(OrEngine::builder().engines(engines).build()))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Box<dyn MatchEngine>, String> {
+        let (factory, case) = (self.factory, self.case);
+        match self.next() {
+            Some(Token::Term(term)) => factory.create_term(term, case),
+            Some(Token::Open) => self.parse_group(),
+            Some(Token::NotOpen) => Ok(factory.negate(self.parse_group()?)),
+            _ => unreachable!("parse_unary is only called on the start of an expression"),
+        }
+    }
+
+    fn parse_group(&mut self) -> Result<Box<dyn MatchEngine>, String> {
+        if let Some(Token::Close) = self.peek() {
+            return Err("empty group '()'".to_string());
+        }
+
+        let engine = self.parse_and()?;
+        match self.next() {
+            Some(Token::Close) => Ok(engine),
+            _ => Err("missing ')'".to_string()),
+        }
     }
 }
 
@@ -260,13 +588,339 @@ mod test {
         let regex_factory = RegexEngineFactory::builder();
         let and_or_factory = AndOrEngineFactory::new(Box::new(exact_or_fuzzy));
 
+        // `|` binds tighter than AND
         let x = and_or_factory.create_engine("'abc | def ^gh ij | kl mn");
         assert_eq!(
             format!("{}", x),
-            "(Or: (And: (Exact|(?i)abc)), (And: (Fuzzy: def), (Exact|(?i)^gh), (Fuzzy: ij)), (And: (Fuzzy: kl), (Fuzzy: mn)))"
+            "(And: (Or: (Exact|(?i)abc), (Fuzzy: def)), (Exact|(?i)^gh), (Or: (Fuzzy: ij), (Fuzzy: kl)), (Fuzzy: mn))"
         );
 
         let x = regex_factory.create_engine("'abc | def ^gh ij | kl mn");
         assert_eq!(format!("{}", x), "(Regex: 'abc | def ^gh ij | kl mn)");
     }
+
+    #[test]
+    fn test_normalize() {
+        use super::*;
+        use crate::{MatchRange, SkimItem};
+
+        let factory = ExactOrFuzzyEngineFactory::builder().normalize(true).build();
+        let item: &dyn SkimItem = &"Crème brûlée";
+
+        let x = factory.create_engine("creme");
+        let res = x.match_item(item, 0).unwrap();
+        assert!(matches!(res.matched_range, MatchRange::Chars(ref indices) if indices[..] == [0, 1, 2, 3, 4]));
+
+        // byte range of the original text
+        let x = factory.create_engine("'brulee");
+        let res = x.match_item(item, 0).unwrap();
+        assert!(matches!(res.matched_range, MatchRange::ByteRange(7, 15)));
+
+        let x = factory.create_engine("brûlée");
+        assert!(x.match_item(item, 0).is_some());
+
+        let literal = ExactOrFuzzyEngineFactory::builder().build();
+        assert!(literal.create_engine("creme").match_item(item, 0).is_none());
+        assert!(literal.create_engine("crème").match_item(item, 0).is_some());
+    }
+
+    #[test]
+    fn test_word_term() {
+        use super::*;
+        use crate::MatchRange;
+
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+        let x = factory.create_engine("'id' !'tmp'");
+        assert_eq!(format!("{}", x), "(And: (Exact|'(?i)id'), (Exact|!'(?i)tmp'))");
+
+        let exact_or_fuzzy = ExactOrFuzzyEngineFactory::builder().build();
+        let range = |query: &str, text: &'static str| {
+            exact_or_fuzzy
+                .create_engine(query)
+                .match_item(&text, 0)
+                .map(|res| match res.matched_range {
+                    MatchRange::ByteRange(start, end) => (start, end),
+                    _ => unreachable!(),
+                })
+        };
+        assert_eq!(range("'id'", "width idle valid"), None);
+        assert_eq!(range("'id'", "valid user_id, id: 3"), Some((15, 17)));
+        assert_eq!(range("'id'", "id"), Some((0, 2)));
+        assert_eq!(range("'id'", "(ID)"), Some((1, 3)));
+        assert_eq!(range("'a.a'", "xa.a.a"), Some((3, 6)));
+        assert_eq!(range("'é'", "café é"), Some((6, 8)));
+
+        let matches = |query: &str, text: &'static str| factory.create_engine(query).match_item(&text, 0).is_some();
+        assert!(matches("'id' !'tmp'", "id tmpfile"));
+        assert!(!matches("'id' !'tmp'", "id tmp"));
+    }
+
+    #[test]
+    fn test_regex_groups() {
+        use super::*;
+        use crate::MatchRange;
+
+        let factory = RegexEngineFactory::builder();
+        let groups = |query: &str, text: &'static str| {
+            factory
+                .create_engine(query)
+                .match_item(&text, 0)
+                .map(|res| res.matched_range)
+        };
+        assert_eq!(
+            groups(r"(\w+)@(\w+)(\.org)?", "mail: foo@example.com"),
+            Some(MatchRange::Groups([(6, 17), (6, 9), (10, 17), (0, 0)].into()))
+        );
+        assert_eq!(
+            groups(r"\w+@\w+", "mail: foo@example.com"),
+            Some(MatchRange::ByteRange(6, 17))
+        );
+        assert_eq!(groups(r"(x)", "mail"), None);
+    }
+
+    #[test]
+    fn test_translit() {
+        use super::*;
+        use crate::engine::translit::TranslitEngine;
+        use crate::helper::item::DefaultSkimItem;
+        use crate::{MatchRange, SkimItem, Translit};
+
+        let delimiter = regex::Regex::new(r"[\t\n ]+").unwrap();
+        let item = |text: &str, nth: &str| {
+            let matching_fields: Vec<_> = nth.split(',').filter_map(crate::field::FieldRange::from_str).collect();
+            DefaultSkimItem::new(text, false, &[], &matching_fields, &delimiter)
+                .translit(Translit::RU | Translit::PINYIN)
+        };
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+        let indices = |query: &str, item: &DefaultSkimItem| {
+            TranslitEngine::new(factory.create_engine(query))
+                .match_item(item as &dyn SkimItem, 0)
+                .map(|res| match res.matched_range {
+                    MatchRange::Chars(indices) => indices.into_vec(),
+                    _ => unreachable!(),
+                })
+        };
+
+        let moscow = item("Москва.jpg", "");
+        assert_eq!(indices("moskva", &moscow), Some(vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(indices("'kva jpg$", &moscow), Some(vec![3, 4, 5, 7, 8, 9]));
+        assert_eq!(indices("Москва", &moscow), Some(vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(indices("london", &moscow), None);
+
+        let chinese = item("中文 文档", "");
+        assert_eq!(indices("'wendang", &chinese), Some(vec![3, 4]));
+
+        // the matching fields apply to the romanized text
+        let fields = item("Москва Щука", "2");
+        assert_eq!(indices("shchuka", &fields), Some(vec![7, 8, 9, 10]));
+        assert_eq!(indices("moskva", &fields), None);
+    }
+
+    #[test]
+    fn test_field_scoped_terms() {
+        use super::*;
+        use crate::SkimItem;
+
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()))
+            .delimiter(Regex::new("\t").unwrap())
+            .field_names(vec!["user".to_string(), "status".to_string()]);
+
+        let x = factory.create_engine("@2:foo");
+        assert_eq!(format!("{}", x), "(And: (Field Single(2): (Fuzzy: foo)))");
+
+        // the indices of the fields need the `@`
+        let x = factory.create_engine("10:30");
+        assert_eq!(format!("{}", x), "(And: (Fuzzy: 10:30))");
+        let meeting: &dyn SkimItem = &"10:30\tmeeting";
+        assert!(x.match_item(meeting, 0).is_some());
+        let x = factory.create_engine("1:foo");
+        assert_eq!(format!("{}", x), "(And: (Fuzzy: 1:foo))");
+
+        let x = factory.create_engine("http://x");
+        assert_eq!(format!("{}", x), "(And: (Fuzzy: http://x))");
+
+        let alice: &dyn SkimItem = &"alice\tfail";
+        let bob: &dyn SkimItem = &"bob\tok alice";
+
+        let x = factory.create_engine("user:alice status:fail");
+        assert!(x.match_item(alice, 0).is_some());
+        assert!(x.match_item(bob, 1).is_none());
+
+        let x = factory.create_engine("@status:'ok");
+        assert!(x.match_item(alice, 0).is_none());
+        assert!(x.match_item(bob, 1).is_some());
+
+        let x = factory.create_engine("@-1:alice");
+        assert!(x.match_item(alice, 0).is_none());
+        assert!(x.match_item(bob, 1).is_some());
+
+        // field out of range never matches
+        let x = factory.create_engine("@3:a");
+        assert!(x.match_item(alice, 0).is_none());
+    }
+
+    #[test]
+    fn test_source_terms() {
+        use super::*;
+        use crate::SkimItem;
+        use crate::arena::ItemChunk;
+
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+        let recent = ItemChunk::from_lines(&["main.rs"]).with_source("recent".into()).get(0);
+        let unlabeled: &dyn SkimItem = &"main.rs";
+
+        let x = factory.create_engine("src:rec main");
+        assert_eq!(format!("{}", x), "(And: (Source: (Fuzzy: rec)), (Fuzzy: main))");
+        assert!(x.match_item(&recent, 0).is_some());
+        assert!(x.match_item(unlabeled, 1).is_none());
+
+        let x = factory.create_engine("!src:recent");
+        assert!(x.match_item(&recent, 0).is_none());
+        assert!(x.match_item(unlabeled, 1).is_some());
+
+        let x = factory.create_engine("!(src:buffers | src:recent)");
+        assert!(x.match_item(&recent, 0).is_none());
+        assert!(x.match_item(unlabeled, 1).is_some());
+    }
+
+    #[test]
+    fn test_query_grouping() {
+        use super::*;
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+
+        let x = factory.create_engine("(foo | bar) !baz");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (And: (Or: (Fuzzy: foo), (Fuzzy: bar))), (Exact|!(?i)baz))"
+        );
+
+        let x = factory.create_engine("a !(b | ^c) !~d");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (Fuzzy: a), (Not: (And: (Or: (Fuzzy: b), (Exact|(?i)^c)))), (Not: (Fuzzy: d)))"
+        );
+
+        let x = factory.create_engine("( a ( b ) ) | c");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (Or: (And: (Fuzzy: a), (And: (Fuzzy: b))), (Fuzzy: c)))"
+        );
+
+        // escaped or unbalanced parentheses are plain text
+        let x = factory.create_engine("foo() \\(bar\\) a\\ b |");
+        assert_eq!(format!("{}", x), "(And: (Fuzzy: foo()), (Fuzzy: (bar)), (Fuzzy: a b))");
+
+        assert_eq!(
+            factory.try_create_engine_with_case("(foo", CaseMatching::Smart).err(),
+            Some("missing ')'".to_string())
+        );
+        assert_eq!(
+            factory.try_create_engine_with_case("a () b", CaseMatching::Smart).err(),
+            Some("empty group '()'".to_string())
+        );
+        let x = factory.create_engine("(foo");
+        assert!(x.match_item(&"foo", 0).is_none());
+    }
+
+    #[test]
+    fn test_query_matching() {
+        use super::*;
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+        let matches = |query: &str, text: &'static str| factory.create_engine(query).match_item(&text, 0).is_some();
+
+        assert!(matches("readme .md$ | .markdown$", "README.markdown"));
+        assert!(!matches("readme .md$ | .markdown$", "notes.markdown"));
+        assert!(matches("(foo | bar) !baz", "bar.rs"));
+        assert!(!matches("(foo | bar) !baz", "foo/baz.rs"));
+        assert!(matches("src !(test | ^bench)", "src/lib.rs"));
+        assert!(!matches("src !(test | ^bench)", "src/test.rs"));
+        // fuzzy negation
+        assert!(matches("!~fbz", "foo/bar.rs"));
+        assert!(!matches("!~fbz", "foo/baz.rs"));
+    }
+
+    #[test]
+    fn test_case_per_term() {
+        use super::*;
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+        let matches = |query: &str, case: CaseMatching, text: &'static str| {
+            factory
+                .create_engine_with_case(query, case)
+                .match_item(&text, 0)
+                .is_some()
+        };
+
+        // smart case for every term on its own
+        assert!(matches("Makefile build", CaseMatching::Smart, "Makefile BUILD"));
+        assert!(!matches("Makefile build", CaseMatching::Smart, "makefile build"));
+        assert!(matches("'Make | 'build", CaseMatching::Smart, "BUILD"));
+        // the escapes of a regex are not upper case letters
+        assert!(matches("/\\Sfoo/", CaseMatching::Smart, "XFOO"));
+        assert!(matches("/\\bBar\\D/", CaseMatching::Smart, "Bar1 Bar2 Barx"));
+        assert!(!matches("/\\bBar\\D/", CaseMatching::Smart, "bar1 barx"));
+        // not in the other terms
+        assert!(!matches("\\Sfoo", CaseMatching::Smart, "\\sfoo"));
+
+        // overrides of a term
+        assert!(matches("Makefile\\c build", CaseMatching::Smart, "makefile build"));
+        assert!(!matches("make\\C", CaseMatching::Ignore, "Makefile"));
+        assert!(matches("^Make\\C file", CaseMatching::Ignore, "Makefile"));
+        assert!(matches("!'make\\C", CaseMatching::Smart, "Makefile"));
+        assert!(matches("/^MAKE/\\c", CaseMatching::Smart, "Makefile"));
+        assert!(matches("@1:glob:MAKE*\\c", CaseMatching::Smart, "Makefile rules"));
+    }
+
+    #[test]
+    fn test_inline_regex_and_glob() {
+        use super::*;
+        let factory = AndOrEngineFactory::new(Box::new(ExactOrFuzzyEngineFactory::builder().build()));
+
+        let x = factory.create_engine("src/ /_test\\.rs$/ glob:*.rs !glob:*.md");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (Fuzzy: src/), (Regex: (?i)_test\\.rs$), (Glob: *.rs), (Not: (Glob: *.md)))"
+        );
+
+        // spaces and parentheses in a regex
+        let x = factory.create_engine("(/a\\ (b|c)/ | /^D/)");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (And: (Or: (Regex: (?i)a (b|c)), (Regex: ^D))))"
+        );
+
+        // not a regex or glob
+        let x = factory.create_engine("/usr/bin / 'a*b");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (Fuzzy: /usr/bin), (Fuzzy: /), (Exact|(?i)a\\*b))"
+        );
+
+        assert_eq!(
+            factory.try_create_engine_with_case("/a(/", CaseMatching::Smart).err(),
+            Some("invalid regex /a(/".to_string())
+        );
+
+        let matches = |query: &str, text: &'static str| factory.create_engine(query).match_item(&text, 0).is_some();
+        assert!(matches("src/ /_test\\.rs$/", "src/engine_test.rs"));
+        assert!(!matches("src/ /_test\\.rs$/", "src/engine_test.rs.orig"));
+        assert!(matches("glob:*.rs", "src/lib.rs"));
+        assert!(!matches("glob:*.rs", "src/lib.rs.bak"));
+        assert!(matches("glob:src/???.rs", "src/lib.rs"));
+        assert!(!matches("glob:src/???.rs", "src/main.rs"));
+        assert!(matches("glob:what\\?", "what?"));
+        assert!(!matches("glob:what\\?", "whats"));
+
+        // `*` and `?` are plain chars of the other terms
+        let x = factory.create_engine("foo* what? ^src*");
+        assert_eq!(
+            format!("{}", x),
+            "(And: (Fuzzy: foo*), (Fuzzy: what?), (Exact|(?i)^src\\*))"
+        );
+        assert!(matches("foo*", "foo*bar"));
+        assert!(!matches("foo*", "foobar"));
+        assert!(matches("what?", "so what? yes"));
+        assert!(!matches("what?", "whats"));
+        assert!(matches("^src*", "src*.txt"));
+        assert!(!matches("^src*", "src/lib.rs"));
+    }
 }
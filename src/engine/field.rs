@@ -0,0 +1,64 @@
+use std::fmt::{Display, Error, Formatter};
+
+use regex::Regex;
+
+use crate::field::{FieldRange, get_string_by_field};
+use crate::{MatchEngine, MatchResult, SkimItem};
+
+const DELIMITER_STR: &str = r"[\t\n ]+";
+
+//------------------------------------------------------------------------------
+// FieldEngine, restricts another engine to the given fields of the text
+pub struct FieldEngine {
+    field: FieldRange,
+    delimiter: Regex,
+    engine: Box<dyn MatchEngine>,
+}
+
+impl FieldEngine {
+    pub fn builder(field: FieldRange, engine: Box<dyn MatchEngine>) -> Self {
+        Self {
+            field,
+            delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            engine,
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: Regex) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    // byte range of the field, an empty range at the end if the text has no such field
+    fn field_range(&self, text: &str) -> (usize, usize) {
+        match get_string_by_field(&self.delimiter, text, &self.field) {
+            Some(field) => {
+                let start = field.as_ptr() as usize - text.as_ptr() as usize;
+                (start, start + field.len())
+            }
+            None => (text.len(), text.len()),
+        }
+    }
+}
+
+impl MatchEngine for FieldEngine {
+    fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        self.match_text(&item.text(), None, item_idx)
+    }
+
+    fn match_text(&self, text: &str, _ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        // the field overrides the matching ranges (`--nth`) of the item
+        let range = [self.field_range(text)];
+        self.engine.match_text(text, Some(&range), item_idx)
+    }
+}
+
+impl Display for FieldEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(Field {:?}: {})", self.field, self.engine)
+    }
+}
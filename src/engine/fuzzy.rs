@@ -6,6 +6,7 @@ use fuzzy_matcher::simple::SimpleMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 
+use crate::engine::util::{FoldedText, fold_diacritics};
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine};
 use crate::{MatchRange, MatchResult, SkimItem};
@@ -42,6 +43,7 @@ pub struct FuzzyEngineBuilder {
     case: CaseMatching,
     algorithm: FuzzyAlgorithm,
     rank_builder: Arc<RankBuilder>,
+    normalize: bool,
 }
 
 impl FuzzyEngineBuilder {
@@ -65,6 +67,12 @@ impl FuzzyEngineBuilder {
         self
     }
 
+    /// ignore diacritics in both query and items
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
     #[allow(deprecated)]
     pub fn build(self) -> FuzzyEngine {
         let matcher: Box<dyn FuzzyMatcher> = match self.algorithm {
@@ -98,10 +106,17 @@ impl FuzzyEngineBuilder {
             }
         };
 
+        let query = if self.normalize {
+            fold_diacritics(&self.query).into_owned()
+        } else {
+            self.query
+        };
+
         FuzzyEngine {
             matcher,
-            query: self.query,
+            query,
             rank_builder: self.rank_builder,
+            normalize: self.normalize,
         }
     }
 }
@@ -110,6 +125,7 @@ pub struct FuzzyEngine {
     query: String,
     matcher: Box<dyn FuzzyMatcher>,
     rank_builder: Arc<RankBuilder>,
+    normalize: bool,
 }
 
 impl FuzzyEngine {
@@ -124,14 +140,16 @@ impl FuzzyEngine {
 
 impl MatchEngine for FuzzyEngine {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        self.match_text(&item.text(), item.get_matching_ranges(), item_idx)
+    }
+
+    fn match_text(&self, item_text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
         // iterate over all matching fields:
-        let item_text = item.text();
         let item_len = item_text.len();
         let query_text = &self.query;
         let default_range = [(0, item_len)];
 
-        let matched_result: Option<(i64, Vec<usize>)> = item
-            .get_matching_ranges()
+        let matched_result: Option<(i64, Vec<usize>)> = ranges
             .unwrap_or(&default_range)
             .iter()
             .map(|(start, end)| {
@@ -141,24 +159,48 @@ impl MatchEngine for FuzzyEngine {
                 (start, choice_range)
             })
             .find_map(|(start, choice_range)| {
-                self.fuzzy_match(choice_range, query_text).map(|(score, indices)| {
-                    if start != 0 {
-                        let start_char = &item_text[..start].len();
-                        return (score, indices.iter().map(|x| x + start_char).collect());
+                let folded = if self.normalize {
+                    FoldedText::new(choice_range)
+                } else {
+                    None
+                };
+
+                let (score, indices) = match folded {
+                    Some(folded) => {
+                        let (score, indices) = self.fuzzy_match(&folded.text, query_text)?;
+                        let indices = indices.into_iter().map(|idx| folded.orig_char_index(idx)).collect();
+                        (score, indices)
                     }
+                    None => self.fuzzy_match(choice_range, query_text)?,
+                };
+
+                if start != 0 {
+                    let start_char = item_text[..start].chars().count();
+                    return Some((score, indices.iter().map(|x| x + start_char).collect()));
+                }
 
-                    (score, indices)
-                })
+                Some((score, indices))
             });
 
         matched_result.map(|(score, matched_range)| {
-            let begin = *matched_range.first().unwrap_or(&0);
-            let end = *matched_range.last().unwrap_or(&0);
+            // the rank is built from the bytes matched, from the first char to the end of the last one
+            let (begin, end) = match (matched_range.first(), matched_range.last()) {
+                (Some(&first), Some(&last)) => {
+                    let mut offsets = item_text
+                        .char_indices()
+                        .map(|(idx, _)| idx)
+                        .chain(std::iter::once(item_text.len()));
+                    let begin = offsets.nth(first).unwrap_or(item_text.len());
+                    let end = offsets.nth(last.saturating_sub(first)).unwrap_or(item_text.len());
+                    (begin, end)
+                }
+                _ => (0, 0),
+            };
 
             MatchResult {
                 rank: self
                     .rank_builder
-                    .build_rank(score as i32, begin, end, item_len, item_idx),
+                    .build_rank(score as i32, begin, end, item_text, item_idx),
                 matched_range: MatchRange::Chars(matched_range.into()),
             }
         })
@@ -0,0 +1,96 @@
+use std::fmt::{Display, Error, Formatter};
+use std::sync::Arc;
+
+use regex::{Regex, escape};
+
+use crate::engine::util::{contains_upper, regex_match};
+use crate::item::RankBuilder;
+use crate::{CaseMatching, MatchEngine};
+use crate::{MatchRange, MatchResult, SkimItem};
+use std::cmp::min;
+
+//------------------------------------------------------------------------------
+// Glob engine
+//
+// `*` matches any string, `?` matches any character, `\*` and `\?` match literally.
+// The pattern has to match the whole text (or matching range), e.g. `*.rs` or `src/*`.
+#[derive(Debug)]
+pub struct GlobEngine {
+    pattern: String,
+    query_regex: Option<Regex>,
+    rank_builder: Arc<RankBuilder>,
+}
+
+impl GlobEngine {
+    pub fn builder(pattern: &str, case: CaseMatching) -> Self {
+        let case_sensitive = match case {
+            CaseMatching::Respect => true,
+            CaseMatching::Ignore => false,
+            CaseMatching::Smart => contains_upper(pattern),
+        };
+
+        let mut query_builder = String::new();
+        if !case_sensitive {
+            query_builder.push_str("(?i)");
+        }
+
+        query_builder.push('^');
+        let mut chars = pattern.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '*' => query_builder.push_str(".*"),
+                '?' => query_builder.push('.'),
+                '\\' => match chars.next() {
+                    Some(escaped) => query_builder.push_str(&escape(escaped.encode_utf8(&mut [0; 4]))),
+                    None => query_builder.push_str(&escape("\\")),
+                },
+                _ => query_builder.push_str(&escape(ch.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        query_builder.push('$');
+
+        GlobEngine {
+            pattern: pattern.to_string(),
+            query_regex: Regex::new(&query_builder).ok(),
+            rank_builder: Default::default(),
+        }
+    }
+
+    pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
+        self.rank_builder = rank_builder;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+}
+
+impl MatchEngine for GlobEngine {
+    fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        self.match_text(&item.text(), item.get_matching_ranges(), item_idx)
+    }
+
+    fn match_text(&self, item_text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        let item_len = item_text.len();
+        let default_range = [(0, item_len)];
+        let matched_result = ranges.unwrap_or(&default_range).iter().find_map(|(start, end)| {
+            let start = min(*start, item_len);
+            let end = min(*end, item_len);
+            regex_match(&item_text[start..end], &self.query_regex).map(|(s, e)| (s + start, e + start))
+        });
+
+        let (begin, end) = matched_result?;
+        let score = (end - begin) as i32;
+        Some(MatchResult {
+            rank: self.rank_builder.build_rank(score, begin, end, item_text, item_idx),
+            matched_range: MatchRange::ByteRange(begin, end),
+        })
+    }
+}
+
+impl Display for GlobEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(Glob: {})", self.pattern)
+    }
+}
@@ -2,6 +2,10 @@ pub mod all;
 pub mod andor;
 pub mod exact;
 pub mod factory;
+pub mod field;
 pub mod fuzzy;
+pub mod glob;
 pub mod regexp;
+pub mod source;
+pub mod translit;
 mod util;
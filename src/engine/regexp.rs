@@ -47,33 +47,67 @@ impl RegexEngine {
 
 impl MatchEngine for RegexEngine {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
-        let item_text = item.text();
+        self.match_text(&item.text(), item.get_matching_ranges(), item_idx)
+    }
+
+    fn match_text(&self, item_text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        if let Some(regex) = self.query_regex.as_ref().filter(|regex| regex.captures_len() > 1) {
+            return self.match_groups(regex, item_text, ranges, item_idx);
+        }
+
         let default_range = [(0, item_text.len())];
-        let matched_result = item
-            .get_matching_ranges()
-            .unwrap_or(&default_range)
-            .iter()
-            .find_map(|(start, end)| {
-                let start = min(*start, item_text.len());
-                let end = min(*end, item_text.len());
-                if self.query_regex.is_none() {
-                    return Some((0, 0));
-                }
-
-                regex_match(&item_text[start..end], &self.query_regex).map(|(s, e)| (s + start, e + start))
-            });
+        let matched_result = ranges.unwrap_or(&default_range).iter().find_map(|(start, end)| {
+            let start = min(*start, item_text.len());
+            let end = min(*end, item_text.len());
+            if self.query_regex.is_none() {
+                return Some((0, 0));
+            }
+
+            regex_match(&item_text[start..end], &self.query_regex).map(|(s, e)| (s + start, e + start))
+        });
 
         let (begin, end) = matched_result?;
         let score = (end - begin) as i32;
-        let item_len = item_text.len();
 
         Some(MatchResult {
-            rank: self.rank_builder.build_rank(score, begin, end, item_len, item_idx),
+            rank: self.rank_builder.build_rank(score, begin, end, item_text, item_idx),
             matched_range: MatchRange::ByteRange(begin, end),
         })
     }
 }
 
+impl RegexEngine {
+    // same as `match_text`, but also reports where the capture groups matched
+    fn match_groups(
+        &self,
+        regex: &Regex,
+        item_text: &str,
+        ranges: Option<&[(usize, usize)]>,
+        item_idx: usize,
+    ) -> Option<MatchResult> {
+        let default_range = [(0, item_text.len())];
+        let groups: Box<[(usize, usize)]> = ranges.unwrap_or(&default_range).iter().find_map(|(start, end)| {
+            let start = min(*start, item_text.len());
+            let end = min(*end, item_text.len());
+            let captures = regex.captures(&item_text[start..end])?;
+            Some(
+                captures
+                    .iter()
+                    .map(|group| group.map_or((0, 0), |group| (group.start() + start, group.end() + start)))
+                    .collect(),
+            )
+        })?;
+
+        let (begin, end) = groups[0];
+        let score = (end - begin) as i32;
+
+        Some(MatchResult {
+            rank: self.rank_builder.build_rank(score, begin, end, item_text, item_idx),
+            matched_range: MatchRange::Groups(groups),
+        })
+    }
+}
+
 impl Display for RegexEngine {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
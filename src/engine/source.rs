@@ -0,0 +1,58 @@
+use std::fmt::{Display, Error, Formatter};
+use std::sync::Arc;
+
+use crate::item::RankBuilder;
+use crate::{MatchEngine, MatchRange, MatchResult, SkimItem};
+
+//------------------------------------------------------------------------------
+// SourceEngine, matches another engine against the label of the source of the item
+pub struct SourceEngine {
+    engine: Box<dyn MatchEngine>,
+    rank_builder: Arc<RankBuilder>,
+}
+
+impl SourceEngine {
+    pub fn builder(engine: Box<dyn MatchEngine>) -> Self {
+        Self {
+            engine,
+            rank_builder: Default::default(),
+        }
+    }
+
+    pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
+        self.rank_builder = rank_builder;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    // the items without a source have an empty label
+    fn match_source(&self, source: &str, text: &str, item_idx: usize) -> Option<MatchResult> {
+        self.engine.match_text(source, None, item_idx)?;
+
+        // nothing of the text itself is matched
+        Some(MatchResult {
+            rank: self.rank_builder.build_rank(0, 0, 0, text, item_idx),
+            matched_range: MatchRange::ByteRange(0, 0),
+        })
+    }
+}
+
+impl MatchEngine for SourceEngine {
+    fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        self.match_source(item.source().unwrap_or(""), &item.text(), item_idx)
+    }
+
+    // without the item, the source is unknown
+    fn match_text(&self, text: &str, _ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        self.match_source("", text, item_idx)
+    }
+}
+
+impl Display for SourceEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(Source: {})", self.engine)
+    }
+}
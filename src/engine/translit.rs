@@ -0,0 +1,40 @@
+use std::fmt::{Display, Error, Formatter};
+
+use crate::{MatchEngine, MatchRange, MatchResult, SkimItem};
+
+//------------------------------------------------------------------------------
+// TranslitEngine, matches the shadow text of an item (see `--translit`) if its text does not match
+pub struct TranslitEngine {
+    engine: Box<dyn MatchEngine>,
+}
+
+impl TranslitEngine {
+    pub fn new(engine: Box<dyn MatchEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+impl MatchEngine for TranslitEngine {
+    fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult> {
+        self.engine.match_item(item, item_idx).or_else(|| {
+            let shadow = item.shadow_text()?;
+            let result = self
+                .engine
+                .match_text(shadow.text(), shadow.matching_ranges(), item_idx)?;
+            Some(MatchResult {
+                rank: result.rank,
+                matched_range: MatchRange::Chars(shadow.orig_char_indices(&result.matched_range).into()),
+            })
+        })
+    }
+
+    fn match_text(&self, text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        self.engine.match_text(text, ranges, item_idx)
+    }
+}
+
+impl Display for TranslitEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(Translit: {})", self.engine)
+    }
+}
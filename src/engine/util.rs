@@ -1,4 +1,6 @@
 use regex::Regex;
+use std::borrow::Cow;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
 
 pub fn regex_match(choice: &str, pattern: &Option<Regex>) -> Option<(usize, usize)> {
     match *pattern {
@@ -18,3 +20,114 @@ pub fn contains_upper(string: &str) -> bool {
     }
     false
 }
+
+//------------------------------------------------------------------------------
+/// Text with diacritics removed (NFD decomposition, combining marks stripped), so that
+/// "resume" is able to match "résumé".
+///
+/// Every char of the folded text remembers the original char it came from, so that matches
+/// found in the folded text can be highlighted in the original one.
+pub struct FoldedText {
+    pub text: String,
+    // (char index, byte start, byte end) of the original char
+    origins: Vec<(usize, usize, usize)>,
+}
+
+impl FoldedText {
+    /// Returns `None` if folding would leave the text as it is, which is always the case for ASCII.
+    pub fn new(orig: &str) -> Option<Self> {
+        if orig.is_ascii() {
+            return None;
+        }
+
+        let mut text = String::with_capacity(orig.len());
+        let mut origins = Vec::with_capacity(orig.len());
+        let mut decomposed = Vec::with_capacity(4);
+        let mut changed = false;
+
+        for (char_idx, (byte_idx, ch)) in orig.char_indices().enumerate() {
+            decomposed.clear();
+            decompose_canonical(ch, |c| decomposed.push(c));
+
+            let origin = (char_idx, byte_idx, byte_idx + ch.len_utf8());
+            if decomposed.iter().any(|&c| is_combining_mark(c)) {
+                changed = true;
+                for &c in decomposed.iter().filter(|&&c| !is_combining_mark(c)) {
+                    text.push(c);
+                    origins.push(origin);
+                }
+            } else {
+                // keep chars without marks (e.g. Hangul syllables) in one piece
+                text.push(ch);
+                origins.push(origin);
+            }
+        }
+
+        changed.then_some(Self { text, origins })
+    }
+
+    /// index of the original char the `idx`th char of the folded text came from
+    pub fn orig_char_index(&self, idx: usize) -> usize {
+        self.origins.get(idx).map_or(0, |&(char_idx, _, _)| char_idx)
+    }
+
+    /// convert a byte range of the folded text into a byte range of the original text
+    pub fn orig_byte_range(&self, start: usize, end: usize) -> (usize, usize) {
+        if start >= end {
+            let pos = self.orig_byte_start(start);
+            return (pos, pos);
+        }
+
+        let last = self.text[..end].chars().count() - 1;
+        let orig_end = self.origins.get(last).map_or(0, |&(_, _, byte_end)| byte_end);
+        (self.orig_byte_start(start), orig_end)
+    }
+
+    fn orig_byte_start(&self, byte_idx: usize) -> usize {
+        let char_idx = self.text[..byte_idx].chars().count();
+        self.origins.get(char_idx).map_or_else(
+            || self.origins.last().map_or(0, |&(_, _, byte_end)| byte_end),
+            |&(_, byte_start, _)| byte_start,
+        )
+    }
+}
+
+/// fold the diacritics of a query, see `FoldedText`
+pub fn fold_diacritics(query: &str) -> Cow<'_, str> {
+    match FoldedText::new(query) {
+        Some(folded) => Cow::Owned(folded.text),
+        None => Cow::Borrowed(query),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_diacritics() {
+        assert_eq!(fold_diacritics("resume"), "resume");
+        assert_eq!(fold_diacritics("résumé"), "resume");
+        assert_eq!(fold_diacritics("Ærøskøbing Đặng"), "Ærøskøbing Đang");
+        // already decomposed input
+        assert_eq!(fold_diacritics("re\u{301}sume\u{301}"), "resume");
+        // no marks, not touched
+        assert_eq!(fold_diacritics("한국어"), "한국어");
+    }
+
+    #[test]
+    fn test_folded_text_mapping() {
+        let folded = FoldedText::new("résumé").unwrap();
+        assert_eq!(folded.orig_char_index(1), 1);
+        assert_eq!(folded.orig_char_index(5), 5);
+        // "sum" => 3..6 in both
+        assert_eq!(folded.orig_byte_range(2, 5), (3, 6));
+        // "resume" => the whole original text
+        assert_eq!(folded.orig_byte_range(0, 6), (0, 8));
+
+        let folded = FoldedText::new("re\u{301}s").unwrap();
+        assert_eq!(folded.text, "res");
+        assert_eq!(folded.orig_char_index(2), 3);
+        assert_eq!(folded.orig_byte_range(0, 3), (0, 5));
+    }
+}
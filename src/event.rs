@@ -31,7 +31,9 @@ pub enum Event {
     EvActDown(i32),
     EvActEndOfLine,
     EvActExecute(String),
+    EvActExecuteArgv(String),
     EvActExecuteSilent(String),
+    EvActExecuteSilentArgv(String),
     EvActForwardChar,
     EvActForwardWord,
     EvActIfQueryEmpty(String),
@@ -68,6 +70,7 @@ pub enum Event {
     EvActTogglePreview,
     EvActTogglePreviewWrap,
     EvActToggleSort,
+    EvActToggleTac,
     EvActUnixLineDiscard,
     EvActUnixWordRubout,
     EvActUp(i32),
@@ -109,7 +112,9 @@ pub fn parse_event(action: &str, arg: Option<String>) -> Option<Event> {
         "down"                 =>   Some(Event::EvActDown(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "end-of-line"          =>   Some(Event::EvActEndOfLine),
         "execute"              =>   Some(Event::EvActExecute(arg.expect("execute event should have argument"))),
+        "execute-argv"         =>   Some(Event::EvActExecuteArgv(arg.expect("execute-argv event should have argument"))),
         "execute-silent"       =>   Some(Event::EvActExecuteSilent(arg.expect("execute-silent event should have argument"))),
+        "execute-silent-argv"  =>   Some(Event::EvActExecuteSilentArgv(arg.expect("execute-silent-argv event should have argument"))),
         "forward-char"         =>   Some(Event::EvActForwardChar),
         "forward-word"         =>   Some(Event::EvActForwardWord),
         "if-non-matched"       =>   Some(Event::EvActIfNonMatched(arg.expect("no arg specified for event if-non-matched"))),
@@ -143,6 +148,7 @@ pub fn parse_event(action: &str, arg: Option<String>) -> Option<Event> {
         "toggle-preview"       =>   Some(Event::EvActTogglePreview),
         "toggle-preview-wrap"  =>   Some(Event::EvActTogglePreviewWrap),
         "toggle-sort"          =>   Some(Event::EvActToggleSort),
+        "toggle-tac"           =>   Some(Event::EvActToggleTac),
         "unix-line-discard"    =>   Some(Event::EvActUnixLineDiscard),
         "unix-word-rubout"     =>   Some(Event::EvActUnixWordRubout),
         "up"                   =>   Some(Event::EvActUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
@@ -46,6 +46,12 @@ impl FieldRange {
         }
     }
 
+    /// like `from_str`, where a field can also be one of `field_names`, e.g. `name..` is `N..` if
+    /// `name` is the Nth name
+    pub fn from_spec(range: &str, field_names: &[&str]) -> Option<FieldRange> {
+        FieldRange::from_str(&index_field_names(range, field_names))
+    }
+
     // Parse FieldRange to index pair (left, right)
     // e.g. 1..3 => (0, 4)
     // note that field range is inclusive while the output index will exclude right end
@@ -97,6 +103,20 @@ impl FieldRange {
     }
 }
 
+/// the range with the names of the fields replaced by their indices, e.g. `name..` => `2..` if
+/// `name` is the second of `field_names`
+pub fn index_field_names(range: &str, field_names: &[&str]) -> String {
+    let index = |field: &str| match field_names.iter().position(|name| *name == field) {
+        Some(idx) if !field.is_empty() => (idx + 1).to_string(),
+        _ => field.to_string(),
+    };
+
+    match range.split_once("..") {
+        Some((left, right)) => format!("{}..{}", index(left), index(right)),
+        None => index(range),
+    }
+}
+
 // ("|", "a|b||c") -> [(0, 2), (2, 4), (4, 5), (5, 6)]
 // explain: split to ["a|", "b|", "|", "c"]
 fn get_ranges_by_delimiter(delimiter: &Regex, text: &str) -> Vec<(usize, usize)> {
@@ -182,6 +202,18 @@ mod test {
         assert_eq!(FieldRange::from_str("a..b"), None);
     }
 
+    #[test]
+    fn test_parse_named_range() {
+        let names = ["user", "status"];
+        assert_eq!(FieldRange::from_spec("status", &names), Some(Single(2)));
+        assert_eq!(FieldRange::from_spec("user..status", &names), Some(Both(1, 2)));
+        assert_eq!(FieldRange::from_spec("status..", &names), Some(RightInf(2)));
+        assert_eq!(FieldRange::from_spec("..-1", &names), Some(LeftInf(-1)));
+        assert_eq!(FieldRange::from_spec("3", &names), Some(Single(3)));
+        assert_eq!(FieldRange::from_spec("host", &names), None);
+        assert_eq!(FieldRange::from_spec("..2", &["", "user"]), Some(LeftInf(2)));
+    }
+
     use regex::Regex;
 
     #[test]
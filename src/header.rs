@@ -124,31 +124,26 @@ impl Draw for Header {
         let lines_used = self.header.len();
 
         // print "reserved" header lines (--header-lines)
-        self.upgrade()
-            .reserved()
-            .iter()
-            .filter_map(Weak::upgrade)
-            .enumerate()
-            .for_each(|(idx, item)| {
-                let mut printer = LinePrinter::builder()
-                    .row(self.adjust_row(idx + lines_used, screen_height))
-                    .col(2)
-                    .tabstop(self.tabstop)
-                    .container_width(screen_width - 2)
-                    .shift(0)
-                    .text_width(screen_width - 2)
-                    .build();
-
-                let context = DisplayContext {
-                    text: &item.text(),
-                    score: 0,
-                    matches: None,
-                    container_width: screen_width - 2,
-                    highlight_attr: self.theme.header(),
-                };
-
-                print_item(canvas, &mut printer, item.display(context), self.theme.header());
-            });
+        self.upgrade().reserved().iter().enumerate().for_each(|(idx, item)| {
+            let mut printer = LinePrinter::builder()
+                .row(self.adjust_row(idx + lines_used, screen_height))
+                .col(2)
+                .tabstop(self.tabstop)
+                .container_width(screen_width - 2)
+                .shift(0)
+                .text_width(screen_width - 2)
+                .build();
+
+            let context = DisplayContext {
+                text: &item.text(),
+                score: 0,
+                matches: None,
+                container_width: screen_width - 2,
+                highlight_attr: self.theme.header(),
+            };
+
+            print_item(canvas, &mut printer, item.display(context), self.theme.header());
+        });
 
         Ok(())
     }
@@ -0,0 +1,116 @@
+/// helper for following a file as it grows, like `tail -F`
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+// how long to wait for more data at the end of the file
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Reads a file to its end, then waits for more lines to be appended. The file is read again from
+/// the beginning if it is truncated, or if it is replaced by a new one, e.g. by log rotation.
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    id: u64,
+    pos: u64,
+}
+
+impl FollowReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let id = file_id(&file.metadata()?);
+        Ok(Self { path, file, id, pos: 0 })
+    }
+
+    // called at the end of the file, `false` if there is nothing new to read yet
+    fn reopen_if_changed(&mut self) -> io::Result<bool> {
+        // the file may be missing for a moment while it is rotated
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(false);
+        };
+
+        if file_id(&metadata) != self.id {
+            // the rest of the old file was read already
+            self.file = File::open(&self.path)?;
+            self.id = file_id(&self.file.metadata()?);
+            self.pos = 0;
+            return Ok(true);
+        }
+
+        if metadata.len() < self.pos {
+            self.pos = self.file.seek(SeekFrom::Start(0))?;
+            return Ok(true);
+        }
+
+        Ok(metadata.len() > self.pos)
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.file.read(buf)?;
+            if len > 0 || buf.is_empty() {
+                self.pos += len as u64;
+                return Ok(len);
+            }
+
+            if !self.reopen_if_changed()? {
+                sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+// without inodes, only the truncation of the file is noticed
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn read_some(reader: &mut FollowReader) -> String {
+        let mut buf = [0u8; 64];
+        let len = reader.read(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..len]).to_string()
+    }
+
+    #[test]
+    fn test_follow_truncated_and_rotated() {
+        let dir = std::env::temp_dir().join(format!("sk-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        fs::write(&path, "a\nb\n").unwrap();
+
+        let mut reader = FollowReader::open(&path).unwrap();
+        assert_eq!(read_some(&mut reader), "a\nb\n");
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"c\n").unwrap();
+        assert_eq!(read_some(&mut reader), "c\n");
+
+        // truncated
+        fs::write(&path, "d\n").unwrap();
+        assert_eq!(read_some(&mut reader), "d\n");
+
+        // rotated
+        fs::rename(&path, dir.join("log.1")).unwrap();
+        fs::write(&path, "e\n").unwrap();
+        assert_eq!(read_some(&mut reader), "e\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
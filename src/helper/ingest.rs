@@ -1,14 +1,18 @@
+use crate::ansi::ANSIParser;
 use crate::field::FieldRange;
-use crate::SkimItem;
+use crate::{SkimItem, Translit};
 /// helper for turn a BufRead into a skim stream
-use std::io::BufRead;
+use std::io::{BufRead, Read};
 use std::sync::{Arc, LazyLock};
 
 use crossbeam_channel::Sender;
 use regex::Regex;
 use std::io::ErrorKind;
 
-use super::item::DefaultSkimItem;
+use super::item::{BytesItem, DefaultSkimItem, RecordItem};
+use super::record::RecordParser;
+use super::unique::{Unique, UniqueKeep};
+use crate::arena::ItemChunkBuilder;
 
 #[derive(Clone)]
 pub enum SendRawOrBuild<'a> {
@@ -22,133 +26,193 @@ pub struct BuildOptions<'a> {
     pub trans_fields: &'a [FieldRange],
     pub matching_fields: &'a [FieldRange],
     pub delimiter: &'a Regex,
+    pub translit: Translit,
+    // for structured input, e.g. JSON lines
+    pub record_parser: Option<&'a RecordParser>,
 }
 
-#[allow(unused_assignments)]
+/// read the lines of `source` into items, without the lines whose key is not new if `unique`
 pub fn ingest_loop(
     mut source: Box<dyn BufRead + Send>,
     line_ending: u8,
     tx_item: &Sender<Vec<Arc<dyn SkimItem>>>,
     opts: &SendRawOrBuild,
+    unique: Option<&Unique>,
 ) {
-    let mut frag_buffer = String::with_capacity(128);
+    if let Some(unique) = unique.filter(|unique| unique.keep() == UniqueKeep::Last) {
+        ingest_keep_last(source, line_ending, tx_item, opts, unique);
+        return;
+    }
+
+    // the beginning of a line that continues in the next buffer
+    let mut frag_buffer: Vec<u8> = Vec::with_capacity(128);
 
-    'outer: loop {
+    loop {
         // first, read lots of bytes into the buffer
-        match source.fill_buf() {
+        let bytes_buffer = match source.fill_buf() {
             Ok(bytes_buffer) if bytes_buffer.is_empty() => break,
-            Ok(ref mut bytes_buffer) => {
-                let buffer_len = bytes_buffer.len();
-
-                'inner: loop {
-                    match std::str::from_utf8(bytes_buffer) {
-                        Ok(string) => {
-                            let _ = process(string, &mut frag_buffer, line_ending, &tx_item, &opts);
-                            break 'inner;
-                        }
-                        Err(err) => {
-                            debug!("Removing bytes which are invalid UTF8: {:?}", err);
-
-                            let (valid, after_valid) = bytes_buffer.split_at(err.valid_up_to());
-                            let pre_checked = unsafe { std::str::from_utf8_unchecked(valid) };
-
-                            if process(pre_checked, &mut frag_buffer, line_ending, &tx_item, &opts).is_err() {
-                                break 'inner;
-                            }
-
-                            if let Some(invalid_sequence_length) = err.error_len() {
-                                *bytes_buffer = &after_valid[invalid_sequence_length..];
-                            }
-
-                            continue 'inner;
-                        }
-                    };
-                }
-
-                source.consume(buffer_len);
-            }
-            Err(err) => match err.kind() {
-                ErrorKind::Interrupted => continue 'outer,
-                ErrorKind::UnexpectedEof | _ => {
-                    break 'outer;
-                }
-            },
+            Ok(bytes_buffer) => bytes_buffer,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let buffer_len = bytes_buffer.len();
+
+        let Some(last) = bytes_buffer.iter().rposition(|&byte| byte == line_ending) else {
+            frag_buffer.extend_from_slice(bytes_buffer);
+            source.consume(buffer_len);
+            continue;
+        };
+        let first = bytes_buffer
+            .iter()
+            .position(|&byte| byte == line_ending)
+            .unwrap_or(last);
+
+        // the first line started in the previous buffers
+        frag_buffer.extend_from_slice(&bytes_buffer[..first]);
+        let mut items = Vec::new();
+        if is_new(&frag_buffer, opts, unique) {
+            items.push(into_skim_item_bytes(&frag_buffer, opts));
+        }
+        frag_buffer.clear();
+
+        if first < last {
+            items.append(&mut into_skim_items_bytes(
+                &bytes_buffer[first + 1..last],
+                line_ending,
+                opts,
+                unique,
+            ));
+        }
+
+        frag_buffer.extend_from_slice(&bytes_buffer[last + 1..]);
+        source.consume(buffer_len);
+
+        if send(items, tx_item).is_err() {
+            return;
         }
     }
 
-    if !frag_buffer.is_empty() {
-        let items = vec![into_skim_item(&frag_buffer, opts)];
+    if !frag_buffer.is_empty() && is_new(&frag_buffer, opts, unique) {
+        let items = vec![into_skim_item_bytes(&frag_buffer, opts)];
         let _ = send(items, tx_item);
     }
 }
 
-fn process(
-    base: &str,
-    mut frag_buffer: &mut String,
+// the last of the lines with the same key is only known at the end of the input, the whole input
+// is read in memory first, nothing is sent before. A source that never ends (e.g. a followed
+// file) is not supported.
+fn ingest_keep_last(
+    mut source: Box<dyn BufRead + Send>,
     line_ending: u8,
     tx_item: &Sender<Vec<Arc<dyn SkimItem>>>,
     opts: &SendRawOrBuild,
-) -> Result<(), crossbeam_channel::SendError<Vec<Arc<dyn SkimItem>>>> {
-    let vec = match base.rsplit_once(line_ending as char) {
-        Some((main, frag)) => {
-            let buffer = if !frag_buffer.is_empty() {
-                match main.split_once(line_ending as char) {
-                    Some((first, rest)) => {
-                        stitch(&mut frag_buffer, first, line_ending, &opts, &tx_item)?;
-                        rest
-                    }
-                    None => {
-                        stitch(&mut frag_buffer, main, line_ending, &opts, &tx_item)?;
-                        return Ok(());
-                    }
-                }
-            } else {
-                main
-            };
+    unique: &Unique,
+) {
+    let mut buffer = Vec::new();
+    // the lines read before an error are kept
+    let _ = source.read_to_end(&mut buffer);
+    if buffer.is_empty() {
+        return;
+    }
 
-            // we have cleared the frag buffer by this point we can append a new string
-            frag_buffer.push_str(frag);
+    let buffer = buffer.strip_suffix(&[line_ending]).unwrap_or(&buffer);
+    let lines: Vec<&[u8]> = buffer.split(|&byte| byte == line_ending).collect();
+    let mut is_kept = vec![false; lines.len()];
+    let mut seen = unique.lock();
+    for (idx, line) in lines.iter().enumerate().rev() {
+        is_kept[idx] = seen.insert(line, record_parser(opts));
+    }
+    drop(seen);
 
-            buffer
-                .split(line_ending as char)
-                .map(|line| into_skim_item(line, &opts))
-                .into_iter()
-                .collect()
-        }
-        None if !frag_buffer.is_empty() => {
-            stitch(&mut frag_buffer, base, line_ending, &opts, &tx_item)?;
-            return Ok(());
-        }
-        None => {
-            vec![into_skim_item(base, &opts)]
-        }
+    let kept_lines = lines
+        .into_iter()
+        .zip(is_kept)
+        .filter_map(|(line, kept)| kept.then_some(line));
+    let _ = send(lines_into_items(kept_lines, opts), tx_item);
+}
+
+fn record_parser<'a>(opts: &SendRawOrBuild<'a>) -> Option<&'a RecordParser> {
+    match opts {
+        SendRawOrBuild::Raw => None,
+        SendRawOrBuild::Build(opts) => opts.record_parser,
+    }
+}
+
+fn is_new(line: &[u8], opts: &SendRawOrBuild, unique: Option<&Unique>) -> bool {
+    unique.is_none_or(|unique| unique.lock().insert(line, record_parser(opts)))
+}
+
+// the lines that are not valid UTF-8 are kept as they are, to be output byte for byte
+fn into_skim_item_bytes(line: &[u8], opts: &SendRawOrBuild) -> Arc<dyn SkimItem> {
+    let err = match std::str::from_utf8(line) {
+        Ok(line) => return into_skim_item(line, opts),
+        Err(err) => err,
     };
 
-    send(vec, tx_item)
+    debug!("Escaping bytes which are invalid UTF8: {:?}", err);
+    let escaped = BytesItem::escape(line);
+    let item = into_skim_item(&escaped, opts);
+    if item.output() == escaped {
+        return Arc::new(BytesItem::new(item, line));
+    }
+
+    // the output is the line with the ANSI codes stripped
+    let output = ANSIParser::default().strip_ansi_bytes(line);
+    Arc::new(BytesItem::new(item, &output))
 }
 
-fn stitch(
-    old: &mut String,
-    new: &str,
+fn into_skim_items_bytes(
+    buffer: &[u8],
     line_ending: u8,
     opts: &SendRawOrBuild,
-    tx_item: &Sender<Vec<Arc<dyn SkimItem>>>,
-) -> Result<(), crossbeam_channel::SendError<Vec<Arc<dyn SkimItem>>>> {
-    if !new.starts_with(line_ending as char) {
-        old.push_str(new);
-        let items = vec![into_skim_item(old, opts)];
-        send(items, tx_item)?;
-        old.clear();
-        return Ok(());
-    }
-
-    let items = [&old, new].iter().map(|line| into_skim_item(line, opts)).collect();
+    unique: Option<&Unique>,
+) -> Vec<Arc<dyn SkimItem>> {
+    let mut items = Vec::new();
+    let mut rest = buffer;
+    loop {
+        let err = match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                items.append(&mut into_skim_items(
+                    valid.split(line_ending as char),
+                    valid.len(),
+                    opts,
+                    unique,
+                ));
+                return items;
+            }
+            Err(err) => err,
+        };
 
-    send(items, tx_item)?;
+        // the valid lines before the invalid one
+        let valid_up_to = err.valid_up_to();
+        let line_start = match rest[..valid_up_to].iter().rposition(|&byte| byte == line_ending) {
+            Some(idx) => {
+                // before `valid_up_to`
+                let valid = unsafe { std::str::from_utf8_unchecked(&rest[..idx]) };
+                items.append(&mut into_skim_items(
+                    valid.split(line_ending as char),
+                    valid.len(),
+                    opts,
+                    unique,
+                ));
+                idx + 1
+            }
+            None => 0,
+        };
 
-    old.clear();
+        let line_end = rest[valid_up_to..]
+            .iter()
+            .position(|&byte| byte == line_ending)
+            .map_or(rest.len(), |idx| valid_up_to + idx);
+        if is_new(&rest[line_start..line_end], opts, unique) {
+            items.push(into_skim_item_bytes(&rest[line_start..line_end], opts));
+        }
 
-    Ok(())
+        if line_end == rest.len() {
+            return items;
+        }
+        rest = &rest[line_end + 1..];
+    }
 }
 
 static EMPTY_STRING: &str = "";
@@ -156,16 +220,13 @@ static ARC_EMPTY_STRING: LazyLock<Arc<Box<str>>> = LazyLock::new(|| Arc::new(EMP
 
 fn into_skim_item(line: &str, opts: &SendRawOrBuild) -> Arc<dyn SkimItem> {
     match opts {
-        SendRawOrBuild::Build(opts) => {
-            let item = DefaultSkimItem::new(
-                line,
-                opts.ansi_enabled,
-                opts.trans_fields,
-                opts.matching_fields,
-                opts.delimiter,
-            );
-            Arc::new(item)
-        }
+        SendRawOrBuild::Build(opts) => match opts.record_parser.and_then(|parser| parser.fields(line)) {
+            Some(fields) => {
+                let text = RecordParser::join(&fields);
+                Arc::new(RecordItem::new(line, build_item(&text, opts)))
+            }
+            None => Arc::new(build_item(line, opts)),
+        },
         SendRawOrBuild::Raw if line.is_empty() => ARC_EMPTY_STRING.clone(),
         SendRawOrBuild::Raw => {
             let item: Box<str> = line.into();
@@ -174,9 +235,209 @@ fn into_skim_item(line: &str, opts: &SendRawOrBuild) -> Arc<dyn SkimItem> {
     }
 }
 
+fn build_item(line: &str, opts: &BuildOptions) -> DefaultSkimItem {
+    DefaultSkimItem::new(
+        line,
+        opts.ansi_enabled,
+        opts.trans_fields,
+        opts.matching_fields,
+        opts.delimiter,
+    )
+    .translit(opts.translit)
+}
+
+/// the items of lines that are split already, e.g. the paths found by a directory walk
+pub fn lines_into_items<'a>(
+    lines: impl IntoIterator<Item = &'a [u8]>,
+    opts: &SendRawOrBuild,
+) -> Vec<Arc<dyn SkimItem>> {
+    let mut items = Vec::new();
+    // the valid lines since the last invalid one
+    let mut valid: Vec<&str> = Vec::new();
+    for line in lines {
+        match std::str::from_utf8(line) {
+            Ok(line) => valid.push(line),
+            Err(_) => {
+                let capacity = valid.iter().map(|line| line.len()).sum();
+                items.append(&mut into_skim_items(valid.drain(..), capacity, opts, None));
+                items.push(into_skim_item_bytes(line, opts));
+            }
+        }
+    }
+
+    let capacity = valid.iter().map(|line| line.len()).sum();
+    items.append(&mut into_skim_items(valid.into_iter(), capacity, opts, None));
+    items
+}
+
+// plain text lines are packed into `ItemChunk`s, the other lines become standalone items
+fn into_skim_items<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    capacity: usize,
+    opts: &SendRawOrBuild,
+    unique: Option<&Unique>,
+) -> Vec<Arc<dyn SkimItem>> {
+    let mut items: Vec<Arc<dyn SkimItem>> = Vec::new();
+    let mut chunk = ItemChunkBuilder::with_capacity(capacity);
+    // the bytes of the lines after the current one, for the chunks that follow an interruption
+    let mut remaining = capacity;
+    // locked once for all the lines
+    let mut seen = unique.map(Unique::lock);
+
+    for line in lines {
+        remaining = remaining.saturating_sub(line.len());
+        if !seen
+            .as_mut()
+            .is_none_or(|seen| seen.insert(line.as_bytes(), record_parser(opts)))
+        {
+            continue;
+        }
+
+        if is_plain(line, opts) {
+            if chunk.push(line) {
+                continue;
+            }
+
+            // the chunk is full
+            let full_chunk = std::mem::replace(&mut chunk, ItemChunkBuilder::with_capacity(line.len() + remaining));
+            items.push(Arc::new(full_chunk.build()));
+            if chunk.push(line) {
+                continue;
+            }
+        }
+
+        if !chunk.is_empty() {
+            let plain_lines = std::mem::replace(&mut chunk, ItemChunkBuilder::with_capacity(remaining));
+            items.push(Arc::new(plain_lines.build()));
+        }
+        items.push(into_skim_item(line, opts));
+    }
+
+    if !chunk.is_empty() {
+        items.push(Arc::new(chunk.build()));
+    }
+
+    items
+}
+
+// whether the line is the same as the text of the item that would be built for it
+fn is_plain(line: &str, opts: &SendRawOrBuild) -> bool {
+    match opts {
+        SendRawOrBuild::Raw => true,
+        SendRawOrBuild::Build(opts) => {
+            !opts.ansi_enabled
+                && opts.record_parser.is_none()
+                && opts.trans_fields.is_empty()
+                && opts.matching_fields.is_empty()
+                && !opts.translit.applies_to(line)
+                // the ANSI parser drops escape sequences and most control characters
+                && line.chars().all(|ch| !ch.is_control() || matches!(ch, '\0' | '\t' | '\r'))
+        }
+    }
+}
+
 fn send(
     vec: Vec<Arc<dyn SkimItem>>,
     tx_item: &Sender<Vec<Arc<dyn SkimItem>>>,
 ) -> Result<(), crossbeam_channel::SendError<Vec<Arc<dyn SkimItem>>>> {
     tx_item.send(vec)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor};
+
+    fn ingest(input: &[u8], capacity: usize, unique: Option<&Unique>) -> Vec<Arc<dyn SkimItem>> {
+        ingest_with(input, capacity, &SendRawOrBuild::Raw, unique)
+    }
+
+    fn ingest_with(
+        input: &[u8],
+        capacity: usize,
+        opts: &SendRawOrBuild,
+        unique: Option<&Unique>,
+    ) -> Vec<Arc<dyn SkimItem>> {
+        let (tx_item, rx_item) = crossbeam_channel::unbounded();
+        let source = Box::new(BufReader::with_capacity(capacity, Cursor::new(input.to_vec())));
+        ingest_loop(source, b'\n', &tx_item, opts, unique);
+        drop(tx_item);
+        rx_item.iter().flat_map(crate::ItemChunk::unpack).collect()
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let input = b"caf\xe9\nok\n\nna\xefve\xff\nlast";
+        for capacity in [3, 5, 64] {
+            let items = ingest(input, capacity, None);
+            let texts: Vec<_> = items.iter().map(|item| item.text().into_owned()).collect();
+            assert_eq!(texts, ["caf\\xE9", "ok", "", "na\\xEFve\\xFF", "last"]);
+
+            let output: Vec<_> = items.iter().map(|item| item.output_bytes().into_owned()).collect();
+            assert_eq!(output.join(&b'\n'), input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8_built() {
+        let input = b"\x1b[31mcaf\xe9\x1b[0m x\nna\xefve";
+        let delimiter = Regex::new(r"[\t\n ]+").unwrap();
+        let build = |ansi_enabled, trans_fields| {
+            SendRawOrBuild::Build(BuildOptions {
+                ansi_enabled,
+                trans_fields,
+                matching_fields: &[],
+                delimiter: &delimiter,
+                translit: Translit::default(),
+                record_parser: None,
+            })
+        };
+        let output = |opts: &SendRawOrBuild| {
+            ingest_with(input, 64, opts, None)
+                .iter()
+                .map(|item| (item.text().into_owned(), item.output_bytes().into_owned()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            output(&build(true, &[])),
+            [
+                ("caf\\xE9 x".to_string(), b"caf\xe9 x".to_vec()),
+                ("na\\xEFve".to_string(), b"na\xefve".to_vec()),
+            ]
+        );
+        // the fields shown, the line output
+        let first_field = [FieldRange::Single(1)];
+        assert_eq!(
+            output(&build(false, &first_field)),
+            [
+                (
+                    "\x1b[31mcaf\\xE9\x1b[0m ".to_string(),
+                    b"\x1b[31mcaf\xe9\x1b[0m x".to_vec()
+                ),
+                ("na\\xEFve".to_string(), b"na\xefve".to_vec()),
+            ]
+        );
+        assert_eq!(
+            output(&build(true, &first_field))[0],
+            ("caf\\xE9 ".to_string(), b"caf\xe9 x".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_unique() {
+        let input = b"b\na\nb\nc\xff\nc\xff\na\nd";
+        let delimiter = Regex::new(r"[\t\n ]+").unwrap();
+        for capacity in [3, 64] {
+            let texts = |keep| {
+                let unique = Unique::new(keep, &[], &delimiter);
+                ingest(input, capacity, Some(&unique))
+                    .iter()
+                    .map(|item| item.text().into_owned())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(texts(UniqueKeep::First), ["b", "a", "c\\xFF", "d"]);
+            assert_eq!(texts(UniqueKeep::Last), ["b", "c\\xFF", "a", "d"]);
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use crate::ansi::ANSIParser;
 use crate::field::{FieldRange, parse_matching_fields, parse_transform_fields};
-use crate::{AnsiString, DisplayContext, Matches, SkimItem};
+use crate::{
+    AnsiString, DisplayContext, ItemPreview, Matches, PreviewContext, ShadowText, SkimItem, Translit, char_ranges,
+};
 use regex::Regex;
 use std::borrow::Cow;
+use std::sync::Arc;
 use tuikit::prelude::Attr;
 
 //------------------------------------------------------------------------------
@@ -28,6 +31,9 @@ pub struct DefaultSkimItem {
 
     // Option<Box<_>> to reduce memory use in normal cases where no matching ranges are specified.
     matching_ranges: Option<Box<[(usize, usize)]>>,
+
+    // romanized text, only for the items with chars to romanize
+    shadow: Option<Box<ShadowText>>,
 }
 
 impl DefaultSkimItem {
@@ -79,8 +85,21 @@ impl DefaultSkimItem {
             orig_text,
             text,
             matching_ranges,
+            shadow: None,
         }
     }
+
+    /// compute the romanized text of the item, to be matched along with the text
+    pub fn translit(mut self, translit: Translit) -> Self {
+        self.shadow = translit.shadow(self.text.stripped()).map(|shadow| {
+            let shadow = match &self.matching_ranges {
+                Some(ranges) => shadow.with_matching_ranges(self.text.stripped(), ranges),
+                None => shadow,
+            };
+            Box::new(shadow)
+        });
+        self
+    }
 }
 
 impl SkimItem for DefaultSkimItem {
@@ -105,6 +124,10 @@ impl SkimItem for DefaultSkimItem {
         self.matching_ranges.as_ref().map(|vec| vec as &[(usize, usize)])
     }
 
+    fn shadow_text(&self) -> Option<&ShadowText> {
+        self.shadow.as_deref()
+    }
+
     fn display(&self, context: DisplayContext) -> AnsiString {
         let new_fragments: Vec<(Attr, (u32, u32))> = match context.matches {
             Some(Matches::CharIndices(indices)) => indices
@@ -113,10 +136,11 @@ impl SkimItem for DefaultSkimItem {
                 .collect(),
             Some(Matches::CharRange(start, end)) => vec![(context.highlight_attr, (start as u32, end as u32))],
             Some(Matches::ByteRange(start, end)) => {
-                let ch_start = context.text[..start].len();
-                let ch_end = ch_start + context.text[start..end].len();
+                let ch_start = context.text[..start].chars().count();
+                let ch_end = ch_start + context.text[start..end].chars().count();
                 vec![(context.highlight_attr, (ch_start as u32, ch_end as u32))]
             }
+            Some(Matches::ByteRanges(ranges)) => char_ranges(context.text, ranges),
             None => vec![],
         };
         let mut ret = self.text.clone();
@@ -124,3 +148,164 @@ impl SkimItem for DefaultSkimItem {
         ret
     }
 }
+
+//------------------------------------------------------------------------------
+/// An item read from a line that is not valid UTF-8. It is matched and displayed with the invalid
+/// bytes escaped as `\xNN`, and output as it was read.
+pub struct BytesItem {
+    item: Arc<dyn SkimItem>,
+    bytes: Box<[u8]>,
+}
+
+impl BytesItem {
+    /// `item` was built from the `escape`d line read, `bytes` are output instead of its output, e.g.
+    /// the line read
+    pub fn new(item: Arc<dyn SkimItem>, bytes: &[u8]) -> Self {
+        Self {
+            item,
+            bytes: bytes.into(),
+        }
+    }
+
+    /// the valid UTF-8 of `bytes`, with the invalid bytes as `\xNN`
+    pub fn escape(bytes: &[u8]) -> String {
+        let mut escaped = String::with_capacity(bytes.len() + 8);
+        for chunk in bytes.utf8_chunks() {
+            escaped.push_str(chunk.valid());
+            chunk
+                .invalid()
+                .iter()
+                .for_each(|byte| escaped.push_str(&format!("\\x{:02X}", byte)));
+        }
+        escaped
+    }
+}
+
+impl SkimItem for BytesItem {
+    fn text(&self) -> Cow<'_, str> {
+        self.item.text()
+    }
+
+    fn display(&self, context: DisplayContext) -> AnsiString {
+        self.item.display(context)
+    }
+
+    fn preview(&self, context: PreviewContext) -> ItemPreview {
+        self.item.preview(context)
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        self.item.output()
+    }
+
+    fn output_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.bytes)
+    }
+
+    fn field_text(&self) -> Cow<'_, str> {
+        self.item.field_text()
+    }
+
+    fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        self.item.get_matching_ranges()
+    }
+
+    fn shadow_text(&self) -> Option<&ShadowText> {
+        self.item.shadow_text()
+    }
+}
+
+//------------------------------------------------------------------------------
+/// An item read from a structured record, e.g. a JSON object. Its text is made of the fields of
+/// the record, and it is output as the record.
+pub struct RecordItem {
+    item: DefaultSkimItem,
+    record: Box<str>,
+}
+
+impl RecordItem {
+    /// `item` was built from the fields of `record`
+    pub fn new(record: &str, item: DefaultSkimItem) -> Self {
+        Self {
+            item,
+            record: record.into(),
+        }
+    }
+}
+
+impl SkimItem for RecordItem {
+    fn text(&self) -> Cow<'_, str> {
+        self.item.text()
+    }
+
+    fn display(&self, context: DisplayContext) -> AnsiString {
+        self.item.display(context)
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.record)
+    }
+
+    fn field_text(&self) -> Cow<'_, str> {
+        self.item.output()
+    }
+
+    fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        self.item.get_matching_ranges()
+    }
+
+    fn shadow_text(&self) -> Option<&ShadowText> {
+        self.item.shadow_text()
+    }
+}
+
+//------------------------------------------------------------------------------
+/// An item labeled with the source it was read from, when there are several (see `ItemSource`)
+pub struct SourceItem {
+    item: Arc<dyn SkimItem>,
+    source: Arc<str>,
+}
+
+impl SourceItem {
+    pub fn new(item: Arc<dyn SkimItem>, source: Arc<str>) -> Self {
+        Self { item, source }
+    }
+}
+
+impl SkimItem for SourceItem {
+    fn text(&self) -> Cow<'_, str> {
+        self.item.text()
+    }
+
+    fn display(&self, context: DisplayContext) -> AnsiString {
+        self.item.display(context)
+    }
+
+    fn preview(&self, context: PreviewContext) -> ItemPreview {
+        self.item.preview(context)
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        self.item.output()
+    }
+
+    fn output_bytes(&self) -> Cow<'_, [u8]> {
+        self.item.output_bytes()
+    }
+
+    fn field_text(&self) -> Cow<'_, str> {
+        self.item.field_text()
+    }
+
+    fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        self.item.get_matching_ranges()
+    }
+
+    fn shadow_text(&self) -> Option<&ShadowText> {
+        self.item.shadow_text()
+    }
+
+    fn source(&self) -> Option<&str> {
+        Some(&self.source)
+    }
+}
@@ -1,20 +1,26 @@
 /// helper for turn a BufRead into a skim stream
-use std::env;
 use std::error::Error;
-use std::io::{BufRead, BufReader};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
 
-use std::process::{Child, Command, Stdio};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::process::{Child, Stdio};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, bounded, unbounded};
 use regex::Regex;
 
 use crate::field::FieldRange;
-use crate::helper::ingest::{ingest_loop, BuildOptions, SendRawOrBuild};
-use crate::reader::CommandCollector;
-use crate::{SkimItem, SkimItemReceiver, SkimItemSender};
+use crate::helper::ingest::{BuildOptions, SendRawOrBuild, ingest_loop};
+use crate::helper::record::{InputFormat, RecordParser};
+use crate::helper::source::{ItemSource, SourceInput, label_items};
+use crate::helper::unique::{Unique, UniqueKeep};
+use crate::helper::walker::{WalkerOption, walk};
+use crate::reader::{CommandCollector, ProcessStatus};
+use crate::{Shell, SkimItem, SkimItemReceiver, SkimItemSender, Translit};
 
 #[cfg(feature = "malloc_trim")]
 #[cfg(target_os = "linux")]
@@ -22,11 +28,17 @@ use crate::{SkimItem, SkimItemReceiver, SkimItemSender};
 use crate::malloc_trim;
 
 const CMD_CHANNEL_SIZE: usize = 1_024;
+const READ_BUFFER_CAPACITY: usize = 65_536;
 const DELIMITER_STR: &str = r"[\t\n ]+";
+// sent to the killer of a command once its output is over, any other message kills it
+const OUTPUT_OVER: i32 = 0;
+// how often a command that closed its output is checked for its exit
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 pub enum CollectorInput {
     Pipe(Box<dyn BufRead + Send>),
     Command(String),
+    Walk(Vec<PathBuf>, WalkerOption),
 }
 
 #[derive(Debug)]
@@ -37,6 +49,12 @@ pub struct SkimItemReaderOption {
     delimiter: Regex,
     line_ending: u8,
     show_error: bool,
+    translit: Translit,
+    input_format: InputFormat,
+    field_names: Vec<String>,
+    unique: Option<UniqueKeep>,
+    unique_key: Vec<FieldRange>,
+    shell: Shell,
 }
 
 impl Default for SkimItemReaderOption {
@@ -48,6 +66,12 @@ impl Default for SkimItemReaderOption {
             matching_fields: Vec::new(),
             delimiter: Regex::new(DELIMITER_STR).unwrap(),
             show_error: false,
+            translit: Translit::empty(),
+            input_format: InputFormat::Text,
+            field_names: Vec::new(),
+            unique: None,
+            unique_key: Vec::new(),
+            shell: Shell::default(),
         }
     }
 }
@@ -70,9 +94,32 @@ impl SkimItemReaderOption {
         self
     }
 
+    /// the names that `with_nth` and `nth` accept along with the indices of the fields, and the
+    /// first columns of JSON records
+    pub fn field_names(mut self, field_names: &str) -> Self {
+        if !field_names.is_empty() {
+            self.field_names = field_names.split(',').map(str::to_string).collect();
+        }
+        self
+    }
+
+    /// parse the records as JSON lines, CSV or TSV, into fields separated by
+    /// `InputFormat::DELIMITER`, which becomes the delimiter
+    pub fn input_format(mut self, input_format: InputFormat) -> Self {
+        self.input_format = input_format;
+        if input_format.is_structured() {
+            self.delimiter = Regex::new(InputFormat::DELIMITER).unwrap();
+        }
+        self
+    }
+
     pub fn with_nth(mut self, with_nth: &str) -> Self {
         if !with_nth.is_empty() {
-            self.transform_fields = with_nth.split(',').filter_map(FieldRange::from_str).collect();
+            let field_names: Vec<&str> = self.field_names.iter().map(String::as_str).collect();
+            self.transform_fields = with_nth
+                .split(',')
+                .filter_map(|range| FieldRange::from_spec(range, &field_names))
+                .collect();
         }
         self
     }
@@ -84,7 +131,11 @@ impl SkimItemReaderOption {
 
     pub fn nth(mut self, nth: &str) -> Self {
         if !nth.is_empty() {
-            self.matching_fields = nth.split(',').filter_map(FieldRange::from_str).collect();
+            let field_names: Vec<&str> = self.field_names.iter().map(String::as_str).collect();
+            self.matching_fields = nth
+                .split(',')
+                .filter_map(|range| FieldRange::from_spec(range, &field_names))
+                .collect();
         }
         self
     }
@@ -108,23 +159,70 @@ impl SkimItemReaderOption {
         self
     }
 
+    /// romanize the items written in the given scripts, e.g. "ru,pinyin"
+    pub fn translit(mut self, translit: &str) -> Self {
+        self.translit = Translit::parse(translit);
+        self
+    }
+
+    /// drop the lines whose key was read already, keeping the first or the last of them
+    pub fn unique(mut self, unique: Option<UniqueKeep>) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    /// the fields that are the key of a line for `unique`, e.g. "2" or "cmd", instead of the
+    /// whole line
+    pub fn unique_key(mut self, unique_key: &str) -> Self {
+        if !unique_key.is_empty() {
+            let field_names: Vec<&str> = self.field_names.iter().map(String::as_str).collect();
+            self.unique_key = unique_key
+                .split(',')
+                .filter_map(|range| FieldRange::from_spec(range, &field_names))
+                .collect();
+        }
+        self
+    }
+
+    /// the shell that runs the commands, `$SHELL -c` by default
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
 
+    // the keys of a new input, none read yet
+    fn new_unique(&self) -> Option<Arc<Unique>> {
+        self.unique
+            .map(|keep| Arc::new(Unique::new(keep, &self.unique_key, &self.delimiter)))
+    }
+
     pub fn is_simple(&self) -> bool {
-        !self.use_ansi_color && self.matching_fields.is_empty() && self.transform_fields.is_empty()
+        !self.use_ansi_color
+            && self.matching_fields.is_empty()
+            && self.transform_fields.is_empty()
+            && self.translit.is_empty()
+            && !self.input_format.is_structured()
     }
 }
 
 pub struct SkimItemReader {
     option: Arc<SkimItemReaderOption>,
+    // the keys shared by the readers of several sources, each input has its own otherwise
+    shared_unique: Option<Arc<Unique>>,
+    // of the command invoked last
+    process_status: Option<Arc<ProcessStatus>>,
 }
 
 impl Default for SkimItemReader {
     fn default() -> Self {
         Self {
             option: Arc::new(Default::default()),
+            shared_unique: None,
+            process_status: None,
         }
     }
 }
@@ -133,6 +231,8 @@ impl SkimItemReader {
     pub fn new(option: SkimItemReaderOption) -> Self {
         Self {
             option: Arc::new(option),
+            shared_unique: None,
+            process_status: None,
         }
     }
 
@@ -153,13 +253,78 @@ impl SkimItemReader {
         }
     }
 
+    /// read the items of all the `sources` at once into one stream, labeled with their source.
+    /// Fails if a file cannot be opened, the error names the file.
+    pub fn of_sources(&self, sources: Vec<ItemSource>) -> io::Result<SkimItemReceiver> {
+        let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = unbounded();
+        // a line is not new if it was read from another source
+        let reader = SkimItemReader {
+            option: self.option.clone(),
+            shared_unique: self.option.new_unique(),
+            process_status: None,
+        };
+
+        for source in sources {
+            let (rx_source, tx_interrupt) = match source.input {
+                SourceInput::Stdin => {
+                    let stdin = BufReader::with_capacity(READ_BUFFER_CAPACITY, io::stdin());
+                    (reader.of_bufread(Box::new(stdin)).0, None)
+                }
+                SourceInput::File(path) => {
+                    let file = File::open(&path)
+                        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+                    let file = BufReader::with_capacity(READ_BUFFER_CAPACITY, file);
+                    (reader.of_bufread(Box::new(file)).0, None)
+                }
+                SourceInput::Command(cmd) => {
+                    let (rx_source, tx_interrupt, _) = reader
+                        .read_and_collect_from_command(Arc::new(AtomicUsize::new(0)), CollectorInput::Command(cmd));
+                    (rx_source, Some(tx_interrupt))
+                }
+            };
+
+            let tx_item = tx_item.clone();
+            let label = source.label;
+            thread::spawn(move || {
+                for items in rx_source.iter() {
+                    if tx_item.send(label_items(items, &label)).is_err() {
+                        break;
+                    }
+                }
+
+                // the command is killed once its items are not wanted anymore
+                let _ = tx_interrupt.map(|tx_interrupt| tx_interrupt.send(1));
+            });
+        }
+
+        Ok(rx_item)
+    }
+
+    /// list the files under the `roots` with the built-in walker, instead of running a command
+    pub fn of_walker(&self, roots: Vec<PathBuf>, walker: WalkerOption) -> (SkimItemReceiver, Option<JoinHandle<()>>) {
+        let (rx_item, _tx_interrupt, opt_ingest_handle) = self.walk(roots, walker, Arc::new(AtomicUsize::new(0)));
+        (rx_item, opt_ingest_handle)
+    }
+
+    /// components_to_stop == 0 => the walk is over
+    /// return (channel_for_receive_item, channel_to_stop_walk)
+    pub fn walk(
+        &self,
+        roots: Vec<PathBuf>,
+        walker: WalkerOption,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>) {
+        self.read_and_collect_from_command(components_to_stop, CollectorInput::Walk(roots, walker))
+    }
+
     /// helper: convert bufread into SkimItemReceiver
     fn raw_bufread(&self, source: Box<dyn BufRead + Send>) -> (SkimItemReceiver, Option<JoinHandle<()>>) {
         let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = unbounded();
         let line_ending = self.option.line_ending;
+        let unique = self.unique();
 
         let ingest_handle = thread::spawn(move || {
-            ingest_loop(source, line_ending, &tx_item, &SendRawOrBuild::Raw);
+            ingest_loop(source, line_ending, &tx_item, &SendRawOrBuild::Raw, unique.as_deref());
 
             #[cfg(feature = "malloc_trim")]
             #[cfg(target_os = "linux")]
@@ -183,28 +348,31 @@ impl SkimItemReader {
 
         match input {
             CollectorInput::Pipe(source) => {
+                let ingest_handle = self.ingest(source, tx_item, tx_interrupt.clone(), components_to_stop);
+                (rx_item, tx_interrupt, Some(ingest_handle))
+            }
+            CollectorInput::Walk(roots, walker) => {
                 let started = Arc::new(AtomicBool::new(false));
                 let started_clone = started.clone();
-                let tx_interrupt_clone = tx_interrupt.clone();
                 let option = self.option.clone();
                 let ingest_handle = thread::spawn(move || {
-                    debug!("collector: command collector start");
+                    debug!("collector: walker start");
                     components_to_stop.fetch_add(1, Ordering::SeqCst);
-                    started_clone.store(true, Ordering::SeqCst);
-                    // notify parent that it is started
+                    started_clone.store(true, Ordering::SeqCst); // notify parent that it is started
 
+                    // the paths are not records of the input format
                     let opts = BuildOptions {
                         ansi_enabled: option.use_ansi_color,
                         trans_fields: &option.transform_fields,
                         matching_fields: &option.matching_fields,
                         delimiter: &option.delimiter,
+                        translit: option.translit,
+                        record_parser: None,
                     };
+                    walk(&roots, &walker, &tx_item, &rx_interrupt, &SendRawOrBuild::Build(opts));
 
-                    ingest_loop(source, option.line_ending, &tx_item, &SendRawOrBuild::Build(opts));
-
-                    let _ = tx_interrupt_clone.send(1); // ensure the waiting thread will exit
                     components_to_stop.fetch_sub(1, Ordering::SeqCst);
-                    debug!("collector: command collector stop");
+                    debug!("collector: walker stop");
                 });
 
                 while !started.load(Ordering::SeqCst) {
@@ -214,49 +382,156 @@ impl SkimItemReader {
                 (rx_item, tx_interrupt, Some(ingest_handle))
             }
             CollectorInput::Command(cmd) => {
-                let command = get_command_output(&cmd).expect("command not found").0;
+                let (killer_handle, _) =
+                    self.run_command(&cmd, tx_item, tx_interrupt.clone(), rx_interrupt, components_to_stop);
+                (rx_item, tx_interrupt, Some(killer_handle))
+            }
+        }
+    }
 
-                let started = Arc::new(AtomicBool::new(false));
-                let started_clone = started.clone();
-                let components_to_stop_clone = components_to_stop;
-                let send_error = self.option.show_error;
-                // listening to close signal and kill command if needed
-                let ingest_handle = thread::spawn(move || {
-                    debug!("collector: command killer start");
-                    components_to_stop_clone.fetch_add(1, Ordering::SeqCst);
-                    started_clone.store(true, Ordering::SeqCst); // notify parent that it is started
+    // run the command and read its output, the command is killed once anything but `OUTPUT_OVER`
+    // is received from `rx_interrupt`. Return the killer thread and the status of the process.
+    fn run_command(
+        &self,
+        cmd: &str,
+        tx_item: SkimItemSender,
+        tx_interrupt: Sender<i32>,
+        rx_interrupt: Receiver<i32>,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> (JoinHandle<()>, Arc<ProcessStatus>) {
+        let (mut command, source) = get_command_output(&self.option.shell, cmd).expect("command not found");
+        // the output is read like a pipe, which tells the killer once it is over. The
+        // ingest thread is detached, it stops once the command is killed
+        let _ingest_handle = self.ingest(source, tx_item.clone(), tx_interrupt, components_to_stop.clone());
+
+        let process_status = Arc::new(ProcessStatus::default());
+        let send_error = self.option.show_error;
+        // stderr is read all along, so that the command is not blocked by a full pipe
+        let stderr_handle = command.as_mut().and_then(|child| child.stderr.take()).map(|stderr| {
+            let process_status = process_status.clone();
+            thread::spawn(move || {
+                let mut lines = Vec::new();
+                for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                    let line = String::from_utf8_lossy(&line).into_owned();
+                    process_status.push_stderr(&line);
+                    if send_error {
+                        lines.push(line);
+                    }
+                }
+                lines
+            })
+        });
 
-                    let _ = rx_interrupt.recv(); // block waiting
-                    if let Some(mut child) = command {
-                        // clean up resources
-                        let _ = child.kill();
-                        let _ = child.wait();
-
-                        if send_error {
-                            let has_error = child
-                                .try_wait()
-                                .map(|os| os.map(|s| !s.success()).unwrap_or(true))
-                                .unwrap_or(false);
-                            if has_error {
-                                let output = child.wait_with_output().expect("could not retrieve error message");
-                                for line in String::from_utf8_lossy(&output.stderr).lines() {
-                                    let _ = tx_item.send(vec![Arc::new(line.to_string())]);
-                                }
-                            }
+        let started = Arc::new(AtomicBool::new(false));
+        let started_clone = started.clone();
+        let components_to_stop_clone = components_to_stop;
+        let process_status_clone = process_status.clone();
+        // listening to close signal and kill command if needed
+        let killer_handle = thread::spawn(move || {
+            debug!("collector: command killer start");
+            components_to_stop_clone.fetch_add(1, Ordering::SeqCst);
+            started_clone.store(true, Ordering::SeqCst); // notify parent that it is started
+
+            let mut output_over = rx_interrupt.recv() == Ok(OUTPUT_OVER); // block waiting
+            if let Some(mut child) = command {
+                // the command may exit a moment after its output is over, unless it is killed
+                let exit_status = loop {
+                    match child.try_wait() {
+                        Ok(Some(exit_status)) => break Some(exit_status),
+                        Ok(None) if output_over => match rx_interrupt.recv_timeout(EXIT_POLL_INTERVAL) {
+                            Ok(OUTPUT_OVER) | Err(RecvTimeoutError::Timeout) => continue,
+                            _ => output_over = false,
+                        },
+                        _ => {
+                            // clean up resources, the processes it started too
+                            kill_process_group(&child);
+                            let _ = child.kill();
+                            break child.wait().ok();
                         }
                     }
+                };
 
-                    components_to_stop_clone.fetch_sub(1, Ordering::SeqCst);
-                    debug!("collector: command killer stop");
-                });
+                if let Some(exit_status) = exit_status {
+                    process_status_clone.set_exit_status(exit_status);
+                }
 
-                while !started.load(Ordering::SeqCst) {
-                    // busy waiting for the thread to start. (components_to_stop is added)
+                // stderr may be held open by a process left behind, it is only waited for here
+                let has_error = exit_status.map(|status| !status.success()).unwrap_or(false);
+                if send_error && has_error {
+                    let lines = stderr_handle.and_then(|handle| handle.join().ok()).unwrap_or_default();
+                    for line in lines {
+                        let _ = tx_item.send(vec![Arc::new(line)]);
+                    }
                 }
+            }
 
-                (rx_item, tx_interrupt, Some(ingest_handle))
+            components_to_stop_clone.fetch_sub(1, Ordering::SeqCst);
+            debug!("collector: command killer stop");
+        });
+
+        while !started.load(Ordering::SeqCst) {
+            // busy waiting for the thread to start. (components_to_stop is added)
+        }
+
+        (killer_handle, process_status)
+    }
+
+    fn unique(&self) -> Option<Arc<Unique>> {
+        self.shared_unique.clone().or_else(|| self.option.new_unique())
+    }
+
+    // read the items from `source` in a new thread, `tx_interrupt` is sent to when it is over
+    fn ingest(
+        &self,
+        source: Box<dyn BufRead + Send>,
+        tx_item: SkimItemSender,
+        tx_interrupt: Sender<i32>,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> JoinHandle<()> {
+        let started = Arc::new(AtomicBool::new(false));
+        let started_clone = started.clone();
+        let option = self.option.clone();
+        let unique = self.unique();
+        let ingest_handle = thread::spawn(move || {
+            debug!("collector: command collector start");
+            components_to_stop.fetch_add(1, Ordering::SeqCst);
+            started_clone.store(true, Ordering::SeqCst);
+            // notify parent that it is started
+
+            let mut source = source;
+            if option.input_format.has_header() {
+                // the names of the columns are not an item
+                let _ = source.read_until(option.line_ending, &mut Vec::new());
             }
+            let record_parser = RecordParser::new(option.input_format, option.field_names.clone());
+
+            let opts = BuildOptions {
+                ansi_enabled: option.use_ansi_color,
+                trans_fields: &option.transform_fields,
+                matching_fields: &option.matching_fields,
+                delimiter: &option.delimiter,
+                translit: option.translit,
+                record_parser: option.input_format.is_structured().then_some(&record_parser),
+            };
+
+            ingest_loop(
+                source,
+                option.line_ending,
+                &tx_item,
+                &SendRawOrBuild::Build(opts),
+                unique.as_deref(),
+            );
+
+            let _ = tx_interrupt.send(OUTPUT_OVER); // ensure the waiting thread will exit
+            components_to_stop.fetch_sub(1, Ordering::SeqCst);
+            debug!("collector: command collector stop");
+        });
+
+        while !started.load(Ordering::SeqCst) {
+            // busy waiting for the thread to start. (components_to_stop is added)
         }
+
+        ingest_handle
     }
 }
 
@@ -266,20 +541,41 @@ impl CommandCollector for SkimItemReader {
         cmd: &str,
         components_to_stop: Arc<AtomicUsize>,
     ) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>) {
-        self.read_and_collect_from_command(components_to_stop, CollectorInput::Command(cmd.to_string()))
+        let (tx_interrupt, rx_interrupt) = bounded(CMD_CHANNEL_SIZE);
+        let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = unbounded();
+        let (killer_handle, process_status) =
+            self.run_command(cmd, tx_item, tx_interrupt.clone(), rx_interrupt, components_to_stop);
+        self.process_status = Some(process_status);
+        (rx_item, tx_interrupt, Some(killer_handle))
+    }
+
+    fn walk_files(
+        &mut self,
+        roots: Vec<PathBuf>,
+        walker: WalkerOption,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> Option<(SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>)> {
+        self.process_status = None;
+        Some(self.walk(roots, walker, components_to_stop))
+    }
+
+    fn process_status(&self) -> Option<Arc<ProcessStatus>> {
+        self.process_status.clone()
     }
 }
 
 type CommandOutput = (Option<Child>, Box<dyn BufRead + Send>);
 
-fn get_command_output(cmd: &str) -> Result<CommandOutput, Box<dyn Error>> {
-    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-    let mut command: Child = Command::new(shell)
-        .arg("-c")
-        .arg(cmd)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+fn get_command_output(shell: &Shell, cmd: &str) -> Result<CommandOutput, Box<dyn Error>> {
+    let mut command = shell.command(cmd);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // in a process group of its own, which is killed with it
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut command: Child = command.spawn()?;
 
     let stdout = command
         .stdout
@@ -288,3 +584,15 @@ fn get_command_output(cmd: &str) -> Result<CommandOutput, Box<dyn Error>> {
 
     Ok((Some(command), Box::new(BufReader::new(stdout))))
 }
+
+#[cfg(unix)]
+fn kill_process_group(child: &Child) {
+    // the process is not waited for yet, its id is not reused
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+// without process groups, only the command itself is killed
+#[cfg(not(unix))]
+fn kill_process_group(_child: &Child) {}
@@ -1,4 +1,9 @@
+pub mod follow;
 pub mod ingest;
 pub mod item;
 pub mod item_reader;
+pub mod record;
 pub mod selector;
+pub mod source;
+pub mod unique;
+pub mod walker;
@@ -0,0 +1,173 @@
+/// helper for parsing the records of structured input into fields
+use std::cell::RefCell;
+
+use serde_json::Value;
+
+/// The format of the input records
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// lines of text, split into fields by the delimiter
+    #[default]
+    Text,
+    /// a JSON object per line, the fields are the values of its keys
+    JsonLines,
+    /// comma separated values, the first row names the columns
+    Csv,
+    /// tab separated values, the first row names the columns
+    Tsv,
+}
+
+impl InputFormat {
+    /// the delimiter of the fields in the text of the items built from structured records
+    pub const DELIMITER: &'static str = "\t";
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(format: &str) -> Option<Self> {
+        match format {
+            "text" => Some(InputFormat::Text),
+            "jsonl" => Some(InputFormat::JsonLines),
+            "csv" => Some(InputFormat::Csv),
+            "tsv" => Some(InputFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    pub fn is_structured(&self) -> bool {
+        *self != InputFormat::Text
+    }
+
+    /// whether the first record names the columns instead of being an item
+    pub fn has_header(&self) -> bool {
+        matches!(self, InputFormat::Csv | InputFormat::Tsv)
+    }
+
+    /// the names of the columns: the header row, or the keys of the first JSON object
+    pub fn columns(&self, first_record: &str) -> Vec<String> {
+        match self {
+            InputFormat::Text => Vec::new(),
+            InputFormat::JsonLines => match serde_json::from_str(first_record) {
+                Ok(Value::Object(object)) => object.keys().cloned().collect(),
+                _ => Vec::new(),
+            },
+            InputFormat::Csv => split_quoted(first_record, ','),
+            InputFormat::Tsv => split_quoted(first_record, '\t'),
+        }
+    }
+}
+
+/// Parses the records of an input into fields. The fields of JSON objects are ordered by the given
+/// columns, the keys that are not among them become new columns as they appear.
+#[derive(Debug)]
+pub struct RecordParser {
+    format: InputFormat,
+    columns: RefCell<Vec<String>>,
+}
+
+impl RecordParser {
+    pub fn new(format: InputFormat, columns: Vec<String>) -> Self {
+        Self {
+            format,
+            columns: RefCell::new(columns),
+        }
+    }
+
+    /// `None` if the record is not one of the format, e.g. invalid JSON
+    pub fn fields(&self, record: &str) -> Option<Vec<String>> {
+        match self.format {
+            InputFormat::Text => None,
+            InputFormat::JsonLines => {
+                let Ok(Value::Object(object)) = serde_json::from_str(record) else {
+                    return None;
+                };
+
+                let mut columns = self.columns.borrow_mut();
+                let mut fields = vec![String::new(); columns.len()];
+                for (key, value) in object {
+                    let value = match value {
+                        Value::String(string) => string,
+                        Value::Null => String::new(),
+                        value => value.to_string(),
+                    };
+                    match columns.iter().position(|column| *column == key) {
+                        Some(idx) => fields[idx] = value,
+                        None => {
+                            columns.push(key);
+                            fields.push(value);
+                        }
+                    }
+                }
+                Some(fields)
+            }
+            InputFormat::Csv => Some(split_quoted(record, ',')),
+            InputFormat::Tsv => Some(split_quoted(record, '\t')),
+        }
+    }
+
+    /// the text of the item of a record: the fields separated by `InputFormat::DELIMITER`, with
+    /// their own tabs and newlines turned into spaces
+    pub fn join(fields: &[String]) -> String {
+        fields
+            .iter()
+            .map(|field| field.replace(['\t', '\n', '\r'], " "))
+            .collect::<Vec<_>>()
+            .join(InputFormat::DELIMITER)
+    }
+}
+
+// a field may be quoted to contain the separator, `""` in a quoted field is a quote
+fn split_quoted(record: &str, separator: char) -> Vec<String> {
+    let record = record.strip_suffix('\r').unwrap_or(record);
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ch if ch == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            ch => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_quoted() {
+        assert_eq!(split_quoted("a,b,,c", ','), ["a", "b", "", "c"]);
+        assert_eq!(
+            split_quoted(r#""a,b","say ""hi""",c"#, ','),
+            ["a,b", r#"say "hi""#, "c"]
+        );
+        assert_eq!(split_quoted("a\t\"b\tc\"\r", '\t'), ["a", "b\tc"]);
+        assert_eq!(split_quoted("", ','), [""]);
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let format = InputFormat::JsonLines;
+        let columns = format.columns(r#"{"name": "foo", "size": 3}"#);
+        assert_eq!(columns, ["name", "size"]);
+
+        let parser = RecordParser::new(format, columns);
+        assert_eq!(
+            parser
+                .fields(r#"{"size": 1.5, "tags": ["a"], "name": "b\tc"}"#)
+                .unwrap(),
+            ["b\tc", "1.5", r#"["a"]"#]
+        );
+        assert_eq!(parser.fields(r#"{"tags": null}"#).unwrap(), ["", "", ""]);
+        assert_eq!(parser.fields("not json"), None);
+        assert_eq!(RecordParser::join(&["b\tc".to_string(), "1".to_string()]), "b c\t1");
+    }
+}
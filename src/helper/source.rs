@@ -0,0 +1,117 @@
+/// helper for reading items from several labeled sources at once
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::item::SourceItem;
+use crate::SkimItem;
+use crate::arena::ItemChunk;
+
+/// Where the items of a source are read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceInput {
+    Stdin,
+    File(PathBuf),
+    /// run with the shell of `SkimItemReaderOption::shell`
+    Command(String),
+}
+
+/// A source of items, whose items are labeled with the name of the source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemSource {
+    pub label: Arc<str>,
+    pub input: SourceInput,
+}
+
+impl ItemSource {
+    /// parse `[LABEL:]SOURCE`, where SOURCE is `-` for stdin, `@FILE` for a file or else a command,
+    /// e.g. "recent:@~/.recent" or "project:fd --type f". The label is a word of letters, digits,
+    /// `_`, `-` and `.`, it is `default_label` if not given. A leading `~/` of FILE is the home
+    /// directory.
+    pub fn parse(spec: &str, default_label: &str) -> Self {
+        let (label, source) = match spec.split_once(':') {
+            Some((label, source)) if is_label(label) => (label, source),
+            _ => (default_label, spec),
+        };
+
+        let input = match source {
+            "-" => SourceInput::Stdin,
+            _ => match source.strip_prefix('@') {
+                Some(path) => SourceInput::File(expand_home(path)),
+                None => SourceInput::Command(source.to_string()),
+            },
+        };
+
+        Self {
+            label: label.into(),
+            input,
+        }
+    }
+}
+
+// the specs are quoted, their `~` is not expanded by the shell
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+}
+
+/// label the items with their source, the chunks of lines keep their lines in place
+pub fn label_items(items: Vec<Arc<dyn SkimItem>>, label: &Arc<str>) -> Vec<Arc<dyn SkimItem>> {
+    items
+        .into_iter()
+        .map(|item| {
+            let chunk = item.as_any().downcast_ref::<ItemChunk>().cloned();
+            match chunk {
+                Some(chunk) => {
+                    // not to copy the lines, the chunk must not be shared
+                    drop(item);
+                    Arc::new(chunk.with_source(label.clone())) as Arc<dyn SkimItem>
+                }
+                None => Arc::new(SourceItem::new(item, label.clone())),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        let source = ItemSource::parse("recent:@/tmp/recent", "1");
+        assert_eq!(&*source.label, "recent");
+        assert_eq!(source.input, SourceInput::File(PathBuf::from("/tmp/recent")));
+
+        let source = ItemSource::parse("recent:@~/.recent", "1");
+        let expected = match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".recent"),
+            None => PathBuf::from("~/.recent"),
+        };
+        assert_eq!(source.input, SourceInput::File(expected));
+        // only a leading `~/`
+        let source = ItemSource::parse("@~user/recent", "1");
+        assert_eq!(source.input, SourceInput::File(PathBuf::from("~user/recent")));
+
+        let source = ItemSource::parse("buf-list:-", "1");
+        assert_eq!(&*source.label, "buf-list");
+        assert_eq!(source.input, SourceInput::Stdin);
+
+        // not a label, the command contains a colon
+        let source = ItemSource::parse("git log --format=%h:%s", "2");
+        assert_eq!(&*source.label, "2");
+        assert_eq!(source.input, SourceInput::Command("git log --format=%h:%s".to_string()));
+
+        let source = ItemSource::parse("project:fd --type f", "3");
+        assert_eq!(source.input, SourceInput::Command("fd --type f".to_string()));
+    }
+}
@@ -0,0 +1,179 @@
+/// helper for dropping the lines whose key was read already
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use regex::Regex;
+
+use super::item::BytesItem;
+use super::record::RecordParser;
+use crate::field::{FieldRange, parse_matching_fields};
+
+/// Which one of the lines with the same key is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniqueKeep {
+    First,
+    /// the lines are kept once the whole input is read, the input must end: it is held in
+    /// memory until then
+    Last,
+}
+
+impl UniqueKeep {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(keep: &str) -> Option<Self> {
+        match keep.to_lowercase().as_str() {
+            "first" => Some(UniqueKeep::First),
+            "last" => Some(UniqueKeep::Last),
+            _ => None,
+        }
+    }
+}
+
+// the keys are hashes already
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        unreachable!("only the hashes of the keys are stored")
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+type Hashes = HashSet<u64, BuildHasherDefault<KeyHasher>>;
+
+/// The keys of the lines read so far, shared by the readers of an input. Only a 64-bit hash of
+/// each key is stored, whatever the length of the lines, so two keys may collide, though
+/// hardly ever before billions of keys.
+pub struct Unique {
+    keep: UniqueKeep,
+    key_fields: Vec<FieldRange>,
+    delimiter: Regex,
+    hashes: Mutex<Hashes>,
+}
+
+impl Unique {
+    /// the key of a line is the whole line, or its `key_fields` split by `delimiter` if any
+    pub fn new(keep: UniqueKeep, key_fields: &[FieldRange], delimiter: &Regex) -> Self {
+        Self {
+            keep,
+            key_fields: key_fields.to_vec(),
+            delimiter: delimiter.clone(),
+            hashes: Mutex::new(Hashes::default()),
+        }
+    }
+
+    pub fn keep(&self) -> UniqueKeep {
+        self.keep
+    }
+
+    /// lock the keys, e.g. for all the lines of a buffer at once
+    pub fn lock(&self) -> SeenKeys<'_> {
+        SeenKeys {
+            unique: self,
+            hashes: self.hashes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+        }
+    }
+
+    fn hash_key(&self, line: &[u8], record_parser: Option<&RecordParser>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        if self.key_fields.is_empty() {
+            line.hash(&mut hasher);
+            return hasher.finish();
+        }
+
+        let text = match std::str::from_utf8(line) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) => Cow::Owned(BytesItem::escape(line)),
+        };
+        // the fields of a record are those of the text of its item
+        let text = match record_parser.and_then(|parser| parser.fields(&text)) {
+            Some(fields) => Cow::Owned(RecordParser::join(&fields)),
+            None => text,
+        };
+
+        for (begin, end) in parse_matching_fields(&self.delimiter, &text, &self.key_fields) {
+            let field = &text[begin..end];
+            // without the delimiter that follows the field, unless it is the last one
+            let field = match self.delimiter.find_iter(field).last() {
+                Some(delimiter) if delimiter.end() == field.len() => &field[..delimiter.start()],
+                _ => field,
+            };
+            field.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// The keys of an `Unique` while it is locked
+pub struct SeenKeys<'a> {
+    unique: &'a Unique,
+    hashes: MutexGuard<'a, Hashes>,
+}
+
+impl SeenKeys<'_> {
+    /// whether the key of the line is new, it is not anymore afterwards
+    pub fn insert(&mut self, line: &[u8], record_parser: Option<&RecordParser>) -> bool {
+        self.hashes.insert(self.unique.hash_key(line, record_parser))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::record::InputFormat;
+
+    fn new_lines<'a>(unique: &Unique, lines: &[&'a str], record_parser: Option<&RecordParser>) -> Vec<&'a str> {
+        let mut seen = unique.lock();
+        lines
+            .iter()
+            .copied()
+            .filter(|line| seen.insert(line.as_bytes(), record_parser))
+            .collect()
+    }
+
+    #[test]
+    fn test_unique_lines() {
+        let delimiter = Regex::new(r"[\t\n ]+").unwrap();
+        let unique = Unique::new(UniqueKeep::First, &[], &delimiter);
+        let lines = ["ls", "cd src", "ls", "ls ", "cd src"];
+        assert_eq!(new_lines(&unique, &lines, None), ["ls", "cd src", "ls "]);
+        assert!(new_lines(&unique, &lines, None).is_empty());
+        assert!(unique.lock().insert(b"caf\xe9", None));
+        assert!(!unique.lock().insert(b"caf\xe9", None));
+    }
+
+    #[test]
+    fn test_unique_key_fields() {
+        let delimiter = Regex::new(",").unwrap();
+        let unique = Unique::new(UniqueKeep::First, &[FieldRange::Single(2)], &delimiter);
+        let lines = ["1,a,x", "2,b", "3,a", "4,b,y", "5,ab", "6"];
+        assert_eq!(new_lines(&unique, &lines, None), ["1,a,x", "2,b", "5,ab", "6"]);
+
+        // without the delimiter after the fields
+        let unique = Unique::new(UniqueKeep::First, &[FieldRange::Both(1, 2)], &delimiter);
+        assert_eq!(new_lines(&unique, &["a,bc", "ab,c", "a,bc,d"], None), ["a,bc", "ab,c"]);
+
+        // the fields of a record are parsed first
+        let parser = RecordParser::new(InputFormat::JsonLines, vec!["id".to_string(), "cmd".to_string()]);
+        let delimiter = Regex::new(InputFormat::DELIMITER).unwrap();
+        let unique = Unique::new(UniqueKeep::First, &[FieldRange::Single(2)], &delimiter);
+        let lines = [
+            r#"{"id":1,"cmd":"ls"}"#,
+            r#"{"cmd":"ls","id":2}"#,
+            r#"{"id":3,"cmd":"pwd"}"#,
+        ];
+        assert_eq!(
+            new_lines(&unique, &lines, Some(&parser)),
+            [r#"{"id":1,"cmd":"ls"}"#, r#"{"id":3,"cmd":"pwd"}"#]
+        );
+    }
+}
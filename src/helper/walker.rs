@@ -0,0 +1,296 @@
+/// helper for listing the files of directory trees in parallel, the default source of items
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+
+use super::ingest::{SendRawOrBuild, lines_into_items};
+use super::item_reader::SkimItemReader;
+use crate::reader::CommandCollector;
+use crate::{SkimItemReceiver, SkimItemSender};
+
+// paths sent at once by a thread of the walk
+const BATCH_SIZE: usize = 1_024;
+// the directories never entered, even with `hidden`
+const SKIPPED_DIRS: [&str; 1] = [".git"];
+
+/// What a directory walk lists, parsed from e.g. "file,dir,follow,hidden,depth:3"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalkerOption {
+    file: bool,
+    dir: bool,
+    follow: bool,
+    hidden: bool,
+    ignore: bool,
+    max_depth: Option<usize>,
+}
+
+impl Default for WalkerOption {
+    fn default() -> Self {
+        Self::from_spec(WalkerOption::DEFAULT_SPEC)
+    }
+}
+
+impl WalkerOption {
+    pub const DEFAULT_SPEC: &'static str = "file,follow,hidden";
+
+    /// comma separated switches:
+    /// - `file`, `dir`: the types of entries listed, the files (and the symlinks that are not
+    ///   followed) if none is given
+    /// - `follow`: follow the symbolic links
+    /// - `hidden`: list the hidden files and enter the hidden directories
+    /// - `no-ignore`: do not honor `.gitignore`, `.ignore` and the git excludes
+    /// - `depth:N`: do not descend more than N directories below the roots
+    pub fn from_spec(spec: &str) -> Self {
+        let mut option = Self {
+            file: false,
+            dir: false,
+            follow: false,
+            hidden: false,
+            ignore: true,
+            max_depth: None,
+        };
+
+        for switch in spec.split(',').map(str::trim) {
+            match switch {
+                "file" => option.file = true,
+                "dir" => option.dir = true,
+                "follow" => option.follow = true,
+                "hidden" => option.hidden = true,
+                "no-ignore" => option.ignore = false,
+                _ => {
+                    if let Some(depth) = switch.strip_prefix("depth:").and_then(|depth| depth.parse().ok()) {
+                        option.max_depth = Some(depth);
+                    }
+                }
+            }
+        }
+
+        if !option.file && !option.dir {
+            option.file = true;
+        }
+        option
+    }
+
+    fn lists(&self, entry: &DirEntry) -> bool {
+        // the roots themselves are not listed
+        if entry.depth() == 0 {
+            return false;
+        }
+
+        match entry.file_type() {
+            Some(file_type) if file_type.is_dir() => self.dir,
+            Some(_) => self.file,
+            None => false,
+        }
+    }
+}
+
+/// List the entries under the `roots` in parallel, the items built from their paths with `opts`
+/// are sent to `tx_item`. The walk stops early once something is sent to `rx_interrupt`.
+pub fn walk(
+    roots: &[PathBuf],
+    option: &WalkerOption,
+    tx_item: &SkimItemSender,
+    rx_interrupt: &Receiver<i32>,
+    opts: &SendRawOrBuild,
+) {
+    let Some((first_root, other_roots)) = roots.split_first() else {
+        return;
+    };
+
+    let mut builder = WalkBuilder::new(first_root);
+    other_roots.iter().for_each(|root| {
+        builder.add(root);
+    });
+    builder
+        .hidden(!option.hidden)
+        .follow_links(option.follow)
+        .max_depth(option.max_depth)
+        .ignore(option.ignore)
+        .git_ignore(option.ignore)
+        .git_global(option.ignore)
+        .git_exclude(option.ignore)
+        .parents(option.ignore)
+        .filter_entry(|entry| !SKIPPED_DIRS.iter().any(|dir| entry.file_name() == *dir));
+    let walker = builder.build_parallel();
+
+    // the items are built here, the threads of the walk only gather the paths
+    let (tx_paths, rx_paths) = unbounded();
+    let stopped = AtomicBool::new(false);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            walker.run(|| {
+                let mut batch = PathBatch::new(&tx_paths);
+                let stopped = &stopped;
+                Box::new(move |entry| {
+                    if stopped.load(Ordering::Relaxed) || !rx_interrupt.is_empty() {
+                        return WalkState::Quit;
+                    }
+
+                    match entry {
+                        Ok(entry) if option.lists(&entry) => batch.push(entry.path()),
+                        Ok(_) => {}
+                        Err(err) => debug!("walker: {}", err),
+                    }
+                    WalkState::Continue
+                })
+            });
+            drop(tx_paths);
+        });
+
+        for paths in rx_paths.iter() {
+            let items = lines_into_items(paths.iter().map(Vec::as_slice), opts);
+            if tx_item.send(items).is_err() {
+                stopped.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+}
+
+// the paths found by a thread of the walk, sent when there are enough of them and at the end
+struct PathBatch<'a> {
+    paths: Vec<Vec<u8>>,
+    tx_paths: &'a Sender<Vec<Vec<u8>>>,
+}
+
+impl<'a> PathBatch<'a> {
+    fn new(tx_paths: &'a Sender<Vec<Vec<u8>>>) -> Self {
+        Self {
+            paths: Vec::new(),
+            tx_paths,
+        }
+    }
+
+    fn push(&mut self, path: &Path) {
+        // relative to the current directory, without the leading "./"
+        let path = path.strip_prefix(".").unwrap_or(path);
+        self.paths.push(path.as_os_str().as_encoded_bytes().to_vec());
+        if self.paths.len() >= BATCH_SIZE {
+            let _ = self.tx_paths.send(std::mem::take(&mut self.paths));
+        }
+    }
+}
+
+impl Drop for PathBatch<'_> {
+    fn drop(&mut self) {
+        if !self.paths.is_empty() {
+            let _ = self.tx_paths.send(std::mem::take(&mut self.paths));
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+/// A `CommandCollector` that lists the files under the directories of the command, e.g.
+/// "src tests", instead of running it. An empty command lists the current directory.
+pub struct SkimWalker {
+    reader: Rc<RefCell<SkimItemReader>>,
+    option: WalkerOption,
+}
+
+impl SkimWalker {
+    /// the items are built by `reader`, with its `--nth`, `--with-nth`, etc.
+    pub fn new(reader: Rc<RefCell<SkimItemReader>>, option: WalkerOption) -> Self {
+        Self { reader, option }
+    }
+}
+
+impl CommandCollector for SkimWalker {
+    fn invoke(
+        &mut self,
+        cmd: &str,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>) {
+        let mut roots: Vec<PathBuf> = cmd.split_whitespace().map(PathBuf::from).collect();
+        if roots.is_empty() {
+            roots.push(PathBuf::from("."));
+        }
+        self.reader
+            .borrow()
+            .walk(roots, self.option.clone(), components_to_stop)
+    }
+
+    /// the walk of the current directory uses the switches of this walker
+    fn walk_files(
+        &mut self,
+        roots: Vec<PathBuf>,
+        _walker: WalkerOption,
+        components_to_stop: Arc<AtomicUsize>,
+    ) -> Option<(SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>)> {
+        Some(
+            self.reader
+                .borrow()
+                .walk(roots, self.option.clone(), components_to_stop),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_walker_option() {
+        let option = WalkerOption::default();
+        assert!(option.file && !option.dir && option.follow && option.hidden && option.ignore);
+
+        let option = WalkerOption::from_spec("dir, no-ignore,depth:2,unknown");
+        assert!(!option.file && option.dir && !option.follow && !option.hidden && !option.ignore);
+        assert_eq!(option.max_depth, Some(2));
+    }
+
+    #[test]
+    fn test_walk() {
+        let root = std::env::temp_dir().join(format!("sk-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        for file in [
+            "a.rs",
+            "src/b.rs",
+            "src/deep/c.rs",
+            ".hidden",
+            ".git/HEAD",
+            "target/d",
+            ".ignore",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(".ignore"), "target/\n").unwrap();
+
+        let list = |spec: &str| {
+            let (tx_item, rx_item) = unbounded();
+            let (_tx_interrupt, rx_interrupt) = unbounded();
+            walk(
+                std::slice::from_ref(&root),
+                &WalkerOption::from_spec(spec),
+                &tx_item,
+                &rx_interrupt,
+                &SendRawOrBuild::Raw,
+            );
+            drop(tx_item);
+
+            let mut paths: Vec<String> = rx_item
+                .iter()
+                .flat_map(crate::ItemChunk::unpack)
+                .map(|item| item.text().to_string())
+                .map(|path| path.strip_prefix(root.to_str().unwrap()).unwrap().to_string())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(list("file"), ["/a.rs", "/src/b.rs", "/src/deep/c.rs"]);
+        assert_eq!(list("file,hidden,depth:1"), ["/.hidden", "/.ignore", "/a.rs"]);
+        assert_eq!(list("dir,no-ignore"), ["/src", "/src/deep", "/target"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
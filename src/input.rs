@@ -225,6 +225,16 @@ mod test {
             ("enter", vec![("execute", Some("$EDITOR +{2} {1}".to_string()))]),
             key_action[0]
         );
+
+        let key_action = parse_key_action("enter:execute-argv:nvim {}");
+        assert_eq!(
+            ("enter", vec![("execute-argv", Some("nvim {}".to_string()))]),
+            key_action[0]
+        );
+        assert_eq!(
+            parse_action_arg("execute-silent-argv(echo {})"),
+            Some(Event::EvActExecuteSilentArgv("echo {}".to_string()))
+        );
     }
 
     #[test]
@@ -1,26 +1,177 @@
 ///! An item is line of text that read from `find` command or stdin together with
 ///! the internal states, such as selected or not
-use std::cmp::min;
+use std::cmp::{Ordering as CmpOrdering, max, min};
 use std::default::Default;
+use std::fmt::{Display, Error, Formatter};
 use std::ops::Deref;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Weak};
 
+use regex::Regex;
+
+use crate::arena::{ChunkData, ChunkLine, ItemChunk};
+use crate::field::{FieldRange, get_string_by_field};
+use crate::options::SkimOptions;
 use crate::spinlock::{SpinLock, SpinLockGuard};
-use crate::{MatchRange, Rank, SkimItem};
+use crate::{MatchEngine, MatchRange, MatchResult, SkimItem};
+
+const DELIMITER_STR: &str = r"[\t\n ]+";
+// the leading numeric keys of a rank, stored without allocating
+const RANK_KEYS: usize = 4;
+
+static DEFAULT_DELIMITER: LazyLock<Regex> = LazyLock::new(|| Regex::new(DELIMITER_STR).unwrap());
+
+//------------------------------------------------------------------------------
+/// The sort keys of a matched item, one per criteria of the `RankBuilder`, the smaller the better
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank {
+    // the field of `--sort-by`, it comes before the criterion
+    sort: Option<Box<RankKey>>,
+    keys: [i32; RANK_KEYS],
+    // the keys after the leading numeric ones, if any
+    more: Option<Box<[RankKey]>>,
+    // the score of the match, kept apart from the keys so that it does not depend on the criterion.
+    // Compared last, the keys already order the items by score.
+    score: i32,
+}
+
+impl Rank {
+    /// the score of the match the rank was built with, the greater the better
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+}
+
+impl From<[i32; RANK_KEYS]> for Rank {
+    fn from(keys: [i32; RANK_KEYS]) -> Self {
+        Self {
+            sort: None,
+            keys,
+            more: None,
+            score: 0,
+        }
+    }
+}
+
+/// A key of a `Rank` that is not one of its leading numeric keys
+#[derive(Clone, Debug)]
+pub enum RankKey {
+    Num(f64),
+    Text(Box<str>),
+    // sorted in descending order
+    RevText(Box<str>),
+    // e.g. a field the item does not have, always sorted last
+    Missing,
+}
+
+impl RankKey {
+    fn kind(&self) -> u8 {
+        match self {
+            RankKey::Num(_) => 0,
+            RankKey::Text(_) | RankKey::RevText(_) => 1,
+            RankKey::Missing => 2,
+        }
+    }
+}
+
+impl Ord for RankKey {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        match (self, other) {
+            (RankKey::Num(a), RankKey::Num(b)) => a.total_cmp(b),
+            (RankKey::Text(a), RankKey::Text(b)) => a.cmp(b),
+            (RankKey::RevText(a), RankKey::RevText(b)) => b.cmp(a),
+            _ => self.kind().cmp(&other.kind()),
+        }
+    }
+}
+
+impl PartialOrd for RankKey {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for RankKey {}
 
 //------------------------------------------------------------------------------
+/// How the values of a field are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldOrder {
+    /// as numbers if they are, otherwise as text
+    Auto,
+    /// as numbers, the values that are not come last
+    Num,
+    /// as text
+    Lex,
+}
+
+/// A field to sort the matched items by, instead of the score (see `--sort-by`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    // as given, e.g. "5:num:desc" or "size:desc"
+    spec: Box<str>,
+    field: i32,
+    order: FieldOrder,
+    desc: bool,
+}
+
+impl SortKey {
+    /// parse `FIELD[:num|:lex][:desc]`, FIELD is the index of the field or one of `field_names`
+    pub fn parse(spec: &str, field_names: &[&str]) -> Option<Self> {
+        let mut parts = spec.split(':');
+        let name = parts.next()?;
+        let field = field_names
+            .iter()
+            .position(|field_name| *field_name == name)
+            .map(|idx| idx as i32 + 1)
+            .or_else(|| name.parse().ok())?;
 
+        let mut sort_key = Self {
+            spec: spec.into(),
+            field,
+            order: FieldOrder::Auto,
+            desc: false,
+        };
+        for part in parts {
+            match part {
+                "num" => sort_key.order = FieldOrder::Num,
+                "lex" => sort_key.order = FieldOrder::Lex,
+                "desc" => sort_key.desc = true,
+                _ => return None,
+            }
+        }
+        Some(sort_key)
+    }
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.spec)
+    }
+}
+
+//------------------------------------------------------------------------------
 #[derive(Debug)]
 pub struct RankBuilder {
     criterion: Vec<RankCriteria>,
+    // number of the leading numeric criterion, stored in `Rank::keys`
+    num_keys: usize,
+    // to split the fields of `field:N` and `--sort-by`
+    delimiter: Regex,
+    sort_keys: Vec<SortKey>,
+    // index of the sort key in use, `sort_keys.len()` to sort by the criterion only
+    sort_idx: AtomicUsize,
 }
 
 impl Default for RankBuilder {
     fn default() -> Self {
-        Self {
-            criterion: vec![RankCriteria::Score, RankCriteria::Begin, RankCriteria::End],
-        }
+        Self::new(vec![RankCriteria::Score, RankCriteria::Begin, RankCriteria::End])
     }
 }
 
@@ -31,42 +182,271 @@ impl RankBuilder {
         }
 
         criterion.dedup();
-        Self { criterion }
+        let num_keys = criterion
+            .iter()
+            .take(RANK_KEYS)
+            .take_while(|criteria| !matches!(criteria, RankCriteria::Field(_) | RankCriteria::NegField(_)))
+            .count();
+        Self {
+            criterion,
+            num_keys,
+            delimiter: DEFAULT_DELIMITER.clone(),
+            sort_keys: Vec::new(),
+            sort_idx: AtomicUsize::new(0),
+        }
     }
 
-    /// score: the greater the better
-    pub fn build_rank(&self, score: i32, begin: usize, end: usize, length: usize, item_idx: usize) -> Rank {
-        let mut rank = [0; 4];
-        let begin = begin as i32;
-        let end = end as i32;
-        let length = length as i32;
-        let item_idx = item_idx as i32;
+    /// the delimiter of the fields of `field:N` and of the sort keys
+    pub fn delimiter(mut self, delimiter: Regex) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// the fields to sort by, in turn (see `toggle_sort`), the first one is used
+    pub fn sort_keys(mut self, sort_keys: Vec<SortKey>) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
 
-        self.criterion.iter().take(4).enumerate().for_each(|(index, criteria)| {
-            let value = match criteria {
-                RankCriteria::Index => item_idx,
-                RankCriteria::NegIndex => -item_idx,
-                RankCriteria::Score => -score,
-                RankCriteria::Begin => begin,
-                RankCriteria::End => end,
-                RankCriteria::NegScore => score,
-                RankCriteria::NegBegin => -begin,
-                RankCriteria::NegEnd => -end,
-                RankCriteria::Length => length,
-                RankCriteria::NegLength => -length,
-            };
-
-            rank[index] = value;
-        });
+    /// criterion from `--tiebreak`, or the default ones, and the sort keys of `--sort-by`
+    pub fn with_options(options: &SkimOptions) -> Self {
+        let rank_builder = match options.tiebreak.as_deref() {
+            Some(tie_breaker) => Self::new(tie_breaker.split(',').filter_map(parse_criteria).collect()),
+            None => Self::default(),
+        };
+
+        let field_names: Vec<&str> = options
+            .field_names
+            .map(|names| names.split(',').collect())
+            .unwrap_or_default();
+        let sort_keys = options
+            .sort_by
+            .map(|specs| {
+                specs
+                    .split(',')
+                    .filter_map(|spec| SortKey::parse(spec, &field_names))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rank_builder = rank_builder.sort_keys(sort_keys);
+
+        match options.delimiter.and_then(|delimiter| Regex::new(delimiter).ok()) {
+            Some(delimiter) => rank_builder.delimiter(delimiter),
+            None => rank_builder,
+        }
+    }
+
+    /// the sort key in use, `None` if the items are sorted by the criterion
+    pub fn sort_key(&self) -> Option<&SortKey> {
+        self.sort_keys.get(self.sort_idx.load(Ordering::Relaxed))
+    }
+
+    /// use the next sort key, then the criterion only, then the first sort key again.
+    /// Returns false if there are no sort keys to toggle.
+    pub fn toggle_sort(&self) -> bool {
+        if self.sort_keys.is_empty() {
+            return false;
+        }
+
+        let sort_idx = (self.sort_idx.load(Ordering::Relaxed) + 1) % (self.sort_keys.len() + 1);
+        self.sort_idx.store(sort_idx, Ordering::Relaxed);
+        true
+    }
+
+    /// the rank of an item that is not matched, only sorted by the sort key in use
+    pub fn sort_rank(&self, text: &str) -> Rank {
+        Rank {
+            sort: self.sort_field(text),
+            ..Rank::default()
+        }
+    }
+
+    /// use the sort key in use in the rank of an item matched before `toggle_sort`
+    pub fn update_sort(&self, matched: &mut MatchedItem) {
+        matched.rank.sort = self.sort_field(&matched.upgrade_infallible().text());
+    }
+
+    fn sort_field(&self, text: &str) -> Option<Box<RankKey>> {
+        self.sort_key()
+            .map(|sort_key| Box::new(self.field_key(sort_key.field, sort_key.order, sort_key.desc, text)))
+    }
+
+    /// score: the greater the better, begin/end: the byte range of `text` that is matched
+    pub fn build_rank(&self, score: i32, begin: usize, end: usize, text: &str, item_idx: usize) -> Rank {
+        let mut rank = self.sort_rank(text);
+        rank.score = score;
+        for (index, criteria) in self.criterion.iter().take(self.num_keys).enumerate() {
+            rank.keys[index] = self.num_value(criteria, score, begin, end, text, item_idx).unwrap_or(0);
+        }
+
+        if self.criterion.len() > self.num_keys {
+            let more = self.criterion[self.num_keys..]
+                .iter()
+                .map(|criteria| match criteria {
+                    RankCriteria::Field(field) => self.field_key(*field, FieldOrder::Auto, false, text),
+                    RankCriteria::NegField(field) => self.field_key(*field, FieldOrder::Auto, true, text),
+                    _ => RankKey::Num(self.num_value(criteria, score, begin, end, text, item_idx).unwrap_or(0) as f64),
+                })
+                .collect();
+            rank.more = Some(more);
+        }
 
         rank
     }
+
+    fn num_value(
+        &self,
+        criteria: &RankCriteria,
+        score: i32,
+        begin: usize,
+        end: usize,
+        text: &str,
+        item_idx: usize,
+    ) -> Option<i32> {
+        let begin_i32 = begin as i32;
+        let end_i32 = end as i32;
+        let length = text.len() as i32;
+        let item_idx = item_idx as i32;
+
+        let value = match criteria {
+            RankCriteria::Index => item_idx,
+            RankCriteria::NegIndex => -item_idx,
+            RankCriteria::Score => -score,
+            RankCriteria::Begin => begin_i32,
+            RankCriteria::End => end_i32,
+            RankCriteria::NegScore => score,
+            RankCriteria::NegBegin => -begin_i32,
+            RankCriteria::NegEnd => -end_i32,
+            RankCriteria::Length => length,
+            RankCriteria::NegLength => -length,
+            RankCriteria::Pathname => path_depth(text),
+            RankCriteria::NegPathname => -path_depth(text),
+            RankCriteria::Chunk => chunk_length(text, begin, end),
+            RankCriteria::NegChunk => -chunk_length(text, begin, end),
+            RankCriteria::LengthOfMatch => match_length(text, begin, end),
+            RankCriteria::NegLengthOfMatch => -match_length(text, begin, end),
+            RankCriteria::Field(_) | RankCriteria::NegField(_) => return None,
+        };
+        Some(value)
+    }
+
+    // the value of a field, compared as `order` says
+    fn field_key(&self, field: i32, order: FieldOrder, desc: bool, text: &str) -> RankKey {
+        let value = match get_string_by_field(&self.delimiter, text, &FieldRange::Single(field)).map(str::trim) {
+            None | Some("") => return RankKey::Missing,
+            Some(value) => value,
+        };
+
+        let num = match order {
+            FieldOrder::Lex => None,
+            _ => value.parse::<f64>().ok(),
+        };
+        match num {
+            Some(num) if desc => RankKey::Num(-num),
+            Some(num) => RankKey::Num(num),
+            None if order == FieldOrder::Num => RankKey::Missing,
+            None if desc => RankKey::RevText(value.into()),
+            None => RankKey::Text(value.into()),
+        }
+    }
+}
+
+// the number of directories of a path
+fn path_depth(text: &str) -> i32 {
+    text.trim_end_matches('/').matches('/').count() as i32
+}
+
+// the byte range `begin..end` of `text`, within the text and on char boundaries
+fn char_bounds(text: &str, begin: usize, end: usize) -> (usize, usize) {
+    let floor = |mut idx: usize| {
+        idx = min(idx, text.len());
+        while !text.is_char_boundary(idx) {
+            idx -= 1;
+        }
+        idx
+    };
+    let begin = floor(begin);
+    (begin, floor(end).max(begin))
+}
+
+// the number of chars matched
+fn match_length(text: &str, begin: usize, end: usize) -> i32 {
+    let (begin, end) = char_bounds(text, begin, end);
+    text[begin..end].chars().count() as i32
+}
+
+// the number of chars of the whitespace delimited chunk of `text` containing the match
+fn chunk_length(text: &str, begin: usize, end: usize) -> i32 {
+    let (begin, end) = char_bounds(text, begin, end);
+
+    let start = text[..begin]
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| ch.is_whitespace())
+        .map_or(0, |(idx, ch)| idx + ch.len_utf8());
+    let stop = text[end..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |idx| end + idx);
+    text[start..stop].chars().count() as i32
+}
+
+//------------------------------------------------------------------------------
+/// A weak reference to an item of the pool, either a standalone item or a line of an `ItemChunk`
+#[derive(Clone)]
+pub enum ItemRef {
+    Item(Weak<dyn SkimItem>),
+    Line(Weak<ChunkData>, u32),
+}
+
+impl ItemRef {
+    pub fn upgrade(&self) -> Option<Arc<dyn SkimItem>> {
+        match self {
+            ItemRef::Item(item) => item.upgrade(),
+            ItemRef::Line(data, idx) => ChunkLine::upgrade(data, *idx).map(|line| Arc::new(line) as Arc<dyn SkimItem>),
+        }
+    }
+}
+
+impl From<&Arc<dyn SkimItem>> for ItemRef {
+    fn from(item: &Arc<dyn SkimItem>) -> Self {
+        ItemRef::Item(Arc::downgrade(item))
+    }
+}
+
+/// A strong reference to an item of the pool
+pub enum PoolItem {
+    Item(Arc<dyn SkimItem>),
+    Line(ChunkLine),
+}
+
+impl PoolItem {
+    pub fn as_item(&self) -> &dyn SkimItem {
+        match self {
+            PoolItem::Item(item) => item.as_ref(),
+            PoolItem::Line(line) => line,
+        }
+    }
+}
+
+/// whether both are the same item, lines of a chunk are upgraded to a new `Arc` every time
+pub fn same_item(a: &Arc<dyn SkimItem>, b: &Arc<dyn SkimItem>) -> bool {
+    if Arc::ptr_eq(a, b) {
+        return true;
+    }
+
+    match (
+        a.as_any().downcast_ref::<ChunkLine>(),
+        b.as_any().downcast_ref::<ChunkLine>(),
+    ) {
+        (Some(a), Some(b)) => a.same_line(b),
+        _ => false,
+    }
 }
 
 //------------------------------------------------------------------------------
 #[derive(Clone)]
 pub struct MatchedItem {
-    pub item: Weak<dyn SkimItem>,
+    pub item: ItemRef,
     pub rank: Rank,
     pub matched_range: Option<MatchRange>, // range of chars that matched the pattern
     pub item_idx: u32,
@@ -80,9 +460,10 @@ impl MatchedItem {
 
 use std::cmp::Ordering as CmpOrd;
 
+// ordered by rank, then by input order, so that the order does not depend on how they are sorted
 impl PartialEq for MatchedItem {
     fn eq(&self, other: &Self) -> bool {
-        self.rank.eq(&other.rank)
+        self.cmp(other) == CmpOrd::Equal
     }
 }
 
@@ -90,28 +471,183 @@ impl std::cmp::Eq for MatchedItem {}
 
 impl PartialOrd for MatchedItem {
     fn partial_cmp(&self, other: &Self) -> Option<CmpOrd> {
-        self.rank.partial_cmp(&other.rank)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for MatchedItem {
     fn cmp(&self, other: &Self) -> CmpOrd {
-        self.rank.cmp(&other.rank)
+        (&self.rank, self.item_idx).cmp(&(&other.rank, other.item_idx))
     }
 }
 
 //------------------------------------------------------------------------------
+enum Segment {
+    Items(Vec<Arc<dyn SkimItem>>),
+    Lines(ItemChunk),
+}
+
+impl Segment {
+    fn len(&self) -> usize {
+        match self {
+            Segment::Items(items) => items.len(),
+            Segment::Lines(chunk) => chunk.len(),
+        }
+    }
+}
+
+/// Items stored in order, the lines of an `ItemChunk` are kept in the chunk
+#[derive(Default)]
+pub struct ItemSegments {
+    // (index of the first item, items)
+    segments: Vec<(usize, Segment)>,
+    length: usize,
+    // index of the first item not evicted
+    first: usize,
+}
+
+impl ItemSegments {
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// index of the oldest item kept, the ones before it were evicted
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.length = 0;
+        self.first = 0;
+    }
+
+    /// evict the items before index `first`. The segments are dropped once all their items are
+    /// evicted, the other evicted items are skipped.
+    pub fn evict(&mut self, first: usize) {
+        if first <= self.first {
+            return;
+        }
+
+        self.first = min(first, self.length);
+        let num_evicted = self
+            .segments
+            .partition_point(|(seg_start, segment)| seg_start + segment.len() <= self.first);
+        self.segments.drain(..num_evicted);
+    }
+
+    pub fn push(&mut self, item: Arc<dyn SkimItem>) {
+        let start = self.length;
+        if let Some(chunk) = item.as_any().downcast_ref::<ItemChunk>() {
+            if !chunk.is_empty() {
+                self.length += chunk.len();
+                self.segments.push((start, Segment::Lines(chunk.clone())));
+            }
+            return;
+        }
 
-use crate::reader::ITEMS_INITIAL_CAPACITY;
+        self.length += 1;
+        match self.segments.last_mut() {
+            Some((_, Segment::Items(items))) => items.push(item),
+            _ => self.segments.push((start, Segment::Items(vec![item]))),
+        }
+    }
+
+    pub fn append(&mut self, items: &mut Vec<Arc<dyn SkimItem>>) {
+        items.drain(..).for_each(|item| self.push(item));
+    }
+
+    /// the items from index `start` on, in slices of at most `max_len` items, together with the
+    /// index of their first item
+    pub fn slices(&self, start: usize, max_len: usize) -> Vec<(usize, ItemSlice<'_>)> {
+        let start = max(start, self.first);
+        let first = self.segments.partition_point(|(seg_start, _)| *seg_start <= start);
+        let mut slices = Vec::new();
+        for (seg_start, segment) in self.segments[first.saturating_sub(1)..].iter() {
+            let mut begin = start.saturating_sub(*seg_start);
+            while begin < segment.len() {
+                let end = min(begin + max_len, segment.len());
+                let slice = match segment {
+                    Segment::Items(items) => ItemSlice::Items(&items[begin..end]),
+                    Segment::Lines(chunk) => ItemSlice::Lines(chunk, begin, end),
+                };
+                slices.push((seg_start + begin, slice));
+                begin = end;
+            }
+        }
+        slices
+    }
+}
+
+/// Consecutive items of an `ItemSegments`
+pub enum ItemSlice<'a> {
+    Items(&'a [Arc<dyn SkimItem>]),
+    Lines(&'a ItemChunk, usize, usize),
+}
+
+impl ItemSlice<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            ItemSlice::Items(items) => items.len(),
+            ItemSlice::Lines(_, begin, end) => end - begin,
+        }
+    }
 
+    /// match the `idx`th item of the slice, the lines of a chunk are matched in place unless they
+    /// are labeled with a source, which the engine may match too
+    pub fn match_item(&self, engine: &dyn MatchEngine, idx: usize, item_idx: usize) -> Option<MatchResult> {
+        match self {
+            ItemSlice::Items(items) => engine.match_item(items[idx].as_ref(), item_idx),
+            ItemSlice::Lines(chunk, begin, _) if chunk.source().is_some() => {
+                engine.match_item(&chunk.get(begin + idx), item_idx)
+            }
+            ItemSlice::Lines(chunk, begin, _) => engine.match_text(chunk.line(begin + idx), None, item_idx),
+        }
+    }
+
+    pub fn item_ref(&self, idx: usize) -> ItemRef {
+        match self {
+            ItemSlice::Items(items) => ItemRef::from(&items[idx]),
+            ItemSlice::Lines(chunk, begin, _) => {
+                let (data, line) = chunk.downgrade(begin + idx);
+                ItemRef::Line(data, line)
+            }
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> PoolItem {
+        match self {
+            ItemSlice::Items(items) => PoolItem::Item(items[idx].clone()),
+            ItemSlice::Lines(chunk, begin, _) => PoolItem::Line(chunk.get(begin + idx)),
+        }
+    }
+}
+
+// the items with the lines of the chunks as standalone items
+fn standalone_items(items: &[Arc<dyn SkimItem>]) -> impl Iterator<Item = Arc<dyn SkimItem>> + '_ {
+    items
+        .iter()
+        .flat_map(|item| -> Box<dyn Iterator<Item = Arc<dyn SkimItem>> + '_> {
+            match item.as_any().downcast_ref::<ItemChunk>() {
+                Some(chunk) => Box::new((0..chunk.len()).map(move |idx| Arc::new(chunk.get(idx)) as Arc<dyn SkimItem>)),
+                None => Box::new(std::iter::once(item.clone())),
+            }
+        })
+}
+
+//------------------------------------------------------------------------------
 pub struct ItemPool {
     length: AtomicUsize,
-    pool: SpinLock<Vec<Arc<dyn SkimItem>>>,
+    pool: SpinLock<ItemSegments>,
     /// number of items that was `take`n
     taken: AtomicUsize,
+    /// number of items evicted, the oldest ones
+    evicted: AtomicUsize,
+    /// keep only the newest N items
+    tail: Option<usize>,
 
-    /// reverse first N lines as header
-    reserved_items: SpinLock<Vec<Weak<dyn SkimItem>>>,
+    /// reverse first N lines as header, kept when evicted
+    reserved_items: SpinLock<Vec<Arc<dyn SkimItem>>>,
     lines_to_reserve: usize,
 }
 
@@ -135,8 +671,10 @@ impl ItemPool {
     pub fn new() -> Self {
         Self {
             length: AtomicUsize::new(0),
-            pool: SpinLock::new(Vec::with_capacity(ITEMS_INITIAL_CAPACITY)),
+            pool: SpinLock::new(ItemSegments::default()),
             taken: AtomicUsize::new(0),
+            evicted: AtomicUsize::new(0),
+            tail: None,
             reserved_items: SpinLock::new(Vec::new()),
             lines_to_reserve: 0,
         }
@@ -147,16 +685,25 @@ impl ItemPool {
         self
     }
 
+    /// keep only the newest `tail` items, the older ones are evicted as new ones are appended
+    pub fn tail(mut self, tail: Option<usize>) -> Self {
+        self.tail = tail;
+        self
+    }
+
+    /// number of items kept
     pub fn len(&self) -> usize {
-        self.length.load(Ordering::SeqCst)
+        self.length.load(Ordering::SeqCst) - self.evicted.load(Ordering::SeqCst)
     }
 
-    pub fn num_not_taken(&self) -> usize {
-        self.length.load(Ordering::SeqCst) - self.taken.load(Ordering::SeqCst)
+    /// index of the oldest item kept, the items before it were evicted
+    pub fn num_evicted(&self) -> usize {
+        self.evicted.load(Ordering::SeqCst)
     }
 
-    pub fn num_taken(&self) -> usize {
-        self.taken.load(Ordering::SeqCst)
+    pub fn num_not_taken(&self) -> usize {
+        let taken = max(self.taken.load(Ordering::SeqCst), self.evicted.load(Ordering::SeqCst));
+        self.length.load(Ordering::SeqCst) - taken
     }
 
     pub fn clear(&self) {
@@ -165,9 +712,17 @@ impl ItemPool {
         let mut header_items = self.reserved_items.lock();
         header_items.clear();
         self.taken.store(0, Ordering::SeqCst);
+        self.evicted.store(0, Ordering::SeqCst);
         self.length.store(0, Ordering::SeqCst);
     }
 
+    /// clear the pool and return its items, e.g. to drop them once they are not displayed anymore
+    pub fn drain(&self) -> ItemSegments {
+        let items = std::mem::take(&mut *self.pool.lock());
+        self.clear();
+        items
+    }
+
     pub fn reset(&self) {
         // lock to ensure consistency
         let _items = self.pool.lock();
@@ -183,27 +738,40 @@ impl ItemPool {
 
         let to_reserve = self.lines_to_reserve - header_items.len();
         if to_reserve > 0 {
-            let to_reserve = min(to_reserve, items.len());
-            let mut reserved_pool = items[..to_reserve].to_vec();
+            // header lines are kept as standalone items
+            let mut reserved_pool: Vec<Arc<dyn SkimItem>> = standalone_items(items).take(to_reserve).collect();
+            header_items.extend(reserved_pool.iter().cloned());
             pool.append(&mut reserved_pool);
-            let mut reserved_header: Vec<Weak<dyn SkimItem>> = reserved_pool.iter().map(Arc::downgrade).collect();
-            header_items.append(&mut reserved_header);
         } else {
             pool.append(items);
         }
 
-        self.length.store(pool.len(), Ordering::SeqCst);
+        let new_len = pool.len();
+        self.length.store(new_len, Ordering::SeqCst);
+        if let Some(tail) = self.tail {
+            pool.evict(new_len.saturating_sub(tail));
+            self.evicted.store(pool.first(), Ordering::SeqCst);
+        }
         trace!("item pool, done append {} items", len);
         pool.len()
     }
 
-    pub fn take(&self) -> ItemPoolGuard<'_, Arc<dyn SkimItem>> {
+    /// the items not taken yet, `None` if `stopped` is set. A matcher killed before it starts must
+    /// not take the items, they are for the one replacing it once the pool is `reset`.
+    pub fn take(&self, stopped: &AtomicBool) -> Option<ItemPoolTaken<'_>> {
         let guard = self.pool.lock();
+        if stopped.load(Ordering::SeqCst) {
+            return None;
+        }
+
         let taken = self.taken.swap(guard.len(), Ordering::SeqCst);
-        ItemPoolGuard { guard, start: taken }
+        Some(ItemPoolTaken {
+            start: max(taken, guard.first()),
+            guard,
+        })
     }
 
-    pub fn reserved(&self) -> ItemPoolGuard<'_, Weak<dyn SkimItem>> {
+    pub fn reserved(&self) -> ItemPoolGuard<'_, Arc<dyn SkimItem>> {
         let guard = self.reserved_items.lock();
         ItemPoolGuard { guard, start: 0 }
     }
@@ -222,6 +790,22 @@ impl<'mutex, T: Sized> Deref for ItemPoolGuard<'mutex, T> {
     }
 }
 
+/// the items not taken before
+pub struct ItemPoolTaken<'a> {
+    guard: SpinLockGuard<'a, ItemSegments>,
+    start: usize,
+}
+
+impl ItemPoolTaken<'_> {
+    pub fn len(&self) -> usize {
+        self.guard.len() - self.start
+    }
+
+    pub fn slices(&self, max_len: usize) -> Vec<(usize, ItemSlice<'_>)> {
+        self.guard.slices(self.start, max_len)
+    }
+}
+
 //------------------------------------------------------------------------------
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RankCriteria {
@@ -235,10 +819,26 @@ pub enum RankCriteria {
     NegEnd,
     Length,
     NegLength,
+    Pathname,
+    NegPathname,
+    Chunk,
+    NegChunk,
+    LengthOfMatch,
+    NegLengthOfMatch,
+    Field(i32),
+    NegField(i32),
 }
 
 pub fn parse_criteria(text: &str) -> Option<RankCriteria> {
-    match text.to_lowercase().as_ref() {
+    let text = text.to_lowercase();
+    if let Some(field) = text.strip_prefix("field:") {
+        return field.parse().ok().map(RankCriteria::Field);
+    }
+    if let Some(field) = text.strip_prefix("-field:") {
+        return field.parse().ok().map(RankCriteria::NegField);
+    }
+
+    match text.as_ref() {
         "score" => Some(RankCriteria::Score),
         "begin" => Some(RankCriteria::Begin),
         "end" => Some(RankCriteria::End),
@@ -249,6 +849,201 @@ pub fn parse_criteria(text: &str) -> Option<RankCriteria> {
         "-length" => Some(RankCriteria::NegLength),
         "index" => Some(RankCriteria::Index),
         "-index" => Some(RankCriteria::NegIndex),
+        "pathname" => Some(RankCriteria::Pathname),
+        "-pathname" => Some(RankCriteria::NegPathname),
+        "chunk" => Some(RankCriteria::Chunk),
+        "-chunk" => Some(RankCriteria::NegChunk),
+        "length-of-match" => Some(RankCriteria::LengthOfMatch),
+        "-length-of-match" => Some(RankCriteria::NegLengthOfMatch),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(lines: &[&str]) -> Arc<dyn SkimItem> {
+        Arc::new(ItemChunk::from_lines(lines))
+    }
+
+    fn texts(slice: &ItemSlice) -> Vec<String> {
+        (0..slice.len())
+            .map(|idx| slice.get(idx).as_item().text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_criterion() {
+        let criterion = "score,field:2,-field:3,pathname,chunk,length-of-match,-index";
+        let rank_builder = RankBuilder::new(criterion.split(',').filter_map(parse_criteria).collect());
+        assert_eq!(rank_builder.num_keys, 1);
+
+        let rank = |text: &str, begin, end, item_idx| rank_builder.build_rank(7, begin, end, text, item_idx);
+        let a = rank("x 10 b dir/file.txt", 13, 15, 0);
+        assert_eq!(a.score(), 7);
+        assert_eq!(
+            a.more.as_deref().unwrap(),
+            [
+                RankKey::Num(10.0),
+                RankKey::RevText("b".into()),
+                RankKey::Num(1.0),
+                RankKey::Num(12.0),
+                RankKey::Num(2.0),
+                RankKey::Num(0.0),
+            ]
+        );
+
+        // ascending numbers, numbers before text, text before missing fields
+        assert!(rank("x 9 b", 0, 0, 1) < a);
+        assert!(a < rank("x abc b", 0, 0, 2));
+        assert!(rank("x abc b", 0, 0, 2) < rank("x", 0, 0, 3));
+        // descending text, then ascending depth
+        assert!(rank("x 10 c", 0, 0, 4) < a);
+        assert!(rank("x 10 b file", 0, 0, 5) < a);
+        // -index: the later the better
+        assert!(rank("x 10 b dir/file.txt", 13, 15, 6) < a);
+        // the higher the score the better
+        assert!(rank_builder.build_rank(8, 0, 0, "x 99", 0) < a);
+
+        // more than four criterion are all taken into account
+        let criterion = "score,begin,end,length,-index";
+        let rank_builder = RankBuilder::new(criterion.split(',').filter_map(parse_criteria).collect());
+        assert!(rank_builder.build_rank(0, 0, 0, "a", 2) < rank_builder.build_rank(0, 0, 0, "b", 1));
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        assert_eq!(SortKey::parse("2:lex", &[]).map(|key| key.field), Some(2));
+        assert_eq!(
+            SortKey::parse("size:num:desc", &["name", "size"]).map(|key| key.field),
+            Some(2)
+        );
+        assert!(SortKey::parse("size", &[]).is_none());
+        assert!(SortKey::parse("2:up", &[]).is_none());
+
+        let sort_keys = ["2:num:desc", "2:lex:desc"]
+            .iter()
+            .filter_map(|spec| SortKey::parse(spec, &[]))
+            .collect();
+        let rank_builder = RankBuilder::default().sort_keys(sort_keys);
+        let sorted = |rank_builder: &RankBuilder| {
+            let mut texts = ["a 9", "b 10", "c", "d x", "e 10"];
+            texts.sort_by_cached_key(|text| rank_builder.build_rank(text.len() as i32, 0, 0, text, 0));
+            texts
+        };
+
+        assert_eq!(rank_builder.sort_key().unwrap().to_string(), "2:num:desc");
+        // the items without a number come last, by score
+        assert_eq!(sorted(&rank_builder), ["b 10", "e 10", "a 9", "d x", "c"]);
+        assert!(rank_builder.toggle_sort());
+        assert_eq!(sorted(&rank_builder), ["d x", "a 9", "b 10", "e 10", "c"]);
+        // by score only
+        assert!(rank_builder.toggle_sort());
+        assert!(rank_builder.sort_key().is_none());
+        assert_eq!(sorted(&rank_builder), ["b 10", "e 10", "a 9", "d x", "c"]);
+        assert!(rank_builder.toggle_sort());
+        assert_eq!(rank_builder.sort_key().unwrap().to_string(), "2:num:desc");
+
+        assert!(!RankBuilder::default().toggle_sort());
+    }
+
+    #[test]
+    fn test_chunk_length() {
+        assert_eq!(chunk_length("ab cdé fg", 4, 5), 3);
+        assert_eq!(chunk_length("ab cdé fg", 0, 9), 9);
+        assert_eq!(chunk_length("abc", 5, 5), 3);
+        // not a char boundary
+        assert_eq!(chunk_length("é é", 1, 1), 1);
+    }
+
+    #[test]
+    fn test_multibyte_match() {
+        use crate::engine::exact::{ExactEngine, ExactMatchingParam};
+        use crate::engine::fuzzy::FuzzyEngine;
+
+        // the fuzzy engine matches chars and the exact one bytes, the ranks use the same unit
+        let criterion = vec![RankCriteria::Begin, RankCriteria::LengthOfMatch, RankCriteria::Chunk];
+        let rank_builder = Arc::new(RankBuilder::new(criterion));
+        let fuzzy = FuzzyEngine::builder()
+            .query("foo")
+            .rank_builder(rank_builder.clone())
+            .build();
+        let exact = ExactEngine::builder("foo", ExactMatchingParam::default())
+            .rank_builder(rank_builder)
+            .build();
+        let keys = |engine: &dyn MatchEngine| {
+            engine
+                .match_item(&"éé xfooé y", 0)
+                .map(|res| res.rank.keys[1..].to_vec())
+        };
+        assert_eq!(keys(&fuzzy), Some(vec![6, 3, 5]));
+        assert_eq!(keys(&exact), keys(&fuzzy));
+        assert_eq!(match_length("é é", 1, 4), 2);
+    }
+
+    #[test]
+    fn test_item_segments() {
+        let mut segments = ItemSegments::default();
+        segments.append(&mut vec![
+            chunk(&["a", "b", "c"]),
+            Arc::new("d"),
+            Arc::new("e"),
+            chunk(&[]),
+            chunk(&["f", "g"]),
+        ]);
+        assert_eq!(segments.len(), 7);
+
+        let slices = segments.slices(0, 2);
+        let starts: Vec<usize> = slices.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, [0, 2, 3, 5]);
+        assert_eq!(texts(&slices[0].1), ["a", "b"]);
+        assert_eq!(texts(&slices[1].1), ["c"]);
+        assert_eq!(texts(&slices[2].1), ["d", "e"]);
+        assert_eq!(texts(&slices[3].1), ["f", "g"]);
+
+        let slices = segments.slices(4, 8);
+        let starts: Vec<usize> = slices.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, [4, 5]);
+        assert_eq!(texts(&slices[0].1), ["e"]);
+        assert!(segments.slices(7, 8).is_empty());
+
+        let item = slices[1].1.item_ref(1).upgrade().unwrap();
+        assert_eq!(item.text(), "g");
+        assert!(same_item(&item, &slices[1].1.item_ref(1).upgrade().unwrap()));
+        assert!(!same_item(&item, &slices[1].1.item_ref(0).upgrade().unwrap()));
+    }
+
+    #[test]
+    fn test_item_pool_header_lines() {
+        let pool = ItemPool::new().lines_to_reserve(2);
+        pool.append(&mut vec![chunk(&["h1", "h2", "x"])]);
+        let header: Vec<String> = pool.reserved().iter().map(|item| item.text().to_string()).collect();
+        assert_eq!(header, ["h1", "h2"]);
+    }
+
+    #[test]
+    fn test_item_pool_tail() {
+        let pool = ItemPool::new().tail(Some(3));
+        pool.append(&mut vec![chunk(&["a", "b"]), Arc::new("c")]);
+        assert_eq!((pool.len(), pool.num_evicted()), (3, 0));
+        let stopped = AtomicBool::new(false);
+        assert_eq!(pool.take(&stopped).unwrap().len(), 3);
+
+        // the chunk of "a" and "b" is kept as long as "b" is
+        pool.append(&mut vec![Arc::new("d")]);
+        assert_eq!((pool.len(), pool.num_evicted(), pool.num_not_taken()), (3, 1, 1));
+        pool.append(&mut vec![chunk(&["e", "f"])]);
+        assert_eq!((pool.len(), pool.num_evicted(), pool.num_not_taken()), (3, 3, 3));
+
+        // a matcher killed before it starts leaves the items to the next one
+        pool.reset();
+        assert!(pool.take(&AtomicBool::new(true)).is_none());
+        let taken = pool.take(&stopped).unwrap();
+        let slices = taken.slices(8);
+        let starts: Vec<usize> = slices.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, [3, 4]);
+        assert_eq!(texts(&slices[0].1), ["d"]);
+        assert_eq!(texts(&slices[1].1), ["e", "f"]);
+    }
+}
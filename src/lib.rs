@@ -4,6 +4,8 @@ extern crate log;
 use std::any::Any;
 use std::borrow::Cow;
 use std::fmt::Display;
+use std::io;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
 
@@ -11,13 +13,20 @@ use crossbeam_channel::{Receiver, Sender, unbounded};
 use tuikit::prelude::{Event as TermEvent, *};
 
 pub use crate::ansi::AnsiString;
+pub use crate::arena::{ChunkLine, ItemChunk};
+use crate::engine::factory::RegexEngineFactory;
 pub use crate::engine::fuzzy::FuzzyAlgorithm;
 use crate::event::{EventReceiver, EventSender};
+use crate::item::RankBuilder;
+pub use crate::item::{Rank, RankKey};
+use crate::matcher::Matcher;
 use crate::model::Model;
 pub use crate::options::SkimOptions;
 pub use crate::output::SkimOutput;
-pub use crate::reader::CommandCollector;
 use crate::reader::Reader;
+pub use crate::reader::{CommandCollector, default_command, default_walker};
+pub use crate::shell::{Quoting, Shell};
+pub use crate::translit::{ShadowText, Translit};
 
 #[cfg(feature = "malloc_trim")]
 #[cfg(target_os = "linux")]
@@ -25,6 +34,7 @@ use crate::reader::Reader;
 use libc as raw_libc;
 
 mod ansi;
+mod arena;
 mod engine;
 mod event;
 pub mod field;
@@ -43,8 +53,10 @@ mod previewer;
 mod query;
 mod reader;
 mod selection;
+mod shell;
 mod spinlock;
 mod theme;
+mod translit;
 mod util;
 
 //------------------------------------------------------------------------------
@@ -126,11 +138,36 @@ pub trait SkimItem: AsAny + Send + Sync + 'static {
         self.text()
     }
 
+    /// The bytes to output, default to `output()`. Differs from it for the items that were read
+    /// from input that is not valid UTF-8, which is output as it was read.
+    fn output_bytes(&self) -> Cow<'_, [u8]> {
+        match self.output() {
+            Cow::Borrowed(output) => Cow::Borrowed(output.as_bytes()),
+            Cow::Owned(output) => Cow::Owned(output.into_bytes()),
+        }
+    }
+
+    /// The text that the fields of placeholders such as `{1}` are taken from, default to
+    /// `output()`. Differs from it for structured records, whose fields are separated by tabs.
+    fn field_text(&self) -> Cow<'_, str> {
+        self.output()
+    }
+
     /// we could limit the matching ranges of the `get_text` of the item.
     /// providing (start_byte, end_byte) of the range
     fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
         None
     }
+
+    /// The romanized text of the item (see `Translit`), matched if `text` does not match
+    fn shadow_text(&self) -> Option<&ShadowText> {
+        None
+    }
+
+    /// The label of the source the item was read from, if there are several (see `--source`)
+    fn source(&self) -> Option<&str> {
+        None
+    }
 }
 
 //------------------------------------------------------------------------------
@@ -144,10 +181,17 @@ impl<T: AsRef<str> + Send + Sync + 'static> SkimItem for T {
 
 //------------------------------------------------------------------------------
 // Display Context
+
+/// The matched part of the text of an item, to highlight it.
+///
+/// New kinds of matches may be added, the code outside of skim has to match it with a `_` arm.
+#[non_exhaustive]
 pub enum Matches<'a> {
     CharIndices(&'a [usize]),
     CharRange(usize, usize),
     ByteRange(usize, usize),
+    // non-overlapping byte ranges, ordered by their start, highlighted with their own attribute
+    ByteRanges(&'a [(Attr, (usize, usize))]),
 }
 
 pub struct DisplayContext<'a> {
@@ -166,18 +210,31 @@ impl<'a> From<DisplayContext<'a>> for AnsiString {
                 AnsiString::new_str(context.text, vec![(context.highlight_attr, (start as u32, end as u32))])
             }
             Some(Matches::ByteRange(start, end)) => {
-                let ch_start = context.text[..start].len();
-                let ch_end = ch_start + context.text[start..end].len();
+                let ch_start = context.text[..start].chars().count();
+                let ch_end = ch_start + context.text[start..end].chars().count();
                 AnsiString::new_str(
                     context.text,
                     vec![(context.highlight_attr, (ch_start as u32, ch_end as u32))],
                 )
             }
+            Some(Matches::ByteRanges(ranges)) => AnsiString::new_str(context.text, char_ranges(context.text, ranges)),
             None => AnsiString::new_str(context.text, vec![]),
         }
     }
 }
 
+/// convert the byte ranges of `Matches::ByteRanges` into the char ranges of `AnsiString` fragments
+fn char_ranges(text: &str, ranges: &[(Attr, (usize, usize))]) -> Vec<(Attr, (u32, u32))> {
+    ranges
+        .iter()
+        .map(|&(attr, (start, end))| {
+            let ch_start = text[..start].chars().count();
+            let ch_end = ch_start + text[start..end].chars().count();
+            (attr, (ch_start as u32, ch_end as u32))
+        })
+        .collect()
+}
+
 //------------------------------------------------------------------------------
 // Preview Context
 
@@ -229,16 +286,21 @@ pub enum CaseMatching {
     Smart,
 }
 
+/// The part of the text that a `MatchEngine` matched.
+///
+/// New kinds of matches may be added, the code outside of skim has to match it with a `_` arm.
 #[derive(PartialEq, Eq, Clone, Debug)]
 #[allow(dead_code)]
+#[non_exhaustive]
 pub enum MatchRange {
     ByteRange(usize, usize),
     // range of bytes
     Chars(Box<[usize]>), // individual character indices matched
+    // byte ranges of the whole match and of every capture group of a regex, empty if the
+    // group did not participate in the match
+    Groups(Box<[(usize, usize)]>),
 }
 
-pub type Rank = [i32; 4];
-
 #[derive(Clone)]
 pub struct MatchResult {
     pub rank: Rank,
@@ -249,21 +311,65 @@ impl MatchResult {
     pub fn range_char_indices(&self, text: &str) -> Vec<usize> {
         match &self.matched_range {
             &MatchRange::ByteRange(start, end) => {
-                let first = text[..start].len();
-                let last = first + text[start..end].len();
+                let first = text[..start].chars().count();
+                let last = first + text[start..end].chars().count();
                 (first..last).collect()
             }
             MatchRange::Chars(vec) => vec.clone().into(),
+            MatchRange::Groups(groups) => {
+                let (start, end) = groups[0];
+                let first = text[..start].chars().count();
+                let last = first + text[start..end].chars().count();
+                (first..last).collect()
+            }
         }
     }
 }
 
 pub trait MatchEngine: Sync + Send + Display {
     fn match_item(&self, item: &dyn SkimItem, item_idx: usize) -> Option<MatchResult>;
+
+    /// Match `text` as if it were the text of an item with the given matching ranges.
+    /// Combinators use it to narrow down the ranges searched by another engine, e.g. a term that
+    /// is scoped to a single field. The default implementation copies the text into a temporary
+    /// item, engines are encouraged to override it.
+    fn match_text(&self, text: &str, ranges: Option<&[(usize, usize)]>, item_idx: usize) -> Option<MatchResult> {
+        let item = RangedText {
+            text: text.into(),
+            ranges: ranges.map(Into::into),
+        };
+        self.match_item(&item, item_idx)
+    }
+}
+
+struct RangedText {
+    text: Box<str>,
+    ranges: Option<Box<[(usize, usize)]>>,
+}
+
+impl SkimItem for RangedText {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.text)
+    }
+
+    fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        self.ranges.as_deref()
+    }
 }
 
 pub trait MatchEngineFactory {
     fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine>;
+
+    /// Same as `create_engine_with_case`, but reports why a query could not be parsed, instead of
+    /// quietly creating an engine for it.
+    fn try_create_engine_with_case(
+        &self,
+        query: &str,
+        case: CaseMatching,
+    ) -> std::result::Result<Box<dyn MatchEngine>, String> {
+        Ok(self.create_engine_with_case(query, case))
+    }
+
     fn create_engine(&self, query: &str) -> Box<dyn MatchEngine> {
         self.create_engine_with_case(query, CaseMatching::default())
     }
@@ -279,6 +385,7 @@ pub trait Selector: Send + Sync {
 
 //------------------------------------------------------------------------------
 pub type SkimItemSender = Sender<Vec<Arc<dyn SkimItem>>>;
+/// The items may be `ItemChunk`s, i.e. many lines in one item, see `ItemChunk::unpack`
 pub type SkimItemReceiver = Receiver<Vec<Arc<dyn SkimItem>>>;
 
 pub struct Skim {}
@@ -367,6 +474,43 @@ impl Skim {
         ret
     }
 
+    /// Filter mode: match the items of `source` against `query` in parallel, without any UI.
+    ///
+    /// params:
+    /// - options: the search options (engine, case, tiebreak, no-sort, ...)
+    /// - query: the query to match the items against
+    /// - source: a stream of items to be filtered
+    /// - limit: only output the best `limit` items, sorted by rank
+    /// - output: called on every matched item, in input order if no limit is given
+    ///
+    /// return:
+    /// - the number of matched items, or an `InvalidInput` error if the query cannot be parsed
+    pub fn filter<F>(
+        options: &SkimOptions,
+        query: &str,
+        source: SkimItemReceiver,
+        limit: Option<usize>,
+        output: F,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&dyn SkimItem) -> io::Result<()>,
+    {
+        let rank_builder = Arc::new(RankBuilder::with_options(options));
+        let matcher = if options.regex && options.engine_factory.is_none() {
+            let regex_engine: Rc<dyn MatchEngineFactory> =
+                Rc::new(RegexEngineFactory::builder().rank_builder(rank_builder.clone()).build());
+            Matcher::builder(regex_engine)
+                .set_case(options.case)
+                .min_score(options.min_score)
+                .rank_builder(rank_builder)
+                .build()
+        } else {
+            Matcher::with_options(options, rank_builder)
+        };
+
+        matcher.filter(query, source, limit, !options.nosort, output)
+    }
+
     // 10 -> TermHeight::Fixed(10)
     // 10% -> TermHeight::Percent(10)
     fn parse_height_string(string: &str) -> TermHeight {
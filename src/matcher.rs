@@ -1,4 +1,7 @@
 use crossbeam_channel::Sender;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::io;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 
@@ -6,16 +9,24 @@ use rayon::ThreadPool;
 use rayon::prelude::*;
 use tuikit::key::Key;
 
-use crate::event::Event;
-use crate::item::{ItemPool, MatchedItem};
+use regex::Regex;
 
+use crate::engine::factory::{AndOrEngineFactory, ExactOrFuzzyEngineFactory};
+use crate::engine::translit::TranslitEngine;
+use crate::event::Event;
+use crate::item::{ItemPool, ItemSegments, ItemSlice, MatchedItem, PoolItem, RankBuilder};
+use crate::options::SkimOptions;
 use crate::spinlock::SpinLock;
-use crate::{CaseMatching, MatchEngine, MatchEngineFactory, SkimItem};
+use crate::{CaseMatching, MatchEngine, MatchEngineFactory, SkimItem, SkimItemReceiver};
 use crate::{MatchRange, Rank};
 use std::rc::Rc;
 
-const UNMATCHED_RANK: Rank = [0i32, 0i32, 0i32, 0i32];
 const UNMATCHED_RANGE: Option<MatchRange> = None;
+const DELIMITER_STR: &str = r"[\t\n ]+";
+// items matched by one rayon task
+const CHUNK_SIZE: usize = 8192;
+// items gathered from the source before matching them in parallel (filter mode)
+const FILTER_BATCH_SIZE: usize = CHUNK_SIZE * 64;
 
 //==============================================================================
 pub struct MatcherControl {
@@ -23,6 +34,7 @@ pub struct MatcherControl {
     processed: Arc<AtomicUsize>,
     matched: Arc<AtomicUsize>,
     items: Arc<SpinLock<Vec<MatchedItem>>>,
+    query_error: Option<String>,
 }
 
 impl Drop for MatcherControl {
@@ -53,6 +65,11 @@ impl MatcherControl {
         std::mem::take(&mut *items)
     }
 
+    /// why the query could not be parsed, if so
+    pub fn get_query_error(&self) -> Option<&str> {
+        self.query_error.as_deref()
+    }
+
     pub fn stopped(&self) -> bool {
         let is_stopped = self.stopped.load(Ordering::Relaxed);
         is_stopped
@@ -69,6 +86,8 @@ impl MatcherControl {
 pub struct Matcher {
     engine_factory: Rc<dyn MatchEngineFactory>,
     case_matching: CaseMatching,
+    min_score: Option<i32>,
+    rank_builder: Arc<RankBuilder>,
 }
 
 #[allow(dead_code)]
@@ -77,7 +96,49 @@ impl Matcher {
         Self {
             engine_factory,
             case_matching: CaseMatching::default(),
+            min_score: None,
+            rank_builder: Default::default(),
+        }
+    }
+
+    /// the matcher for the search options: the `engine_factory` given, or the
+    /// extended search syntax on top of exact/fuzzy matching
+    pub fn with_options(options: &SkimOptions, rank_builder: Arc<RankBuilder>) -> Self {
+        if let Some(engine_factory) = options.engine_factory.as_ref() {
+            // use provided engine
+            return Matcher::builder(engine_factory.clone())
+                .set_case(options.case)
+                .min_score(options.min_score)
+                .rank_builder(rank_builder)
+                .build();
         }
+
+        let delimiter = options
+            .delimiter
+            .and_then(|delimiter| Regex::new(delimiter).ok())
+            .unwrap_or_else(|| Regex::new(DELIMITER_STR).unwrap());
+        let field_names = options
+            .field_names
+            .map(|names| names.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        let fuzzy_engine_factory: Rc<dyn MatchEngineFactory> = Rc::new(
+            AndOrEngineFactory::new(Box::new(
+                ExactOrFuzzyEngineFactory::builder()
+                    .fuzzy_algorithm(options.algorithm)
+                    .exact_mode(options.exact)
+                    .normalize(!options.literal)
+                    .rank_builder(rank_builder.clone())
+                    .build(),
+            ))
+            .delimiter(delimiter)
+            .field_names(field_names)
+            .rank_builder(rank_builder.clone()),
+        );
+        Matcher::builder(fuzzy_engine_factory)
+            .set_case(options.case)
+            .min_score(options.min_score)
+            .rank_builder(rank_builder)
+            .build()
     }
 
     pub fn get_case(&self) -> CaseMatching {
@@ -89,6 +150,18 @@ impl Matcher {
         self
     }
 
+    /// drop the matches scoring less than `min_score`
+    pub fn min_score(mut self, min_score: Option<i32>) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// the rank builder of the engines, to get the score back from a rank
+    pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
+        self.rank_builder = rank_builder;
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
@@ -102,8 +175,15 @@ impl Matcher {
         matched_items: Vec<MatchedItem>,
         thread_pool_weak: Weak<ThreadPool>,
     ) -> MatcherControl {
-        let matcher_engine = self.engine_factory.create_engine_with_case(query, self.case_matching);
+        let case = self.case_matching;
+        let (matcher_engine, query_error) = match self.engine_factory.try_create_engine_with_case(query, case) {
+            Ok(engine) => (engine, None),
+            Err(err) => (self.engine_factory.create_engine_with_case(query, case), Some(err)),
+        };
+        let matcher_engine = TranslitEngine::new(matcher_engine);
         debug!("engine: {}", matcher_engine);
+        let min_score = self.min_score;
+        let rank_builder = self.rank_builder.clone();
         let stopped = Arc::new(AtomicBool::new(false));
         let stopped_clone = stopped.clone();
         let processed = Arc::new(AtomicUsize::new(0));
@@ -119,56 +199,53 @@ impl Matcher {
         if let Some(pool) = Weak::upgrade(&thread_pool_weak) {
             pool.install(|| {
                 rayon::spawn(move || {
-                    if let Some(item_pool_strong) = Weak::upgrade(&item_pool_weak) {
-                        let num_taken = item_pool_strong.num_taken();
-                        let items = &*item_pool_strong.take();
+                    let item_pool_strong = Weak::upgrade(&item_pool_weak);
+                    if let Some(items) = item_pool_strong.as_ref().and_then(|pool| pool.take(&stopped)) {
+                        let slices = items.slices(CHUNK_SIZE);
                         let stopped_ref = stopped.as_ref();
                         let processed_ref = processed.as_ref();
                         let matched_ref = matched.as_ref();
+                        let rank_builder = rank_builder.as_ref();
+                        let sort_unmatched = rank_builder.sort_key().is_some();
 
                         trace!("matcher start, total: {}", items.len());
 
-                        let par_iter = items
-                            .par_chunks(8192)
-                            .enumerate()
-                            .take_any_while(|(_master_idx, chunk)| {
+                        let par_iter = slices
+                            .par_iter()
+                            .take_any_while(|(_start, slice)| {
                                 if stopped_ref.load(Ordering::Relaxed) {
                                     return false;
                                 }
 
-                                processed_ref.fetch_add(chunk.len(), Ordering::Relaxed);
+                                processed_ref.fetch_add(slice.len(), Ordering::Relaxed);
                                 true
                             })
-                            .map(|(master_idx, chunk)| {
-                                chunk
-                                    .iter()
-                                    .enumerate()
-                                    .map(move |(idx, item)| {
-                                        let item_idx = ((master_idx - 1) * 8192) + idx;
-
-                                        (item_idx, item)
-                                    })
-                                    .filter_map(|(item_idx, item)| {
-                                        // dummy values should not change, as changing them
-                                        // may cause the disabled/query empty case disappear!
-                                        // especially item index.  Needs an index to appear!
-                                        if matcher_disabled {
-                                            return Some(MatchedItem {
-                                                item: Arc::downgrade(item),
-                                                rank: UNMATCHED_RANK,
-                                                matched_range: UNMATCHED_RANGE,
-                                                item_idx: (num_taken + item_idx) as u32,
-                                            });
-                                        }
-
-                                        Self::process_item(
-                                            item_idx,
-                                            num_taken,
-                                            matched_ref,
-                                            matcher_engine.as_ref(),
-                                            item,
-                                        )
-                                    })
+                            .map(|(start, slice)| {
+                                let start = *start;
+                                let matcher_engine = &matcher_engine;
+                                (0..slice.len()).filter_map(move |idx| {
+                                    let item_idx = start + idx;
+
+                                    // dummy values should not change, as changing them
+                                    // may cause the disabled/query empty case disappear!
+                                    // especially item index.  Needs an index to appear!
+                                    if matcher_disabled {
+                                        // still sorted by the field of `--sort-by`
+                                        let rank = if sort_unmatched {
+                                            rank_builder.sort_rank(&slice.get(idx).as_item().text())
+                                        } else {
+                                            Rank::default()
+                                        };
+                                        return Some(MatchedItem {
+                                            item: slice.item_ref(idx),
+                                            rank,
+                                            matched_range: UNMATCHED_RANGE,
+                                            item_idx: item_idx as u32,
+                                        });
+                                    }
+
+                                    Self::process_item(item_idx, matched_ref, matcher_engine, min_score, slice, idx)
+                                })
                             })
                             .flatten_iter();
 
@@ -194,25 +271,222 @@ impl Matcher {
             matched: matched_clone,
             processed: processed_clone,
             items: matched_items,
+            query_error,
+        }
+    }
+
+    /// Match the items of `source` against `query` on the rayon thread pool, without any UI.
+    ///
+    /// Without a `limit`, the matched items are passed to `output` in input order, as soon as
+    /// their batch is matched. With a `limit`, only the best `limit` items are kept and passed
+    /// to `output` best first, once the source is exhausted (or the first `limit` matches in
+    /// input order, if `sort` is false).
+    ///
+    /// return the number of matched items
+    pub fn filter<F>(
+        &self,
+        query: &str,
+        source: SkimItemReceiver,
+        limit: Option<usize>,
+        sort: bool,
+        mut output: F,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&dyn SkimItem) -> io::Result<()>,
+    {
+        let matcher_engine = self
+            .engine_factory
+            .try_create_engine_with_case(query, self.case_matching)
+            .map(TranslitEngine::new)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        debug!("engine: {}", matcher_engine);
+
+        let min_score = self.min_score;
+        let matched = AtomicUsize::new(0);
+        let mut num_received = 0;
+        let mut num_output = 0;
+        let mut top_items = BinaryHeap::new();
+
+        while let Ok(mut items) = source.recv() {
+            let mut batch = ItemSegments::default();
+            batch.append(&mut items);
+            while batch.len() < FILTER_BATCH_SIZE {
+                match source.try_recv() {
+                    Ok(mut items) => batch.append(&mut items),
+                    Err(_) => break,
+                }
+            }
+
+            // rayon keeps the order of the items when collecting
+            let matched_items: Vec<RankedItem> = batch
+                .slices(0, CHUNK_SIZE)
+                .par_iter()
+                .flat_map_iter(|(start, slice)| {
+                    let matched_ref = &matched;
+                    let matcher_engine = &matcher_engine;
+                    (0..slice.len()).filter_map(move |idx| {
+                        let item_idx = num_received + start + idx;
+                        Self::process_item(item_idx, matched_ref, matcher_engine, min_score, slice, idx).map(
+                            |matched_item| RankedItem {
+                                rank: matched_item.rank,
+                                item_idx,
+                                item: slice.get(idx),
+                            },
+                        )
+                    })
+                })
+                .collect();
+            num_received += batch.len();
+
+            match limit {
+                Some(limit) if sort => {
+                    for matched_item in matched_items {
+                        top_items.push(matched_item);
+                        if top_items.len() > limit {
+                            top_items.pop();
+                        }
+                    }
+                }
+                _ => {
+                    let limit = limit.unwrap_or(usize::MAX);
+                    for matched_item in matched_items.into_iter().take(limit - num_output) {
+                        output(matched_item.item.as_item())?;
+                        num_output += 1;
+                    }
+                    if num_output >= limit {
+                        return Ok(num_output);
+                    }
+                }
+            }
         }
+
+        if limit.is_some() && sort {
+            let top_items = top_items.into_sorted_vec();
+            for matched_item in top_items.iter() {
+                output(matched_item.item.as_item())?;
+            }
+            return Ok(top_items.len());
+        }
+
+        Ok(matched.load(Ordering::Relaxed))
     }
 
     fn process_item(
-        index: usize,
-        num_taken: usize,
+        item_idx: usize,
         matched: &AtomicUsize,
         matcher_engine: &dyn MatchEngine,
-        item: &Arc<dyn SkimItem>,
+        min_score: Option<i32>,
+        slice: &ItemSlice,
+        idx: usize,
     ) -> Option<MatchedItem> {
-        matcher_engine.match_item(item.as_ref(), index).map(|match_result| {
-            matched.fetch_add(1, Ordering::Relaxed);
-
-            MatchedItem {
-                item: Arc::downgrade(item),
-                rank: match_result.rank,
-                matched_range: Some(match_result.matched_range),
-                item_idx: (num_taken + index) as u32,
-            }
+        let match_result = slice.match_item(matcher_engine, idx, item_idx)?;
+        if min_score.is_some_and(|min_score| match_result.rank.score() < min_score) {
+            return None;
+        }
+
+        matched.fetch_add(1, Ordering::Relaxed);
+        Some(MatchedItem {
+            item: slice.item_ref(idx),
+            rank: match_result.rank,
+            matched_range: Some(match_result.matched_range),
+            item_idx: item_idx as u32,
         })
     }
 }
+
+//==============================================================================
+// a matched item of the filter mode, ordered by rank, then by input order
+struct RankedItem {
+    rank: Rank,
+    item_idx: usize,
+    item: PoolItem,
+}
+
+impl PartialEq for RankedItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for RankedItem {}
+
+impl PartialOrd for RankedItem {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedItem {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (&self.rank, self.item_idx).cmp(&(&other.rank, other.item_idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::RankCriteria;
+    use crossbeam_channel::unbounded;
+
+    fn filter(query: &str, lines: &[&'static str], limit: Option<usize>, sort: bool) -> Vec<String> {
+        let (tx, rx) = unbounded();
+        // split the input over several sends, as a reader would
+        for chunk in lines.chunks(2) {
+            let items: Vec<Arc<dyn SkimItem>> = chunk.iter().map(|line| Arc::new(*line) as Arc<dyn SkimItem>).collect();
+            tx.send(items).unwrap();
+        }
+        drop(tx);
+
+        let matcher = Matcher::with_options(&SkimOptions::default(), Arc::new(RankBuilder::default()));
+        let mut output = Vec::new();
+        let num_output = matcher
+            .filter(query, rx, limit, sort, |item| {
+                output.push(item.text().to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(num_output, output.len());
+        output
+    }
+
+    #[test]
+    fn test_filter() {
+        let lines = ["f_o_o", "bar", "foo.rs", "afoob", "zzz", "foo"];
+
+        // streaming, in input order
+        assert_eq!(filter("foo", &lines, None, true), ["f_o_o", "foo.rs", "afoob", "foo"]);
+        // best N, sorted by rank
+        assert_eq!(filter("foo", &lines, Some(2), true), ["foo.rs", "foo"]);
+        // first N without sorting
+        assert_eq!(filter("foo", &lines, Some(2), false), ["f_o_o", "foo.rs"]);
+        assert!(filter("nothing", &lines, Some(2), true).is_empty());
+
+        let (_tx, rx) = unbounded();
+        let matcher = Matcher::with_options(&SkimOptions::default(), Arc::new(RankBuilder::default()));
+        let err = matcher.filter("(foo", rx, None, true, |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_min_score() {
+        let (tx, rx) = unbounded();
+        let items: Vec<Arc<dyn SkimItem>> = ["f_________o_________o", "foo"]
+            .iter()
+            .map(|line| Arc::new(*line) as Arc<dyn SkimItem>)
+            .collect();
+        tx.send(items).unwrap();
+        drop(tx);
+
+        // the score is kept even if the tiebreak has none, the exact match scores more
+        let rank_builder = RankBuilder::new(vec![RankCriteria::Length, RankCriteria::NegIndex]);
+        let matcher = Matcher::with_options(&SkimOptions::default(), Arc::new(rank_builder)).min_score(Some(60));
+        let mut output = Vec::new();
+        matcher
+            .filter("foo", rx, None, true, |item| {
+                output.push(item.text().to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(output, ["foo"]);
+    }
+}
@@ -1,35 +1,39 @@
 use std::borrow::Cow;
-use std::env;
 
 use std::process::Command;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::thread::sleep;
+use std::thread::{self, sleep};
 use std::time::{Duration, Instant};
 
 use rayon::ThreadPool;
+use rayon::prelude::*;
 use regex::Regex;
 use std::sync::LazyLock;
 use tuikit::prelude::{Event as TermEvent, *};
 
-use crate::engine::factory::{AndOrEngineFactory, ExactOrFuzzyEngineFactory, RegexEngineFactory};
+use crate::engine::factory::RegexEngineFactory;
 use crate::event::UpdateScreen;
 use crate::event::{Event, EventHandler, EventReceiver, EventSender};
 use crate::global::current_run_num;
 use crate::header::Header;
 use crate::input::parse_action_arg;
-use crate::item::{ItemPool, MatchedItem, RankBuilder, RankCriteria, parse_criteria};
+use crate::item::{ItemPool, ItemRef, ItemSegments, MatchedItem, RankBuilder};
 use crate::matcher::{Matcher, MatcherControl};
 use crate::options::SkimOptions;
 use crate::output::SkimOutput;
 use crate::previewer::Previewer;
 use crate::query::Query;
-use crate::reader::{Reader, ReaderControl};
+use crate::reader::{ProcessState, Reader, ReaderControl, default_command};
 use crate::selection::Selection;
+use crate::shell::Shell;
 use crate::spinlock::SpinLock;
 use crate::theme::ColorTheme;
 use crate::util::clear_canvas;
-use crate::util::{InjectContext, depends_on_items, inject_command, margin_string_to_size, parse_margin};
+use crate::util::{
+    InjectContext, depends_on_items, inject_argv, inject_command, inject_field_names, margin_string_to_size,
+    parse_margin, split_argv,
+};
 use crate::{MatchEngineFactory, MatchRange, SkimItem};
 use std::cmp::max;
 
@@ -40,6 +44,7 @@ use crate::malloc_trim;
 
 const REFRESH_TIMEOUT: Duration = std::time::Duration::from_millis(1);
 const MATCHER_TIMEOUT: Duration = std::time::Duration::from_millis(10);
+const CMD_OUTPUT_TIMEOUT: Duration = std::time::Duration::from_millis(10);
 const SPINNER_DURATION: u32 = 200;
 // const SPINNERS: [char; 8] = ['-', '\\', '|', '/', '-', '\\', '|', '/'];
 const SPINNERS_INLINE: [char; 2] = ['-', '<'];
@@ -48,8 +53,6 @@ const DELIMITER_STR: &str = r"[\t\n ]+";
 
 static RE_PREVIEW_OFFSET: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\+([0-9]+|\{-?[0-9]+\})(-[0-9]+|-/[1-9][0-9]*)?$").unwrap());
-static DEFAULT_CRITERION: LazyLock<Vec<RankCriteria>> =
-    LazyLock::new(|| vec![RankCriteria::Score, RankCriteria::Begin, RankCriteria::End]);
 
 pub struct Model {
     reader: Reader,
@@ -77,6 +80,15 @@ pub struct Model {
     reader_control: Option<ReaderControl>,
     matcher_control: Option<MatcherControl>,
     thread_pool: Arc<ThreadPool>,
+    // the matcher running was started before `toggle-sort`, its items are sorted by the previous key
+    stale_sort: bool,
+
+    // the edited command is run once it is not edited for a while
+    cmd_debounce: CmdDebounce,
+    // the items of the previous command are shown until the new one outputs its first items
+    waiting_for_cmd: bool,
+    // the items of the previous command, alive until they are not displayed anymore
+    previous_items: Option<ItemSegments>,
 
     header: Header,
 
@@ -92,12 +104,15 @@ pub struct Model {
 
     layout: String,
     delimiter: Regex,
+    field_names: Vec<String>,
+    shell: Shell,
     inline_info: bool,
     no_clear_if_empty: bool,
     theme: Arc<ColorTheme>,
 
     // for AppendAndSelect action
     rank_builder: Arc<RankBuilder>,
+    query_error: Option<String>,
 }
 
 impl Drop for Model {
@@ -125,10 +140,9 @@ impl Drop for Model {
 
 impl Model {
     pub fn new(rx: EventReceiver, tx: EventSender, reader: Reader, term: Arc<Term>, options: &SkimOptions) -> Self {
-        let default_command = match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
-            Ok("") | Err(_) => "find .".to_owned(),
-            Ok(val) => val.to_owned(),
-        };
+        // the reader lists the files with the built-in walker instead (see `default_walker`), unless
+        // SKIM_DEFAULT_COMMAND is set or the collector cannot walk
+        let default_command = default_command();
 
         let theme = Arc::new(ColorTheme::init_from_options(options));
         let query = Query::from_options(options)
@@ -136,39 +150,30 @@ impl Model {
             .theme(theme.clone())
             .build();
 
-        let criterion = if let Some(ref tie_breaker) = options.tiebreak {
-            tie_breaker.split(',').filter_map(parse_criteria).collect()
-        } else {
-            DEFAULT_CRITERION.clone()
-        };
-
         let disabled = options.disabled;
 
-        let rank_builder = Arc::new(RankBuilder::new(criterion));
+        let rank_builder = Arc::new(RankBuilder::with_options(options));
 
-        let selection = Selection::with_options(options).theme(theme.clone());
+        let tx_sorted = tx.clone();
+        let selection = Selection::with_options(options)
+            .theme(theme.clone())
+            .on_sorted(move || {
+                let _ = tx_sorted.send((Key::Null, Event::EvActRedraw));
+            });
         let regex_engine: Rc<dyn MatchEngineFactory> =
             Rc::new(RegexEngineFactory::builder().rank_builder(rank_builder.clone()).build());
-        let regex_matcher = Matcher::builder(regex_engine).build();
-
-        let exact_mode = options.exact;
-        let case_matching = options.case;
+        let regex_matcher = Matcher::builder(regex_engine)
+            .min_score(options.min_score)
+            .rank_builder(rank_builder.clone())
+            .build();
 
-        let matcher = if let Some(engine_factory) = options.engine_factory.as_ref() {
-            // use provided engine
-            Matcher::builder(engine_factory.clone()).set_case(options.case).build()
-        } else {
-            let fuzzy_engine_factory: Rc<dyn MatchEngineFactory> = Rc::new(AndOrEngineFactory::new(Box::new(
-                ExactOrFuzzyEngineFactory::builder()
-                    .fuzzy_algorithm(options.algorithm)
-                    .exact_mode(exact_mode)
-                    .rank_builder(rank_builder.clone())
-                    .build(),
-            )));
-            Matcher::builder(fuzzy_engine_factory).set_case(case_matching).build()
-        };
+        let matcher = Matcher::with_options(options, rank_builder.clone());
 
-        let item_pool = Arc::new(ItemPool::new().lines_to_reserve(options.header_lines));
+        let item_pool = Arc::new(
+            ItemPool::new()
+                .lines_to_reserve(options.header_lines)
+                .tail(options.tail),
+        );
         let header = Header::default()
             .with_options(options)
             .item_pool(&item_pool)
@@ -204,6 +209,11 @@ impl Model {
             reader_control: None,
             matcher_control: None,
             thread_pool,
+            stale_sort: false,
+
+            cmd_debounce: CmdDebounce::new(Duration::from_millis(options.cmd_debounce)),
+            waiting_for_cmd: false,
+            previous_items: None,
 
             header,
             preview_hidden: true,
@@ -218,10 +228,13 @@ impl Model {
 
             layout: "default".to_string(),
             delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            field_names: Vec::new(),
+            shell: options.shell.clone(),
             inline_info: false,
             no_clear_if_empty: false,
             theme,
             rank_builder,
+            query_error: None,
         };
         ret.parse_options(options);
         ret
@@ -240,6 +253,10 @@ impl Model {
             self.delimiter = Regex::new(delimiter).unwrap_or_else(|_| Regex::new(DELIMITER_STR).unwrap());
         }
 
+        if let Some(field_names) = options.field_names {
+            self.field_names = field_names.split(',').map(str::to_string).collect();
+        }
+
         self.layout = options.layout.to_string();
 
         if options.inline_info {
@@ -269,6 +286,9 @@ impl Model {
             )
             .wrap(preview_wrap)
             .delimiter(self.delimiter.clone())
+            .field_names(self.field_names.clone())
+            .shell(self.shell.clone())
+            .argv(options.preview_argv)
             .preview_offset(
                 options
                     .preview_window
@@ -335,6 +355,10 @@ impl Model {
     }
 
     fn act_heart_beat(&mut self, env: &mut ModelEnv) {
+        if self.cmd_debounce.take_due(Instant::now()) {
+            self.on_cmd_query_change(env);
+        }
+
         // save the processed items
         let matcher_stopped = self
             .matcher_control
@@ -346,17 +370,23 @@ impl Model {
 
         if matcher_stopped {
             let ctrl = self.matcher_control.take().unwrap();
-            let matched = ctrl.into_items();
+            let mut matched = ctrl.into_items();
+            if std::mem::take(&mut self.stale_sort) {
+                let rank_builder = self.rank_builder.as_ref();
+                matched.par_iter_mut().for_each(|item| rank_builder.update_sort(item));
+            }
 
             match env.clear_selection {
                 ClearStrategy::DontClear => {}
                 ClearStrategy::Clear => {
                     self.selection.clear();
+                    self.previous_items = None;
                     env.clear_selection = ClearStrategy::DontClear;
                 }
                 ClearStrategy::ClearIfNotNull => {
                     if (!self.no_clear_if_empty && reader_stopped) || !matched.is_empty() {
                         self.selection.clear();
+                        self.previous_items = None;
                         env.clear_selection = ClearStrategy::DontClear;
                     }
                 }
@@ -365,6 +395,23 @@ impl Model {
             self.selection.append_sorted_items(matched);
         }
 
+        // with `--tail`, the oldest items are evicted from the pool as new ones are read. The matches
+        // of a previous query may not be cleared yet, they are not counted anymore.
+        let num_evicted = self.item_pool.num_evicted();
+        if num_evicted > 0 {
+            self.num_options = self.num_options.saturating_sub(self.selection.evict(num_evicted));
+        }
+
+        if self.waiting_for_cmd && self.wait_for_cmd(env) {
+            let tx = self.tx.clone();
+            // not in the thread pool, busy with the matcher and the sorting
+            thread::spawn(move || {
+                sleep(CMD_OUTPUT_TIMEOUT);
+                let _ = tx.send((Key::Null, Event::EvHeartBeat));
+            });
+            return;
+        }
+
         let items_consumed = self.item_pool.num_not_taken() == 0;
         let processed = reader_stopped && items_consumed;
 
@@ -401,6 +448,28 @@ impl Model {
         self.restart_matcher();
     }
 
+    fn act_toggle_sort(&mut self) {
+        // the fields of `--sort-by` in turn, then the score, then the input order
+        if self.rank_builder.sort_key().is_none() && self.selection.is_sorted() {
+            self.selection.act_toggle_sort();
+            return;
+        }
+
+        if !self.selection.is_sorted() {
+            self.selection.act_toggle_sort();
+        }
+
+        if !self.rank_builder.toggle_sort() {
+            return;
+        }
+
+        // the ranks are built with the sort key, the ones of the matched items are updated and sorted
+        // again, so are the ones of the matcher still running once it is done
+        self.stale_sort = self.matcher_control.is_some();
+        let rank_builder = self.rank_builder.clone();
+        self.selection.rerank(move |item| rank_builder.update_sort(item));
+    }
+
     fn handle_select1_or_exit0(&mut self) {
         if !self.select1 && !self.exit0 && !self.sync {
             return;
@@ -429,24 +498,64 @@ impl Model {
         }
     }
 
+    // the command is run once it is not edited for `cmd_debounce`
+    fn on_cmd_edit(&mut self, env: &mut ModelEnv) {
+        let Some(delay) = self.cmd_debounce.edit(Instant::now()) else {
+            self.on_cmd_query_change(env);
+            return;
+        };
+
+        let tx = self.tx.clone();
+        // not in the thread pool, where the timers of the edits would wait for each other
+        thread::spawn(move || {
+            sleep(delay);
+            let _ = tx.send((Key::Null, Event::EvHeartBeat));
+        });
+    }
+
     fn on_cmd_query_change(&mut self, env: &mut ModelEnv) {
+        self.cmd_debounce.cancel();
+
+        // restart reader, the items of the previous command are kept until the new one outputs
+        let old_reader = self.reader_control.replace(self.reader.run(&env.cmd));
+        if let Some(mut reader) = old_reader {
+            reader.kill()
+        }
+
+        self.waiting_for_cmd = true;
+        self.reader_timer = Instant::now();
+        let _ = self.tx.send((Key::Null, Event::EvHeartBeat));
+    }
+
+    // the first items of the new command replace the items of the previous one, so does its exit
+    // without any unless `no_clear_if_empty`. Return whether the command is still waited for.
+    fn wait_for_cmd(&mut self, env: &mut ModelEnv) -> bool {
+        let Some(ctrl) = self.reader_control.as_ref() else {
+            self.waiting_for_cmd = false;
+            return false;
+        };
+
+        let has_output = !ctrl.is_empty();
+        if !has_output && !ctrl.all_stopped() {
+            return true;
+        }
+
+        self.waiting_for_cmd = false;
+        if !has_output && self.no_clear_if_empty {
+            return false;
+        }
+
         // stop matcher
         if let Some(mut matcher) = self.matcher_control.take() {
             matcher.kill();
         }
 
         env.clear_selection = ClearStrategy::ClearIfNotNull;
-        self.item_pool.clear();
+        // the previous items are displayed until the first matches replace them
+        self.previous_items = Some(self.item_pool.drain());
         self.num_options = 0;
-
-        // restart reader
-        let old_reader = self.reader_control.replace(self.reader.run(&env.cmd));
-        if let Some(mut reader) = old_reader {
-            reader.kill()
-        }
-
         self.restart_matcher();
-        self.reader_timer = Instant::now();
+        false
     }
 
     fn on_query_change(&mut self, env: &mut ModelEnv) {
@@ -461,7 +570,8 @@ impl Model {
         self.restart_matcher();
     }
 
-    fn act_execute(&mut self, cmd: &str) {
+    // argv: run the command without a shell, see `split_argv`
+    fn act_execute(&mut self, cmd: &str, argv: bool) {
         let item = self.selection.get_current_item();
         if depends_on_items(cmd) && item.is_none() {
             debug!("act_execute: command refers to items and there is no item for now");
@@ -470,11 +580,11 @@ impl Model {
         }
 
         let _ = self.term.pause();
-        self.act_execute_silent(cmd);
+        self.act_execute_silent(cmd, argv);
         let _ = self.term.restart();
     }
 
-    fn act_execute_silent(&mut self, cmd: &str) {
+    fn act_execute_silent(&mut self, cmd: &str, argv: bool) {
         let current_index = self.selection.get_current_item_idx();
         let current_item = self.selection.get_current_item();
         if depends_on_items(cmd) && current_item.is_none() {
@@ -487,6 +597,11 @@ impl Model {
             .as_ref()
             .map(|item| item.output())
             .unwrap_or_else(|| Cow::Borrowed(""));
+        let current_fields = current_item
+            .as_ref()
+            .map(|item| item.field_text())
+            .unwrap_or_else(|| Cow::Borrowed(""));
+        let current_source = current_item.as_ref().and_then(|item| item.source()).unwrap_or("");
         let query = self.query.get_fz_query();
         let cmd_query = self.query.get_cmd_query();
 
@@ -496,20 +611,35 @@ impl Model {
             .map(|item| item.upgrade_infallible())
             .map(|item| item.text().into())
             .collect();
+        let captures = self.selection.get_current_captures();
 
         let context = InjectContext {
             current_index,
             delimiter: &self.delimiter,
             current_selection: &current_selection,
+            current_fields: &current_fields,
             selections: selected_texts.as_slice(),
             indices: &indices,
             query: &query,
             cmd_query: &cmd_query,
+            captures: &captures,
+            current_source,
+            quoting: self.shell.quoting(),
         };
 
-        let cmd = inject_command(cmd, context).to_string();
-        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-        let _ = Command::new(shell).arg("-c").arg(cmd).status();
+        if !argv {
+            let cmd = inject_command(cmd, context).to_string();
+            let _ = self.shell.command(&cmd).status();
+            return;
+        }
+
+        let args = inject_argv(&split_argv(cmd), context);
+        match args.split_first() {
+            Some((program, args)) => {
+                let _ = Command::new(program).args(args).status();
+            }
+            None => debug!("act_execute_silent: no program to execute"),
+        }
     }
 
     #[allow(clippy::trivial_regex)]
@@ -519,15 +649,15 @@ impl Model {
             return;
         }
 
-        let item_len = query.len();
         let item: Arc<dyn SkimItem> = Arc::new(query);
-        let downgraded = Arc::downgrade(&item);
-        let new_len = self.item_pool.append(&mut [item].to_vec());
+        let downgraded = ItemRef::from(&item);
+        let new_len = self.item_pool.append(&mut [item.clone()].to_vec());
         let item_idx = (max(new_len, 1) - 1) as usize;
+        let rank = self.rank_builder.build_rank(0, 0, 0, &item.text(), item_idx);
 
         let matched_item = MatchedItem {
             item: downgraded,
-            rank: self.rank_builder.build_rank(0, 0, 0, item_len, item_idx),
+            rank,
             matched_range: Some(MatchRange::ByteRange(0, 0)),
             item_idx: item_idx as u32,
         };
@@ -595,6 +725,10 @@ impl Model {
                     self.act_rotate_mode(&mut env);
                 }
 
+                Event::EvActToggleSort => {
+                    self.act_toggle_sort();
+                }
+
                 Event::EvActAccept(accept_key) => {
                     if let Some(mut ctrl) = self.reader_control.take() {
                         ctrl.kill();
@@ -657,11 +791,19 @@ impl Model {
                 }
 
                 Event::EvActExecute(ref cmd) => {
-                    self.act_execute(cmd);
+                    self.act_execute(&inject_field_names(cmd, &self.field_names), false);
+                }
+
+                Event::EvActExecuteArgv(ref cmd) => {
+                    self.act_execute(&inject_field_names(cmd, &self.field_names), true);
                 }
 
                 Event::EvActExecuteSilent(ref cmd) => {
-                    self.act_execute_silent(cmd);
+                    self.act_execute_silent(&inject_field_names(cmd, &self.field_names), false);
+                }
+
+                Event::EvActExecuteSilentArgv(ref cmd) => {
+                    self.act_execute_silent(&inject_field_names(cmd, &self.field_names), true);
                 }
 
                 Event::EvActAppendAndSelect => {
@@ -719,7 +861,7 @@ impl Model {
             // re-run reader & matcher if needed;
             if new_cmd != env.cmd {
                 env.cmd = new_cmd;
-                self.on_cmd_query_change(&mut env);
+                self.on_cmd_edit(&mut env);
             } else if new_query != env.query {
                 env.query = new_query;
                 self.on_query_change(&mut env);
@@ -742,7 +884,8 @@ impl Model {
     }
 
     fn draw_preview(&mut self, env: &ModelEnv, force: bool) {
-        if self.preview_hidden {
+        // drawn again once the current item is sorted
+        if self.preview_hidden || !self.selection.is_current_sorted() {
             return;
         }
 
@@ -757,6 +900,7 @@ impl Model {
                 item,
                 env.query.to_string(),
                 env.cmd_query.to_string(),
+                selections.get_current_captures(),
                 selections.get_num_of_selected_exclude_current(),
                 get_selected_items,
                 force,
@@ -782,8 +926,10 @@ impl Model {
         self.matcher_timer = Instant::now();
         let query = self.query.get_fz_query();
 
-        // if there are new items, move them to item pool
-        let reader_ctrl = self.reader_control.as_mut();
+        // if there are new items, move them to item pool, unless they are of a new command
+        // that does not replace the items of the previous one yet
+        let waiting_for_cmd = self.waiting_for_cmd;
+        let reader_ctrl = self.reader_control.as_mut().filter(|_| !waiting_for_cmd);
 
         if let Some(ctrl) = reader_ctrl {
             let all_stopped = ctrl.all_stopped();
@@ -810,6 +956,7 @@ impl Model {
 
         // send heart beat (so that heartbeat/refresh is triggered)
         let _ = self.tx.send((Key::Null, Event::EvHeartBeat));
+        self.stale_sort = false;
 
         // kill existing matcher if exists, but reuse old matched items vec
         let cleared_vec = self
@@ -838,6 +985,8 @@ impl Model {
             Arc::downgrade(&self.thread_pool),
         );
 
+        self.query_error = new_matcher_control.get_query_error().map(str::to_string);
+
         // replace None matcher
         self.matcher_control.replace(new_matcher_control);
     }
@@ -854,6 +1003,17 @@ impl Model {
             "".to_string()
         };
 
+        let sort_state = match (self.rank_builder.sort_key(), self.selection.is_sorted()) {
+            (_, false) => Some("sort:off".to_string()),
+            (Some(sort_key), true) => Some(format!("sort:{}", sort_key)),
+            (None, true) => None,
+        };
+        let sort_state = sort_state
+            .into_iter()
+            .chain(self.selection.is_tac().then(|| "tac".to_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+
         let matched = self.num_options + self.matcher_control.as_ref().map(|c| c.get_num_matched()).unwrap_or(0);
         let matcher_running = self.item_pool.num_not_taken() != 0 || matched != self.num_options;
         let processed = self
@@ -862,6 +1022,19 @@ impl Model {
             .map(|c| c.get_num_processed())
             .unwrap_or(total);
 
+        // the state of the command in interactive mode, and the last line of its errors
+        let process_status = self
+            .reader_control
+            .as_ref()
+            .and_then(|ctrl| ctrl.process_status())
+            .filter(|_| !self.query.in_query_mode());
+        let process_state = process_status.map(|status| match status.state() {
+            ProcessState::Running => "running".to_string(),
+            ProcessState::Exited(code) => format!("exit {}", code),
+            ProcessState::Signaled(signal) => format!("signal {}", signal),
+        });
+        let stderr_tail = process_status.map(|status| status.stderr_tail()).unwrap_or_default();
+
         let status = Status {
             total,
             matched,
@@ -875,6 +1048,10 @@ impl Model {
             time_since_read: self.reader_timer.elapsed(),
             time_since_match: self.matcher_timer.elapsed(),
             matcher_mode,
+            sort_state,
+            query_error: self.query_error.clone(),
+            process_state,
+            stderr_tail,
             theme: self.theme.clone(),
             inline_info: self.inline_info,
         };
@@ -954,6 +1131,37 @@ impl Model {
     }
 }
 
+/// When the edited command of interactive mode is run: once it is not edited for `delay`
+struct CmdDebounce {
+    delay: Duration,
+    deadline: Option<Instant>,
+}
+
+impl CmdDebounce {
+    fn new(delay: Duration) -> Self {
+        Self { delay, deadline: None }
+    }
+
+    /// an edit at `now`, return how long to wait before the command is due, `None` if it is run
+    /// at once
+    fn edit(&mut self, now: Instant) -> Option<Duration> {
+        if self.delay.is_zero() {
+            return None;
+        }
+        self.deadline = Some(now + self.delay);
+        Some(self.delay)
+    }
+
+    /// whether the command is due at `now`, it is not anymore afterwards
+    fn take_due(&mut self, now: Instant) -> bool {
+        self.deadline.take_if(|deadline| *deadline <= now).is_some()
+    }
+
+    fn cancel(&mut self) {
+        self.deadline = None;
+    }
+}
+
 struct ModelEnv {
     pub cmd: String,
     pub query: String,
@@ -976,6 +1184,12 @@ struct Status {
     time_since_read: Duration,
     time_since_match: Duration,
     matcher_mode: String,
+    // e.g. "sort:3:num tac", empty when sorted by score
+    sort_state: String,
+    query_error: Option<String>,
+    // e.g. "running" or "exit 2", of the command in interactive mode
+    process_state: Option<String>,
+    stderr_tail: String,
     theme: Arc<ColorTheme>,
     inline_info: bool,
 }
@@ -1037,6 +1251,21 @@ impl Draw for Status {
             col += canvas.print_with_attr(0, col, format!("/{}", &self.matcher_mode).as_ref(), info_attr)?;
         }
 
+        // display how the items are sorted
+        if !self.sort_state.is_empty() {
+            col += canvas.print_with_attr(0, col, format!(" {}", &self.sort_state).as_ref(), info_attr)?;
+        }
+
+        // display why the query is invalid
+        if let Some(query_error) = &self.query_error {
+            col += canvas.print_with_attr(0, col, format!(" ({})", query_error).as_ref(), info_attr_bold)?;
+        }
+
+        // display the state of the command
+        if let Some(process_state) = &self.process_state {
+            col += canvas.print_with_attr(0, col, format!(" [{}]", process_state).as_ref(), info_attr)?;
+        }
+
         // display the percentage of the number of processed items
         if self.matcher_running && a_while_since_match {
             col += canvas.print_with_attr(
@@ -1052,6 +1281,11 @@ impl Draw for Status {
             col += canvas.print_with_attr(0, col, format!(" [{}]", self.selected).as_ref(), info_attr_bold)?;
         }
 
+        // display the last error of the command, as far as it fits
+        if !self.stderr_tail.is_empty() {
+            col += canvas.print_with_attr(0, col, format!(" {}", self.stderr_tail).as_ref(), info_attr_bold)?;
+        }
+
         // item cursor
         let line_num_str = format!(
             " {}/{}{}",
@@ -1081,3 +1315,32 @@ enum ClearStrategy {
     Clear,
     ClearIfNotNull,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmd_debounce() {
+        let now = Instant::now();
+        let mut debounce = CmdDebounce::new(Duration::ZERO);
+        assert_eq!(debounce.edit(now), None);
+        assert!(!debounce.take_due(now));
+
+        let delay = Duration::from_millis(100);
+        let mut debounce = CmdDebounce::new(delay);
+        assert_eq!(debounce.edit(now), Some(delay));
+        assert!(!debounce.take_due(now + delay / 2));
+
+        // the deadline is pushed back by every edit
+        assert_eq!(debounce.edit(now + delay / 2), Some(delay));
+        assert!(!debounce.take_due(now + delay));
+        assert!(debounce.take_due(now + delay * 3 / 2));
+        assert!(!debounce.take_due(now + delay * 2));
+
+        // the command was run by another event already
+        debounce.edit(now);
+        debounce.cancel();
+        assert!(!debounce.take_due(now + delay));
+    }
+}
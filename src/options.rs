@@ -4,6 +4,7 @@ use derive_builder::Builder;
 
 use crate::helper::item_reader::SkimItemReader;
 use crate::reader::CommandCollector;
+use crate::shell::Shell;
 use crate::{CaseMatching, FuzzyAlgorithm, MatchEngineFactory, Selector};
 use std::cell::RefCell;
 use std::sync::Arc;
@@ -20,14 +21,21 @@ pub struct SkimOptions<'a> {
     pub tac: bool,
     pub nosort: bool,
     pub tiebreak: Option<String>,
+    pub sort_by: Option<&'a str>,
+    pub min_score: Option<i32>,
+    pub max_results: Option<usize>,
+    pub tail: Option<usize>,
     pub exact: bool,
+    pub literal: bool,
     pub disabled: bool,
     pub cmd: Option<&'a str>,
     pub interactive: bool,
+    pub cmd_debounce: u64,
     pub query: Option<&'a str>,
     pub cmd_query: Option<&'a str>,
     pub regex: bool,
     pub delimiter: Option<&'a str>,
+    pub field_names: Option<&'a str>,
     pub replstr: Option<&'a str>,
     pub color: Option<&'a str>,
     pub margin: Option<&'a str>,
@@ -37,6 +45,7 @@ pub struct SkimOptions<'a> {
     pub min_height: Option<&'a str>,
     pub height: Option<&'a str>,
     pub preview: Option<&'a str>,
+    pub preview_argv: bool,
     pub preview_window: Option<&'a str>,
     pub reverse: bool,
     pub tabstop: Option<&'a str>,
@@ -45,6 +54,7 @@ pub struct SkimOptions<'a> {
     pub inline_info: bool,
     pub header: Option<&'a str>,
     pub header_lines: usize,
+    pub multi_line: usize,
     pub layout: &'a str,
     pub algorithm: FuzzyAlgorithm,
     pub case: CaseMatching,
@@ -52,6 +62,8 @@ pub struct SkimOptions<'a> {
     pub query_history: &'a [String],
     pub cmd_history: &'a [String],
     pub cmd_collector: Rc<RefCell<dyn CommandCollector>>,
+    pub walker: Option<&'a str>,
+    pub shell: Shell,
     pub keep_right: bool,
     pub skip_to_pattern: &'a str,
     pub select1: bool,
@@ -72,14 +84,21 @@ impl<'a> Default for SkimOptions<'a> {
             tac: false,
             nosort: false,
             tiebreak: None,
+            sort_by: None,
+            min_score: None,
+            max_results: None,
+            tail: None,
             exact: false,
+            literal: false,
             disabled: false,
             cmd: None,
             interactive: false,
+            cmd_debounce: 0,
             query: None,
             cmd_query: None,
             regex: false,
             delimiter: None,
+            field_names: None,
             replstr: Some("{}"),
             color: None,
             margin: Some("0,0,0,0"),
@@ -89,6 +108,7 @@ impl<'a> Default for SkimOptions<'a> {
             min_height: Some("10"),
             height: Some("100%"),
             preview: None,
+            preview_argv: false,
             preview_window: Some("right:50%"),
             reverse: false,
             tabstop: None,
@@ -97,6 +117,7 @@ impl<'a> Default for SkimOptions<'a> {
             inline_info: false,
             header: None,
             header_lines: 0,
+            multi_line: 1,
             layout: "",
             algorithm: FuzzyAlgorithm::default(),
             case: CaseMatching::default(),
@@ -104,6 +125,8 @@ impl<'a> Default for SkimOptions<'a> {
             query_history: &[],
             cmd_history: &[],
             cmd_collector: Rc::new(RefCell::new(SkimItemReader::new(Default::default()))),
+            walker: None,
+            shell: Shell::default(),
             keep_right: false,
             skip_to_pattern: "",
             select1: false,
@@ -1,29 +1,53 @@
 // ordered container
-// Normally, user will only care about the first several options. So we only keep several of them
-// in order. Other items are kept unordered and are sorted on demand.
+// Normally, user will only care about the first several options. So we only keep the smallest items
+// in order, a few pages of them. Other items are kept unordered, the next pages are selected from
+// them in parallel on demand, so that scrolling down a huge list never sorts all of it. Large
+// selections run in a rayon task, the items being drawn are only read from the sorted ones.
 
 use std::cell::RefCell;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, max, min};
+use std::sync::Arc;
 
-const ORDERED_SIZE: usize = 300;
-const MAX_MOVEMENT: usize = 100;
+use crossbeam_channel::{Receiver, TryRecvError, bounded};
+use rayon::prelude::*;
+
+// the least number of items sorted at once
+const PAGE_SIZE: usize = 300;
+// the unordered items are split into chunks of at least that size to be selected in parallel
+const MIN_CHUNK_SIZE: usize = 4096;
+// the pages are selected from more unordered items than that in a rayon task
+const BACKGROUND_SORT_SIZE: usize = 1 << 16;
 
 pub struct OrderedVec<T: Send + Sync + Ord + Clone + 'static> {
-    // sorted vectors for merge, reverse ordered, last one is the smallest one
-    sub_vectors: RefCell<Vec<Vec<T>>>,
-    // globally sorted items, the first one is the smallest one.
+    // the smallest items, globally sorted, the first one is the smallest one.
+    // With `nosort`, all the items in the order they were appended.
     sorted: RefCell<Vec<T>>,
+    // the other items, unordered, none of them is smaller than the sorted ones
+    unsorted: RefCell<Vec<T>>,
+    // the unordered items a rayon task selects the next pages from, the items appended meanwhile
+    // are in `sorted` and `unsorted`
+    sorting: RefCell<Option<Sorting<T>>>,
+    // called by the rayon task once the items are sorted, e.g. to draw them
+    on_sorted: Option<Arc<dyn Fn() + Send + Sync>>,
     tac: bool,
     nosort: bool,
+    // keep the best `limit` items only (the first ones if not sorted)
+    limit: Option<usize>,
+}
+
+struct Sorting<T> {
+    len: usize,
+    // the smallest items, sorted, and the other ones
+    rx: Receiver<(Vec<T>, Vec<T>)>,
 }
 
 impl<T: Send + Sync + Ord + Clone + 'static> Drop for OrderedVec<T> {
     fn drop(&mut self) {
         // guarantees not borrowed elsewhere
-        let sub_vectors = std::mem::take(self.sub_vectors.get_mut());
+        let unsorted = std::mem::take(self.unsorted.get_mut());
         let sorted = std::mem::take(self.sorted.get_mut());
 
-        drop(sub_vectors);
+        drop(unsorted);
         drop(sorted);
     }
 }
@@ -37,10 +61,13 @@ impl<T: Send + Sync + Ord + Clone + 'static> Default for OrderedVec<T> {
 impl<T: Send + Sync + Ord + Clone + 'static> OrderedVec<T> {
     pub fn new() -> Self {
         OrderedVec {
-            sub_vectors: RefCell::new(Vec::new()),
-            sorted: RefCell::new(Vec::with_capacity(ORDERED_SIZE)),
+            sorted: RefCell::new(Vec::with_capacity(PAGE_SIZE)),
+            unsorted: RefCell::new(Vec::new()),
+            sorting: RefCell::new(None),
+            on_sorted: None,
             tac: false,
             nosort: false,
+            limit: None,
         }
     }
 
@@ -49,109 +76,165 @@ impl<T: Send + Sync + Ord + Clone + 'static> OrderedVec<T> {
         self
     }
 
+    pub fn is_tac(&self) -> bool {
+        self.tac
+    }
+
     pub fn nosort(&mut self, nosort: bool) -> &mut Self {
         self.nosort = nosort;
         self
     }
 
+    pub fn is_nosort(&self) -> bool {
+        self.nosort
+    }
+
+    pub fn limit(&mut self, limit: Option<usize>) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// called from a rayon task once the items are sorted in the background
+    pub fn on_sorted(&mut self, on_sorted: Arc<dyn Fn() + Send + Sync>) -> &mut Self {
+        self.on_sorted = Some(on_sorted);
+        self
+    }
+
     pub fn append(&mut self, mut items: Vec<T>) {
         trace!("orderedvec append: new vec size: {}", items.len());
         if self.nosort {
-            self.sorted.borrow_mut().append(&mut items);
+            self.sorted.get_mut().append(&mut items);
+            self.truncate_to_limit();
             return;
         }
 
-        self.sort_vector(&mut items, false);
-        let mut items_smaller = Vec::new();
-
-        {
-            let sorted = self.sorted.borrow();
-            if !sorted.is_empty() {
-                // move the ones <= sorted to sorted
-                while items_smaller.len() < MAX_MOVEMENT
-                    && !items.is_empty()
-                    && self.compare_item(items.last().unwrap(), sorted.last().unwrap()) == Ordering::Less
-                {
-                    items_smaller.push(items.pop().unwrap());
-                }
-            }
+        if self.limit.is_some() {
+            self.unsorted.get_mut().append(&mut items);
+            self.truncate_to_limit();
+            return;
         }
 
-        if !items.is_empty() {
-            self.sub_vectors.borrow_mut().push(items);
+        insert(self.tac, self.sorted.get_mut(), self.unsorted.get_mut(), items);
+    }
+
+    fn truncate_to_limit(&mut self) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+
+        if self.nosort {
+            self.sorted.get_mut().truncate(limit);
+            return;
         }
 
-        let too_many_moved = items_smaller.len() >= ORDERED_SIZE;
-        trace!("append_ordered: num_moved: {}", items_smaller.len());
-
-        {
-            let mut sorted = self.sorted.borrow_mut();
-            sorted.append(&mut items_smaller);
-            if too_many_moved {
-                // means the current sorted vector contains item that's large
-                // so we'll move the sorted vector to partially sorted candidates.
-                self.sort_vector(&mut sorted, false);
-                let old_vec = self.sorted.take();
-                self.sub_vectors.borrow_mut().push(old_vec);
-            } else {
-                self.sort_vector(&mut sorted, true);
+        // keep the best ones, sorted, `tac` only reverses the order they are shown in
+        let mut items = self.take_all();
+        if limit < items.len() {
+            let mut smallest = take_smallest(&mut items, limit, false);
+            if self.tac {
+                smallest.reverse();
             }
+            *self.sorted.get_mut() = smallest;
+        } else {
+            *self.unsorted.get_mut() = items;
         }
-
-        trace!(
-            "orderedvec done append: sub_vector size: {}",
-            self.sub_vectors.borrow().len()
-        );
     }
 
-    fn sort_vector(&self, vec: &mut [T], asc: bool) {
-        let asc = asc ^ self.tac;
-        vec.sort();
+    /// sort the items till `index`, and a few pages more, so that getting them is cheap. Many items
+    /// are sorted in a rayon task, `get_sorted` does not return them until it is done.
+    pub fn sort_till(&self, index: usize) {
+        self.finish_sorting(false);
+        if self.nosort || index < self.sorted.borrow().len() || self.sorting.borrow().is_some() {
+            return;
+        }
 
-        if !asc {
-            vec.reverse();
+        let mut unsorted = self.unsorted.borrow_mut();
+        if unsorted.len() <= BACKGROUND_SORT_SIZE {
+            drop(unsorted);
+            self.sort_now(index);
+            return;
         }
-    }
 
-    #[inline]
-    fn compare_item(&self, a: &T, b: &T) -> Ordering {
-        if !self.tac { a.cmp(b) } else { b.cmp(a) }
+        let num_to_sort = self.num_to_sort(index);
+        trace!(
+            "sort_till: index: {}, num_to_sort: {} in background",
+            index, num_to_sort
+        );
+        let mut items = std::mem::take(&mut *unsorted);
+        let (tx, rx) = bounded(1);
+        *self.sorting.borrow_mut() = Some(Sorting { len: items.len(), rx });
+
+        let tac = self.tac;
+        let on_sorted = self.on_sorted.clone();
+        rayon::spawn(move || {
+            let smallest = take_smallest(&mut items, num_to_sort, tac);
+            // nothing to draw if the items were cleared meanwhile
+            let sent = tx.send((smallest, items)).is_ok();
+            if let Some(on_sorted) = on_sorted.filter(|_| sent) {
+                on_sorted();
+            }
+        });
     }
 
-    fn merge_till(&self, index: usize) {
-        let mut sorted = self.sorted.borrow_mut();
-        let mut vectors = self.sub_vectors.borrow_mut();
-
-        if index >= sorted.len() {
-            trace!("merge_till: index: {}, num_sorted: {}", index, sorted.len());
+    // sort the items till `index` right away, waiting for the rayon task if any
+    fn sort_now(&self, index: usize) {
+        self.finish_sorting(true);
+        if self.nosort || index < self.sorted.borrow().len() {
+            return;
         }
 
-        while index >= sorted.len() {
-            let Some(min_index) = vectors
-                .iter()
-                .map(|v| v.last())
-                .enumerate()
-                .filter_map(|(idx, item)| item.map(|i| (idx, i)))
-                .min_by(|(_, a), (_, b)| self.compare_item(a, b))
-                .map(|(idx, _)| idx)
-            else {
-                break;
-            };
+        let num_to_sort = self.num_to_sort(index);
+        let mut sorted = self.sorted.borrow_mut();
+        let mut unsorted = self.unsorted.borrow_mut();
+        if unsorted.is_empty() {
+            return;
+        }
 
-            let Some(min_item) = vectors[min_index].pop() else {
-                break;
-            };
+        trace!("sort_now: index: {}, num_to_sort: {}", index, num_to_sort);
+        let mut smallest = take_smallest(&mut unsorted, num_to_sort, self.tac);
+        sorted.append(&mut smallest);
+    }
 
-            if vectors[min_index].is_empty() {
-                vectors.remove(min_index);
-            }
+    // the further down, the more pages at once
+    fn num_to_sort(&self, index: usize) -> usize {
+        let num_sorted = self.sorted.borrow().len();
+        max((index + 1).saturating_sub(num_sorted), max(PAGE_SIZE, num_sorted))
+    }
 
-            sorted.push(min_item);
-        }
+    // take the items sorted by the rayon task, if it is done or if `wait`
+    fn finish_sorting(&self, wait: bool) {
+        let mut sorting = self.sorting.borrow_mut();
+        let Some(Sorting { rx, .. }) = sorting.as_ref() else {
+            return;
+        };
+        let sorted_items = match rx.try_recv() {
+            Err(TryRecvError::Empty) if !wait => return,
+            Err(TryRecvError::Empty) => rx.recv().ok(),
+            Ok(sorted_items) => Some(sorted_items),
+            Err(TryRecvError::Disconnected) => None,
+        };
+        *sorting = None;
+
+        let Some((mut smallest, others)) = sorted_items else {
+            return;
+        };
+        let mut sorted = self.sorted.borrow_mut();
+        let mut unsorted = self.unsorted.borrow_mut();
+        sorted.append(&mut smallest);
+        // the ones appended meanwhile are only known not to be smaller than the items sorted before
+        let appended = std::mem::replace(&mut *unsorted, others);
+        insert(self.tac, &mut sorted, &mut unsorted, appended);
     }
 
+    /// the item at `index`, sorting the items till there if needed
     pub fn get(&self, index: usize) -> Option<T> {
-        self.merge_till(index);
+        self.sort_now(index);
+        self.get_sorted(index)
+    }
+
+    /// the item at `index` if it is sorted already, e.g. to draw it without waiting
+    pub fn get_sorted(&self, index: usize) -> Option<T> {
+        self.finish_sorting(false);
         if self.len() <= index {
             None
         } else {
@@ -164,23 +247,47 @@ impl<T: Send + Sync + Ord + Clone + 'static> OrderedVec<T> {
         }
     }
 
+    /// whether the item at `index` is sorted already, or there is none
+    pub fn is_sorted_till(&self, index: usize) -> bool {
+        self.finish_sorting(false);
+        self.nosort || index < self.sorted.borrow().len() || self.len() <= index
+    }
+
     pub fn len(&self) -> usize {
-        let sorted_len = self.sorted.borrow().len();
-        let unsorted_len: usize = self.sub_vectors.borrow().iter().map(|v| v.len()).sum();
-        sorted_len + unsorted_len
+        let num_sorting = self.sorting.borrow().as_ref().map_or(0, |sorting| sorting.len);
+        self.sorted.borrow().len() + self.unsorted.borrow().len() + num_sorting
     }
 
     pub fn clear(&mut self) {
-        self.sub_vectors.get_mut().clear();
+        // the rayon task, if any, sorts for nothing
+        *self.sorting.get_mut() = None;
+        self.unsorted.get_mut().clear();
         self.sorted.get_mut().clear();
     }
 
+    /// keep only the items for which `f` is true, return the number of items removed
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) -> usize {
+        self.finish_sorting(true);
+        let len = self.len();
+        self.sorted.get_mut().retain(&mut f);
+        self.unsorted.get_mut().retain(&mut f);
+        len - self.len()
+    }
+
+    /// remove all the items, in no particular order
+    pub fn take_all(&mut self) -> Vec<T> {
+        self.finish_sorting(true);
+        let mut items = std::mem::take(self.sorted.get_mut());
+        items.append(self.unsorted.get_mut());
+        items
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
     pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
-        self.merge_till(self.len());
+        self.sort_now(self.len());
         OrderedVecIter {
             ordered_vec: self,
             index: 0,
@@ -188,6 +295,83 @@ impl<T: Send + Sync + Ord + Clone + 'static> OrderedVec<T> {
     }
 }
 
+// add items to the sorted and the unordered ones
+fn insert<T: Send + Sync + Ord>(tac: bool, sorted: &mut Vec<T>, unsorted: &mut Vec<T>, mut items: Vec<T>) {
+    let Some(last) = sorted.last() else {
+        unsorted.append(&mut items);
+        return;
+    };
+
+    // the new items smaller than the sorted ones take their place, the greatest sorted ones
+    // become unordered so that the number of sorted items stays the same
+    let (mut smaller, mut larger): (Vec<T>, Vec<T>) = items
+        .into_par_iter()
+        .partition(|item| compare(tac, item, last) == Ordering::Less);
+    unsorted.append(&mut larger);
+    trace!("orderedvec insert: num smaller than sorted: {}", smaller.len());
+
+    if !smaller.is_empty() {
+        let num_sorted = sorted.len();
+        sorted.append(&mut smaller);
+        sorted.par_sort_unstable_by(|a, b| compare(tac, a, b));
+        unsorted.extend(sorted.drain(num_sorted..));
+    }
+}
+
+#[inline]
+fn compare<T: Ord>(tac: bool, a: &T, b: &T) -> Ordering {
+    if !tac { a.cmp(b) } else { b.cmp(a) }
+}
+
+// remove the `num` smallest items, and return them sorted
+fn take_smallest<T: Send + Ord>(items: &mut Vec<T>, num: usize, tac: bool) -> Vec<T> {
+    let cmp = |a: &T, b: &T| compare(tac, a, b);
+    if num.saturating_mul(4) >= items.len() {
+        // a large part of the items, cheaper to sort them all
+        items.par_sort_unstable_by(cmp);
+        let larger = items.split_off(min(num, items.len()));
+        return std::mem::replace(items, larger);
+    }
+
+    // the smallest ones of every chunk, sorted, at the beginning of the chunk
+    let chunk_size = max(MIN_CHUNK_SIZE, items.len().div_ceil(rayon::current_num_threads()));
+    items.par_chunks_mut(chunk_size).for_each(|chunk| {
+        let num = min(num, chunk.len());
+        if num < chunk.len() {
+            chunk.select_nth_unstable_by(num, cmp);
+        }
+        chunk[..num].sort_unstable_by(cmp);
+    });
+
+    // merge the chunks to count how many of the smallest items each of them has
+    let mut heads: Vec<(usize, usize)> = (0..items.len())
+        .step_by(chunk_size)
+        .map(|start| (start, min(start + min(num, chunk_size), items.len())))
+        .collect();
+    let mut counts = vec![0; heads.len()];
+    for _ in 0..num {
+        let Some(chunk_idx) = (0..heads.len())
+            .filter(|&chunk_idx| heads[chunk_idx].0 < heads[chunk_idx].1)
+            .min_by(|&a, &b| cmp(&items[heads[a].0], &items[heads[b].0]))
+        else {
+            break;
+        };
+        heads[chunk_idx].0 += 1;
+        counts[chunk_idx] += 1;
+    }
+
+    // remove them from the last one, so that `swap_remove` never moves one of them
+    let mut smallest = Vec::with_capacity(num);
+    for (chunk_idx, count) in counts.iter().enumerate().rev() {
+        let start = chunk_idx * chunk_size;
+        for idx in (start..start + count).rev() {
+            smallest.push(items.swap_remove(idx));
+        }
+    }
+    smallest.par_sort_unstable_by(cmp);
+    smallest
+}
+
 struct OrderedVecIter<'a, T: Send + Sync + Ord + Clone + 'static> {
     ordered_vec: &'a OrderedVec<T>,
     index: usize,
@@ -282,6 +466,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_limit() {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.limit(Some(4));
+        ordered_vec.append(vec![9, 3, 7, 5]);
+        ordered_vec.append(vec![4, 8, 1]);
+        ordered_vec.append(vec![2, 6, 10]);
+        assert_eq!(ordered_vec.len(), 4);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.limit(Some(3)).tac(true);
+        ordered_vec.append(vec![1, 3, 5, 7]);
+        ordered_vec.append(vec![4, 8, 9]);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [4, 3, 1]);
+        ordered_vec.append(vec![2, 6]);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [3, 2, 1]);
+
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.limit(Some(3)).nosort(true);
+        ordered_vec.append(vec![5, 1]);
+        ordered_vec.append(vec![4, 2]);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [5, 1, 4]);
+    }
+
+    #[test]
+    fn test_sort_in_background() {
+        let num = BACKGROUND_SORT_SIZE * 2;
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append((1..=num).rev().collect());
+        ordered_vec.sort_till(0);
+        assert!(ordered_vec.sorting.borrow().is_some());
+        assert_eq!(ordered_vec.len(), num);
+
+        // appended while sorting, smaller than the items being sorted
+        ordered_vec.append(vec![0, num + 1]);
+        assert_eq!(ordered_vec.len(), num + 2);
+        ordered_vec.finish_sorting(true);
+        assert_eq!(ordered_vec.get_sorted(0), Some(0));
+        assert_eq!(ordered_vec.get_sorted(PAGE_SIZE - 1), Some(PAGE_SIZE - 1));
+        assert_eq!(ordered_vec.get_sorted(PAGE_SIZE), None);
+        assert!(!ordered_vec.is_sorted_till(PAGE_SIZE));
+
+        // sorted right away
+        assert_eq!(ordered_vec.get(PAGE_SIZE), Some(PAGE_SIZE));
+        assert_eq!(ordered_vec.iter().last(), Some(num + 1));
+    }
+
+    #[test]
+    fn test_take_all() {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append(vec![5, 1, 3]);
+        ordered_vec.append(vec![4, 2]);
+        assert_eq!(ordered_vec.get(0).unwrap(), 1);
+
+        let mut items = ordered_vec.take_all();
+        assert!(ordered_vec.is_empty());
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+
+        ordered_vec.tac(true).append(items);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append(vec![5, 1, 3]);
+        assert_eq!(ordered_vec.get(0).unwrap(), 1);
+        ordered_vec.append(vec![4, 2, 0]);
+
+        assert_eq!(ordered_vec.retain(|item| item % 2 == 1), 3);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [1, 3, 5]);
+        ordered_vec.append(vec![2]);
+        assert_eq!(ordered_vec.iter().collect::<Vec<_>>(), [1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_pages() {
+        // a pseudo random permutation of 0..NUM, in several chunks
+        const NUM: usize = 50_000;
+        let items: Vec<usize> = (0..NUM).map(|idx| (idx * 7919 + 12345) % NUM).collect();
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append(items[..10].to_vec());
+        assert_eq!(ordered_vec.get(0).unwrap(), 1940);
+        for batch in items[10..].chunks(7000) {
+            ordered_vec.append(batch.to_vec());
+        }
+
+        // the smaller items took the place of the sorted ones
+        assert_eq!(ordered_vec.sorted.borrow().len(), 10);
+        assert_eq!(ordered_vec.get(0).unwrap(), 0);
+        assert_eq!(ordered_vec.get(10).unwrap(), 10);
+        assert_eq!(ordered_vec.sorted.borrow().len(), 10 + PAGE_SIZE);
+        assert_eq!(ordered_vec.get(20_000).unwrap(), 20_000);
+        assert_eq!(ordered_vec.len(), NUM);
+        assert!(ordered_vec.iter().eq(0..NUM));
+
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.tac(true).append(items);
+        assert_eq!(ordered_vec.get(1000).unwrap(), NUM - 1001);
+        assert!(ordered_vec.iter().eq((0..NUM).rev()));
+    }
+
     #[test]
     fn test_equals() {
         let a = vec![1, 2, 3, 4];
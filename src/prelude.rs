@@ -1,8 +1,13 @@
 pub use crate::ansi::AnsiString;
 pub use crate::engine::{factory::*, fuzzy::FuzzyAlgorithm};
 pub use crate::event::Event;
+pub use crate::helper::follow::FollowReader;
 pub use crate::helper::item_reader::{SkimItemReader, SkimItemReaderOption};
+pub use crate::helper::record::InputFormat;
 pub use crate::helper::selector::DefaultSkimSelector;
+pub use crate::helper::source::{ItemSource, SourceInput};
+pub use crate::helper::unique::UniqueKeep;
+pub use crate::helper::walker::{SkimWalker, WalkerOption};
 pub use crate::options::{SkimOptions, SkimOptionsBuilder};
 pub use crate::output::SkimOutput;
 pub use crate::*;
@@ -1,6 +1,5 @@
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use std::cmp::{max, min};
-use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -11,9 +10,12 @@ use tuikit::prelude::{Event as TermEvent, *};
 
 use crate::ansi::{ANSIParser, AnsiString};
 use crate::event::{Event, EventHandler, UpdateScreen};
-use crate::item::MatchedItem;
+use crate::item::{MatchedItem, same_item};
+use crate::shell::Shell;
 use crate::spinlock::SpinLock;
-use crate::util::{atoi, clear_canvas, depends_on_items, inject_command, InjectContext};
+use crate::util::{
+    InjectContext, atoi, clear_canvas, depends_on_items, inject_argv, inject_command, inject_field_names, split_argv,
+};
 use crate::{ItemPreview, PreviewContext, PreviewPosition, SkimItem};
 
 const TAB_STOP: usize = 8;
@@ -32,11 +34,16 @@ pub struct Previewer {
     prev_item: Option<Arc<dyn SkimItem>>,
     prev_query: Option<String>,
     prev_cmd_query: Option<String>,
+    prev_captures: Vec<Box<str>>,
     prev_num_selected: usize,
 
     preview_cmd: Option<String>,
     preview_offset: String, // e.g. +SCROLL-OFFSET
     delimiter: Regex,
+    field_names: Vec<String>,
+    shell: Shell,
+    // the preview command is run without a shell
+    argv: bool,
 }
 
 impl Previewer {
@@ -87,11 +94,15 @@ impl Previewer {
             prev_item: None,
             prev_query: None,
             prev_cmd_query: None,
+            prev_captures: Vec::new(),
             prev_num_selected: 0,
 
             preview_cmd,
             preview_offset: "".to_string(),
             delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            field_names: Vec::new(),
+            shell: Shell::default(),
+            argv: false,
         }
     }
 
@@ -105,6 +116,24 @@ impl Previewer {
         self
     }
 
+    // the names of the fields that can be used in place of their indices, e.g. `{name}`
+    pub fn field_names(mut self, field_names: Vec<String>) -> Self {
+        self.field_names = field_names;
+        self
+    }
+
+    // the shell that runs the preview command, its fields are quoted for it
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    // run the preview command without a shell, split into its arguments
+    pub fn argv(mut self, argv: bool) -> Self {
+        self.argv = argv;
+        self
+    }
+
     // e.g. +SCROLL-OFFSET
     pub fn preview_offset(mut self, offset: String) -> Self {
         self.preview_offset = offset;
@@ -118,6 +147,7 @@ impl Previewer {
         new_item: impl Into<Option<Arc<dyn SkimItem>>>,
         new_query: impl Into<Option<String>>,
         new_cmd_query: impl Into<Option<String>>,
+        captures: Vec<Box<str>>,
         num_selected: usize,
         get_selected_items: impl Fn() -> (Vec<usize>, Vec<MatchedItem>), // LazyLock get
         force: bool,
@@ -130,8 +160,7 @@ impl Previewer {
             (None, None) => false,
             (None, Some(_)) => true,
             (Some(_), None) => true,
-            #[allow(clippy::vtable_address_comparisons)]
-            (Some(prev), Some(new)) => !Arc::ptr_eq(prev, new),
+            (Some(prev), Some(new)) => !same_item(prev, new),
         };
 
         let query_changed = match (self.prev_query.as_ref(), new_query.as_ref()) {
@@ -148,15 +177,25 @@ impl Previewer {
             (Some(prev), Some(cur)) => prev != cur,
         };
 
+        // the captures of the item may arrive after the query changed
+        let captures_changed = self.prev_captures != captures;
+
         let selected_items_changed = self.prev_num_selected != num_selected;
 
-        if !force && !item_changed && !query_changed && !cmd_query_changed && !selected_items_changed {
+        if !force
+            && !item_changed
+            && !query_changed
+            && !cmd_query_changed
+            && !captures_changed
+            && !selected_items_changed
+        {
             return;
         }
 
         self.prev_item = new_item.clone();
         self.prev_query = new_query;
         self.prev_cmd_query = new_cmd_query;
+        self.prev_captures = captures;
         self.prev_num_selected = num_selected;
 
         // prepare preview context
@@ -166,6 +205,12 @@ impl Previewer {
             .as_ref()
             .map(|item| item.output())
             .unwrap_or_else(|| "".into());
+        let current_fields = self
+            .prev_item
+            .as_ref()
+            .map(|item| item.field_text())
+            .unwrap_or_else(|| "".into());
+        let current_source = self.prev_item.as_ref().and_then(|item| item.source()).unwrap_or("");
         let query = self.prev_query.as_deref().unwrap_or("");
         let cmd_query = self.prev_cmd_query.as_deref().unwrap_or("");
 
@@ -183,10 +228,14 @@ impl Previewer {
             current_index: new_item_index,
             delimiter: &self.delimiter,
             current_selection: &current_selection,
+            current_fields: &current_fields,
             selections: selected_texts.as_slice(),
             indices: &indices,
             query,
             cmd_query,
+            captures: &self.prev_captures,
+            current_source,
+            quoting: self.shell.quoting(),
         };
 
         let preview_context = PreviewContext {
@@ -207,26 +256,34 @@ impl Previewer {
                 (ItemPreview::TextWithPos(text, pos), _) => PreviewEvent::PreviewPlainText(text, pos),
                 (ItemPreview::AnsiWithPos(text, pos), _) => PreviewEvent::PreviewAnsiText(text, pos),
                 (ItemPreview::Command(cmd), pos) | (ItemPreview::CommandWithPos(cmd, pos), _) => {
+                    let cmd = inject_field_names(&cmd, &self.field_names);
                     if depends_on_items(&cmd) && self.prev_item.is_none() {
                         debug!("the command for preview refers to items and currently there is no item");
                         debug!("command to execute: [{}]", cmd);
                         PreviewEvent::PreviewPlainText("no item matched".to_string(), Default::default())
                     } else {
-                        let cmd = inject_command(&cmd, inject_context).to_string();
-                        let preview_command = PreviewCommand { cmd, columns, lines };
+                        let preview_command = PreviewCommand {
+                            argv: self.command_argv(&cmd, inject_context, false),
+                            columns,
+                            lines,
+                        };
                         PreviewEvent::PreviewCommand(preview_command, pos)
                     }
                 }
                 (ItemPreview::Global, _) => {
                     let cmd = self.preview_cmd.clone().expect("previewer: not provided");
+                    let cmd = inject_field_names(&cmd, &self.field_names);
                     if depends_on_items(&cmd) && self.prev_item.is_none() {
                         debug!("the command for preview refers to items and currently there is no item");
                         debug!("command to execute: [{}]", cmd);
                         PreviewEvent::PreviewPlainText("no item matched".to_string(), Default::default())
                     } else {
-                        let cmd = inject_command(&cmd, inject_context).to_string();
                         let pos = self.eval_scroll_offset(inject_context);
-                        let preview_command = PreviewCommand { cmd, columns, lines };
+                        let preview_command = PreviewCommand {
+                            argv: self.command_argv(&cmd, inject_context, self.argv),
+                            columns,
+                            lines,
+                        };
                         PreviewEvent::PreviewCommand(preview_command, pos)
                     }
                 }
@@ -237,6 +294,21 @@ impl Previewer {
         let _ = self.tx_preview.send(preview_event);
     }
 
+    // the program and the arguments that run the command, with the shell unless `argv`, nothing if
+    // the command is empty
+    fn command_argv(&self, cmd: &str, context: InjectContext, argv: bool) -> Vec<String> {
+        if argv {
+            return inject_argv(&split_argv(cmd), context);
+        }
+
+        let cmd = inject_command(cmd, context);
+        if cmd.is_empty() {
+            Vec::new()
+        } else {
+            self.shell.argv(&cmd)
+        }
+    }
+
     fn act_scroll_down(&mut self, diff: i32) {
         let vscroll_offset = self.vscroll_offset.load(Ordering::SeqCst);
         let new_offset = if diff > 0 {
@@ -278,7 +350,8 @@ impl Previewer {
             return Default::default();
         }
 
-        let offset_expr = inject_command(&self.preview_offset, context);
+        let preview_offset = inject_field_names(&self.preview_offset, &self.field_names);
+        let offset_expr = inject_command(&preview_offset, context);
         if offset_expr.is_empty() {
             return Default::default();
         }
@@ -399,7 +472,8 @@ impl Widget<Event> for Previewer {
 
 #[derive(Debug, Ord, PartialOrd, PartialEq, Eq)]
 pub struct PreviewCommand {
-    pub cmd: String,
+    // the program and its arguments, empty if there is nothing to run
+    pub argv: Vec<String>,
     pub lines: usize,
     pub columns: usize,
 }
@@ -456,19 +530,14 @@ fn run(rx_preview: Receiver<PreviewEvent>, on_return: Box<dyn Fn(Vec<AnsiString>
 
         match event {
             PreviewEvent::PreviewCommand(preview_cmd, pos) => {
-                let cmd = &preview_cmd.cmd;
-                if cmd.is_empty() {
+                let Some((program, args)) = preview_cmd.argv.split_first() else {
                     continue;
-                }
-
-                let env_command = which::which("env").unwrap_or_else(|_| PathBuf::from("/usr/bin/env"));
+                };
 
-                let spawned = Command::new(env_command)
+                let spawned = Command::new(program)
+                    .args(args)
                     .env("LINES", preview_cmd.lines.to_string())
                     .env("COLUMNS", preview_cmd.columns.to_string())
-                    .arg("bash")
-                    .arg("-c")
-                    .arg(cmd)
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
                     .spawn();
@@ -1,4 +1,5 @@
 use crate::global::mark_new_run;
+use crate::helper::walker::WalkerOption;
 use crate::item::ItemPool;
 ///! Reader is used for reading items from datasource (e.g. stdin or command output)
 ///!
@@ -8,6 +9,9 @@ use crate::{SkimItem, SkimItemReceiver};
 use crossbeam_channel::TryRecvError;
 use crossbeam_channel::{unbounded, Select, Sender};
 use std::cell::RefCell;
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitStatus;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, TryLockError, Weak};
@@ -22,6 +26,8 @@ use crate::malloc_trim;
 pub const ITEMS_INITIAL_CAPACITY: usize = 65_536;
 const SLEEP_FAST: Duration = Duration::from_millis(1);
 const SLEEP_SLOW: Duration = Duration::from_millis(10);
+// the end of the last line written to stderr that is kept
+const STDERR_TAIL_MAX_LEN: usize = 256;
 
 pub trait CommandCollector {
     /// execute the `cmd` and produce a
@@ -36,6 +42,99 @@ pub trait CommandCollector {
         cmd: &str,
         components_to_stop: Arc<AtomicUsize>,
     ) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>);
+
+    /// list the files under `roots` with the built-in walker, the source of items when there is
+    /// neither input nor command. `None` if the collector cannot walk, the default command is
+    /// `invoke`d instead.
+    fn walk_files(
+        &mut self,
+        _roots: Vec<PathBuf>,
+        _walker: WalkerOption,
+        _components_to_stop: Arc<AtomicUsize>,
+    ) -> Option<(SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>)> {
+        None
+    }
+
+    /// the status of the process of the command that was `invoke`d last, if any
+    fn process_status(&self) -> Option<Arc<ProcessStatus>> {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    Exited(i32),
+    /// killed by a signal, e.g. once its items are not wanted anymore
+    Signaled(i32),
+}
+
+/// The state of the process of a command and the last line it wrote to stderr, updated while it
+/// runs
+#[derive(Debug)]
+pub struct ProcessStatus {
+    state: Mutex<(ProcessState, String)>,
+}
+
+impl Default for ProcessStatus {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new((ProcessState::Running, String::new())),
+        }
+    }
+}
+
+impl ProcessStatus {
+    pub fn state(&self) -> ProcessState {
+        self.state
+            .lock()
+            .map(|state| state.0.clone())
+            .unwrap_or(ProcessState::Running)
+    }
+
+    pub fn stderr_tail(&self) -> String {
+        self.state.lock().map(|state| state.1.clone()).unwrap_or_default()
+    }
+
+    pub fn set_exit_status(&self, status: ExitStatus) {
+        let state = exit_state(status);
+        if let Ok(mut locked) = self.state.lock() {
+            locked.0 = state;
+        }
+    }
+
+    /// a line written to stderr, only the end of the last one that is not blank is kept
+    pub fn push_stderr(&self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        let mut start = line.len().saturating_sub(STDERR_TAIL_MAX_LEN);
+        while !line.is_char_boundary(start) {
+            start += 1;
+        }
+        if let Ok(mut locked) = self.state.lock() {
+            locked.1 = line[start..].to_string();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_state(status: ExitStatus) -> ProcessState {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (Some(code), _) => ProcessState::Exited(code),
+        (None, Some(signal)) => ProcessState::Signaled(signal),
+        (None, None) => ProcessState::Exited(-1),
+    }
+}
+
+// without signals, a process always has an exit code
+#[cfg(not(unix))]
+fn exit_state(status: ExitStatus) -> ProcessState {
+    ProcessState::Exited(status.code().unwrap_or(-1))
 }
 
 pub struct ReaderControl {
@@ -45,6 +144,7 @@ pub struct ReaderControl {
     items: Arc<Mutex<Vec<Arc<dyn SkimItem>>>>,
     thread_reader: Option<JoinHandle<()>>,
     thread_ingest: Option<JoinHandle<()>>,
+    process_status: Option<Arc<ProcessStatus>>,
 }
 
 impl Drop for ReaderControl {
@@ -110,10 +210,39 @@ impl ReaderControl {
     pub fn is_done(&self) -> bool {
         self.all_stopped() && self.is_empty()
     }
+
+    /// the status of the process of the command, `None` if the items are not read from a command
+    pub fn process_status(&self) -> Option<&ProcessStatus> {
+        self.process_status.as_deref()
+    }
+}
+
+/// The command run when there is neither input nor command: `SKIM_DEFAULT_COMMAND`, or `find .`
+/// if the files are not listed by the built-in walker
+pub fn default_command() -> String {
+    match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
+        Ok("") | Err(_) => "find .".to_owned(),
+        Ok(val) => val.to_owned(),
+    }
+}
+
+/// The walk that lists the files of the current directory when there is neither input nor
+/// command: unless `SKIM_DEFAULT_COMMAND` is set, or always with `options.walker`
+pub fn default_walker(options: &SkimOptions) -> Option<WalkerOption> {
+    let default_command_set = env::var("SKIM_DEFAULT_COMMAND").is_ok_and(|cmd| !cmd.is_empty());
+    if options.cmd.is_some() || options.interactive || (default_command_set && options.walker.is_none()) {
+        return None;
+    }
+    Some(WalkerOption::from_spec(
+        options.walker.unwrap_or(WalkerOption::DEFAULT_SPEC),
+    ))
 }
 
 pub struct Reader {
     cmd_collector: Rc<RefCell<dyn CommandCollector>>,
+    // the walk that replaces the runs of `default_command`
+    walker: Option<WalkerOption>,
+    default_command: String,
     rx_item: Option<SkimItemReceiver>,
 }
 
@@ -121,6 +250,8 @@ impl Reader {
     pub fn with_options(options: &SkimOptions) -> Self {
         Self {
             cmd_collector: options.cmd_collector.clone(),
+            walker: default_walker(options),
+            default_command: default_command(),
             rx_item: None,
         }
     }
@@ -137,12 +268,23 @@ impl Reader {
         let items_strong = Arc::new(Mutex::new(Vec::with_capacity(ITEMS_INITIAL_CAPACITY)));
         let items_weak = Arc::downgrade(&items_strong);
 
-        let (rx_item, tx_interrupt_cmd, opt_ingest_handle) =
-            self.rx_item.take().map(|rx| (rx, None, None)).unwrap_or_else(|| {
+        let (rx_item, tx_interrupt_cmd, opt_ingest_handle, process_status) =
+            self.rx_item.take().map(|rx| (rx, None, None, None)).unwrap_or_else(|| {
                 let components_to_stop_clone = components_to_stop.clone();
+                let mut cmd_collector = self.cmd_collector.borrow_mut();
+                // e.g. refreshed, the default command is walked again, an edited one is invoked
+                let walker = self.walker.clone().filter(|_| cmd == self.default_command);
+                let walked = walker.and_then(|walker| {
+                    cmd_collector.walk_files(vec![PathBuf::from(".")], walker, components_to_stop_clone.clone())
+                });
                 let (rx_item, tx_interrupt_cmd, opt_ingest_handle) =
-                    self.cmd_collector.borrow_mut().invoke(cmd, components_to_stop_clone);
-                (rx_item, Some(tx_interrupt_cmd), opt_ingest_handle)
+                    walked.unwrap_or_else(|| cmd_collector.invoke(cmd, components_to_stop_clone));
+                (
+                    rx_item,
+                    Some(tx_interrupt_cmd),
+                    opt_ingest_handle,
+                    cmd_collector.process_status(),
+                )
             });
 
         let components_to_stop_clone = components_to_stop.clone();
@@ -155,6 +297,7 @@ impl Reader {
             items: items_strong,
             thread_reader: Some(thread_reader),
             thread_ingest: opt_ingest_handle,
+            process_status,
         }
     }
 }
@@ -235,3 +378,165 @@ fn collect_item(
 
     (tx_interrupt, thread_reader)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::Instant;
+
+    // the items read once the command is over, or `None` if it does not end in time
+    fn wait_until_stopped(ctrl: &mut ReaderControl) -> Option<Vec<Arc<dyn SkimItem>>> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !ctrl.all_stopped() {
+            if Instant::now() > deadline {
+                return None;
+            }
+            sleep(SLEEP_SLOW);
+        }
+        Some(ctrl.take())
+    }
+
+    #[test]
+    fn test_push_stderr() {
+        let status = ProcessStatus::default();
+        assert_eq!(status.state(), ProcessState::Running);
+
+        status.push_stderr("  first error\n");
+        assert_eq!(status.stderr_tail(), "first error");
+
+        // the blank lines do not hide the last error
+        status.push_stderr("");
+        status.push_stderr(" \t\n");
+        assert_eq!(status.stderr_tail(), "first error");
+
+        // cut after a 3-byte char, not in the middle of one
+        let line = "€".repeat(100);
+        status.push_stderr(&line);
+        let tail = status.stderr_tail();
+        assert_eq!(tail.len(), 255);
+        assert!(line.ends_with(&tail));
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let status = ProcessStatus::default();
+        status.set_exit_status(Command::new("sh").args(["-c", "exit 3"]).status().unwrap());
+        assert_eq!(status.state(), ProcessState::Exited(3));
+
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        child.kill().unwrap();
+        status.set_exit_status(child.wait().unwrap());
+        #[cfg(unix)]
+        assert_eq!(status.state(), ProcessState::Signaled(9));
+        #[cfg(not(unix))]
+        assert!(matches!(status.state(), ProcessState::Exited(_)));
+    }
+
+    // what the model waits for before the items of the previous command are replaced
+    #[test]
+    fn test_wait_for_cmd() {
+        let options = SkimOptions {
+            interactive: true,
+            ..Default::default()
+        };
+        let mut reader = Reader::with_options(&options);
+
+        // without output, only the end of the command is noticed
+        let mut ctrl = reader.run("echo failed >&2; exit 3");
+        let items = wait_until_stopped(&mut ctrl).expect("the command did not end");
+        assert!(items.is_empty());
+        let status = ctrl.process_status().unwrap();
+        assert_eq!(status.state(), ProcessState::Exited(3));
+        assert_eq!(status.stderr_tail(), "failed");
+
+        // the first items come before the end of the command
+        let mut ctrl = reader.run("echo a; sleep 10");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while ctrl.is_empty() && Instant::now() < deadline {
+            sleep(SLEEP_SLOW);
+        }
+        assert!(!ctrl.is_empty());
+        assert!(!ctrl.all_stopped());
+        assert_eq!(ctrl.process_status().unwrap().state(), ProcessState::Running);
+
+        // the previous command is killed once the next one runs
+        ctrl.kill();
+        assert!(ctrl.all_stopped());
+    }
+
+    // records the runs instead of reading any item
+    #[derive(Default)]
+    struct RunsCollector {
+        runs: Vec<String>,
+    }
+
+    impl RunsCollector {
+        fn no_items(&self) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>) {
+            let (_, rx_item) = unbounded();
+            let (tx_interrupt, _) = unbounded();
+            (rx_item, tx_interrupt, None)
+        }
+    }
+
+    impl CommandCollector for RunsCollector {
+        fn invoke(
+            &mut self,
+            cmd: &str,
+            _components_to_stop: Arc<AtomicUsize>,
+        ) -> (SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>) {
+            self.runs.push(cmd.to_string());
+            self.no_items()
+        }
+
+        fn walk_files(
+            &mut self,
+            _roots: Vec<PathBuf>,
+            _walker: WalkerOption,
+            _components_to_stop: Arc<AtomicUsize>,
+        ) -> Option<(SkimItemReceiver, Sender<i32>, Option<JoinHandle<()>>)> {
+            self.runs.push("walk".to_string());
+            Some(self.no_items())
+        }
+    }
+
+    #[test]
+    fn test_walk_every_run() {
+        let collector = Rc::new(RefCell::new(RunsCollector::default()));
+        let options = SkimOptions {
+            cmd_collector: collector.clone(),
+            walker: Some("file"),
+            ..Default::default()
+        };
+        let mut reader = Reader::with_options(&options);
+
+        // e.g. refreshed, the default command is walked again
+        reader.run(&default_command());
+        reader.run(&default_command());
+        // unless it is edited
+        reader.run("ls");
+        assert_eq!(collector.borrow().runs, ["walk", "walk", "ls"]);
+    }
+
+    #[test]
+    fn test_default_walker() {
+        let options = SkimOptions {
+            walker: Some("dir"),
+            ..Default::default()
+        };
+        assert_eq!(default_walker(&options), Some(WalkerOption::from_spec("dir")));
+
+        let options = SkimOptions {
+            cmd: Some("ls"),
+            walker: Some("dir"),
+            ..Default::default()
+        };
+        assert_eq!(default_walker(&options), None);
+
+        let options = SkimOptions {
+            interactive: true,
+            ..Default::default()
+        };
+        assert_eq!(default_walker(&options), None);
+    }
+}
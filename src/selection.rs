@@ -1,6 +1,7 @@
+use std::cell::RefCell;
 use std::cmp::max;
 use std::cmp::min;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -13,8 +14,9 @@ use crate::item::MatchedItem;
 use crate::orderedvec::OrderedVec;
 use crate::theme::{ColorTheme, DEFAULT_THEME};
 use crate::util::clear_canvas;
-use crate::util::{LinePrinter, print_item, reshape_string};
-use crate::{DisplayContext, MatchRange, Matches, Selector, SkimItem, SkimOptions};
+use crate::util::{LinePrinter, accumulate_text_width, print_item, reshape_string};
+use crate::{AnsiString, DisplayContext, MatchRange, Matches, Selector, SkimItem, SkimOptions};
+use rayon::prelude::*;
 use regex::Regex;
 use unicode_width::UnicodeWidthStr;
 
@@ -45,6 +47,10 @@ pub struct Selection {
     skip_to_pattern: Option<Regex>,
     height: AtomicUsize,
     tabstop: usize,
+    // maximum rows of an item that contains newlines
+    multi_line: usize,
+    // the rows of the items by index, counted once, only with `multi_line`
+    rows_by_item: RefCell<HashMap<u32, usize>>,
 
     // Options
     multi_selection: bool,
@@ -87,6 +93,8 @@ impl Selection {
             skip_to_pattern: None,
             height: AtomicUsize::new(0),
             tabstop: 8,
+            multi_line: 1,
+            rows_by_item: RefCell::new(HashMap::new()),
             multi_selection: false,
             reverse: false,
             no_hscroll: false,
@@ -129,10 +137,13 @@ impl Selection {
             self.items.nosort(true);
         }
 
+        self.items.limit(options.max_results);
+
         if !options.skip_to_pattern.is_empty() {
             self.skip_to_pattern = Regex::new(options.skip_to_pattern).ok();
         }
 
+        self.multi_line = max(1, options.multi_line);
         self.keep_right = options.keep_right;
         self.selector = options.selector.clone();
     }
@@ -142,6 +153,12 @@ impl Selection {
         self
     }
 
+    /// called from another thread once the items are sorted in the background, to draw them
+    pub fn on_sorted(mut self, on_sorted: impl Fn() + Send + Sync + 'static) -> Self {
+        self.items.on_sorted(Arc::new(on_sorted));
+        self
+    }
+
     pub fn append_sorted_items(&mut self, items: Vec<MatchedItem>) {
         debug!("append_sorted_items: num: {}", items.len());
         let current_run_num = current_run_num();
@@ -156,7 +173,30 @@ impl Selection {
 
         self.items.append(items);
         self.pre_selected_watermark = max(self.pre_selected_watermark, self.items.len());
+        self.fit_cursor();
+        self.sort_screen();
+    }
 
+    /// drop the matched items evicted from the item pool, the ones before index `first`, and
+    /// return their number
+    pub fn evict(&mut self, first: usize) -> usize {
+        let num_evicted = self.items.retain(|item| item.item_idx as usize >= first);
+        let run_num = current_run_num();
+        self.selected
+            .retain(|&(item_run_num, item_idx), _| item_run_num != run_num || item_idx as usize >= first);
+        self.rows_by_item
+            .get_mut()
+            .retain(|&item_idx, _| item_idx as usize >= first);
+        if num_evicted > 0 {
+            self.pre_selected_watermark = min(self.pre_selected_watermark, self.items.len());
+            self.fit_cursor();
+            self.sort_screen();
+        }
+        num_evicted
+    }
+
+    // keep the cursor on an item when there are fewer of them
+    fn fit_cursor(&mut self) {
         let height = self.height.load(Ordering::Relaxed);
         if self.items.len() <= self.line_cursor {
             // if not enough items, move cursor down
@@ -167,10 +207,60 @@ impl Selection {
             // if not enough items, scroll the cursor a page down
             self.item_cursor = max(self.items.len(), height) - height;
         }
+
+        if self.multi_line > 1 {
+            // the blocks of the new items may push the cursor off the screen
+            self.move_block_cursor(0);
+        }
+    }
+
+    // sort the items of the screen and of the next one, so that drawing them never does
+    fn sort_screen(&self) {
+        let height = self.height.load(Ordering::Relaxed);
+        self.items.sort_till(self.item_cursor + 2 * height);
     }
 
     pub fn clear(&mut self) {
         self.items.clear();
+        self.rows_by_item.get_mut().clear();
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        !self.items.is_nosort()
+    }
+
+    pub fn is_tac(&self) -> bool {
+        self.items.is_tac()
+    }
+
+    /// sort the items already matched, or put them back in the input order
+    pub fn act_toggle_sort(&mut self) {
+        let nosort = !self.items.is_nosort();
+        self.reorder(self.items.is_tac(), nosort);
+    }
+
+    pub fn act_toggle_tac(&mut self) {
+        let tac = !self.items.is_tac();
+        self.reorder(tac, self.items.is_nosort());
+    }
+
+    /// sort the items again, once `update` changed their ranks
+    pub fn rerank(&mut self, update: impl Fn(&mut MatchedItem) + Send + Sync) {
+        let mut items = self.items.take_all();
+        items.par_iter_mut().for_each(update);
+        self.items.append(items);
+        self.sort_screen();
+    }
+
+    fn reorder(&mut self, tac: bool, nosort: bool) {
+        let mut items = self.items.take_all();
+        if nosort {
+            // the items are matched in the input order
+            items.sort_unstable_by_key(|item| item.item_idx);
+        }
+        self.items.tac(tac).nosort(nosort);
+        self.items.append(items);
+        self.sort_screen();
     }
 
     fn pre_select(&mut self, items: &[MatchedItem]) {
@@ -196,6 +286,10 @@ impl Selection {
     // > 0 means move up, < 0 means move down
     pub fn act_move_line_cursor(&mut self, diff: i32) {
         let diff = if self.reverse { -diff } else { diff };
+        if self.multi_line > 1 {
+            self.move_block_cursor(diff);
+            return;
+        }
 
         let mut line_cursor = self.line_cursor as i32;
         let mut item_cursor = self.item_cursor as i32;
@@ -222,8 +316,91 @@ impl Selection {
         self.line_cursor = line_cursor as usize;
     }
 
+    // > 0 means move to the later items, the screen is scrolled until the block of the cursor fits
+    fn move_block_cursor(&mut self, diff: i32) {
+        let item_len = self.items.len();
+        if item_len == 0 {
+            self.item_cursor = 0;
+            self.line_cursor = 0;
+            return;
+        }
+
+        let height = max(self.height.load(Ordering::Relaxed), 1);
+        let cursor = (self.item_cursor + self.line_cursor) as i64 + diff as i64;
+        let cursor = max(0, min(cursor, item_len as i64 - 1)) as usize;
+
+        // every item takes at least one row
+        let mut item_cursor = max(min(self.item_cursor, cursor), cursor.saturating_sub(height - 1));
+        let mut rows: usize = (item_cursor..=cursor).map(|idx| self.item_rows(idx)).sum();
+        while rows > height && item_cursor < cursor {
+            rows -= self.item_rows(item_cursor);
+            item_cursor += 1;
+        }
+
+        self.item_cursor = item_cursor;
+        self.line_cursor = cursor - item_cursor;
+    }
+
+    /// the number of rows the item takes on the screen
+    fn item_rows(&self, item_idx: usize) -> usize {
+        if self.multi_line <= 1 {
+            return 1;
+        }
+
+        self.items.get(item_idx).map(|item| self.rows_of(&item)).unwrap_or(1)
+    }
+
+    fn rows_of(&self, item: &MatchedItem) -> usize {
+        if self.multi_line <= 1 {
+            return 1;
+        }
+
+        *self.rows_by_item.borrow_mut().entry(item.item_idx).or_insert_with(|| {
+            let item = item.upgrade_infallible();
+            let lines = item.text().trim_end_matches('\n').split('\n').count();
+            min(lines, self.multi_line)
+        })
+    }
+
+    // the number of items covered by moving `rows` rows on the screen from the cursor
+    fn rows_to_items(&self, rows: i32) -> i32 {
+        if self.multi_line <= 1 || rows == 0 {
+            return rows;
+        }
+
+        let forward = (rows > 0) != self.reverse;
+        let mut cursor = self.item_cursor + self.line_cursor;
+        let mut rows_left = rows.unsigned_abs() as usize;
+        let mut items = 0;
+        loop {
+            let next = if forward {
+                cursor + 1
+            } else if cursor > 0 {
+                cursor - 1
+            } else {
+                break;
+            };
+            if next >= self.items.len() {
+                break;
+            }
+            let item_rows = self.item_rows(next);
+            if item_rows > rows_left {
+                break;
+            }
+            rows_left -= item_rows;
+            items += 1;
+            cursor = next;
+        }
+
+        max(items, 1) * rows.signum()
+    }
+
     pub fn act_select_screen_row(&mut self, rows_to_top: usize) {
         let height = self.height.load(Ordering::Relaxed);
+        if self.multi_line > 1 {
+            self.select_block_row(rows_to_top, height);
+            return;
+        }
         let diff = if self.reverse {
             self.line_cursor as i32 - rows_to_top as i32
         } else {
@@ -232,6 +409,22 @@ impl Selection {
         self.act_move_line_cursor(diff);
     }
 
+    fn select_block_row(&mut self, rows_to_top: usize, height: usize) {
+        let mut used = 0;
+        let mut item_idx = self.item_cursor;
+        while used < height && item_idx < self.items.len() {
+            let rows = min(self.item_rows(item_idx), height - used);
+            let top = if self.reverse { used } else { height - used - rows };
+            if (top..top + rows).contains(&rows_to_top) {
+                let diff = item_idx as i32 - (self.item_cursor + self.line_cursor) as i32;
+                self.move_block_cursor(diff);
+                return;
+            }
+            used += rows;
+            item_idx += 1;
+        }
+    }
+
     #[allow(clippy::map_entry)]
     pub fn act_toggle(&mut self) {
         if !self.multi_selection || self.items.is_empty() {
@@ -340,11 +533,37 @@ impl Selection {
         self.multi_selection
     }
 
+    /// whether the current item is sorted already, or there is none, so that getting it does not wait
+    pub fn is_current_sorted(&self) -> bool {
+        self.items.is_sorted_till(self.get_current_item_idx())
+    }
+
     pub fn get_current_item(&self) -> Option<Arc<dyn SkimItem>> {
         let item_idx = self.get_current_item_idx();
         self.items.get(item_idx).map(|item| item.upgrade_infallible())
     }
 
+    /// the text of the whole match and of the capture groups of the current item, if matched by a
+    /// regex with capture groups
+    pub fn get_current_captures(&self) -> Vec<Box<str>> {
+        let item_idx = self.get_current_item_idx();
+        let Some(matched_item) = self.items.get(item_idx) else {
+            return Vec::new();
+        };
+
+        match &matched_item.matched_range {
+            Some(MatchRange::Groups(groups)) => {
+                let item = matched_item.upgrade_infallible();
+                let text = item.text();
+                groups
+                    .iter()
+                    .map(|&(start, end)| text.get(start..end).unwrap_or("").into())
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
     pub fn get_hscroll_offset(&self) -> i64 {
         self.hscroll_offset
     }
@@ -392,19 +611,19 @@ impl EventHandler for Selection {
             }
             EvActHalfPageDown(diff) => {
                 let height = 1 - (self.height.load(Ordering::Relaxed) as i32);
-                self.act_move_line_cursor(height * *diff / 2);
+                self.act_move_line_cursor(self.rows_to_items(height * *diff / 2));
             }
             EvActHalfPageUp(diff) => {
                 let height = (self.height.load(Ordering::Relaxed) as i32) - 1;
-                self.act_move_line_cursor(height * *diff / 2);
+                self.act_move_line_cursor(self.rows_to_items(height * *diff / 2));
             }
             EvActPageDown(diff) => {
                 let height = 1 - (self.height.load(Ordering::Relaxed) as i32);
-                self.act_move_line_cursor(height * *diff);
+                self.act_move_line_cursor(self.rows_to_items(height * *diff));
             }
             EvActPageUp(diff) => {
                 let height = (self.height.load(Ordering::Relaxed) as i32) - 1;
-                self.act_move_line_cursor(height * *diff);
+                self.act_move_line_cursor(self.rows_to_items(height * *diff));
             }
             EvActSelectRow(row) => {
                 self.act_select_screen_row(*row);
@@ -415,19 +634,51 @@ impl EventHandler for Selection {
             EvActScrollRight(diff) => {
                 self.act_scroll(*diff);
             }
+            EvActToggleTac => {
+                self.act_toggle_tac();
+            }
             _ => return UpdateScreen::DONT_REDRAW,
         }
+        self.sort_screen();
         UpdateScreen::REDRAW
     }
 }
 
 impl Selection {
+    // the whole match and the capture groups as non-overlapping ranges, an inner group wins
+    fn group_ranges(&self, groups: &[(usize, usize)], is_current: bool) -> Vec<(Attr, (usize, usize))> {
+        let attr = |group: usize| match (group, is_current) {
+            (0, true) => self.theme.current_match(),
+            (0, false) => self.theme.matched(),
+            (_, true) => self.theme.current_match_group(group),
+            (_, false) => self.theme.matched_group(group),
+        };
+
+        let mut bounds: Vec<usize> = groups.iter().flat_map(|&(start, end)| [start, end]).collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut ranges: Vec<(Attr, (usize, usize))> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let Some(group) = groups.iter().rposition(|&(s, e)| s <= start && end <= e) else {
+                continue;
+            };
+            match ranges.last_mut() {
+                Some((last_attr, (_, last_end))) if *last_end == start && *last_attr == attr(group) => *last_end = end,
+                _ => ranges.push((attr(group), (start, end))),
+            }
+        }
+        ranges
+    }
+
     fn draw_item(
         &self,
         canvas: &mut dyn Canvas,
         row: usize,
         matched_item: &MatchedItem,
         is_current: bool,
+        rows: usize,
     ) -> DrawResult<()> {
         let (screen_width, screen_height) = canvas.size()?;
 
@@ -450,21 +701,43 @@ impl Selection {
             self.theme.matched()
         };
 
-        // print selection cursor
+        // print selection cursor, along the whole block of a multi-line item
         let index = (current_run_num(), matched_item.item_idx);
-        if self.selected.contains_key(&index) {
-            let _ = canvas.print_with_attr(row, 1, ">", default_attr.extend(self.theme.selected()));
-        } else {
-            let _ = canvas.print_with_attr(row, 1, " ", default_attr);
+        for line_no in 0..rows {
+            if self.selected.contains_key(&index) {
+                let marker = if line_no == 0 { ">" } else { "│" };
+                let _ = canvas.print_with_attr(row + line_no, 1, marker, default_attr.extend(self.theme.selected()));
+            } else {
+                let _ = canvas.print_with_attr(row + line_no, 1, " ", default_attr);
+            }
         }
 
         let item = &matched_item.upgrade_infallible();
         let item_text = item.text();
-        let container_width = screen_width - 2;
+
+        // the label of the source comes first, unless it would take most of the line
+        let source_width = match item
+            .source()
+            .filter(|source| source.width_cjk() < (screen_width - 2) / 2)
+        {
+            Some(source) => {
+                let _ = canvas.print_with_attr(row, 2, source, default_attr.extend(self.theme.source()));
+                source.width_cjk() + 1
+            }
+            None => 0,
+        };
+        let col = 2 + source_width;
+        let container_width = screen_width - col;
+
+        let group_ranges = match &matched_item.matched_range {
+            Some(MatchRange::Groups(groups)) => self.group_ranges(groups, is_current),
+            _ => Vec::new(),
+        };
 
         let opt_matches = match &matched_item.matched_range {
             Some(MatchRange::Chars(matched_indices)) => Some(Matches::CharIndices(matched_indices)),
             Some(MatchRange::ByteRange(start, end)) => Some(Matches::ByteRange(*start, *end)),
+            Some(MatchRange::Groups(_)) => Some(Matches::ByteRanges(&group_ranges)),
             _ => None,
         };
 
@@ -478,6 +751,11 @@ impl Selection {
 
         let display_content = item.display(context);
 
+        if rows > 1 {
+            self.draw_lines(canvas, row, rows, display_content, default_attr, col, container_width);
+            return Ok(());
+        }
+
         let mut printer = if display_content.stripped() == item_text {
             // need to display the match content
             let (match_start_char, match_end_char) = match matched_item.matched_range {
@@ -489,8 +767,14 @@ impl Selection {
                     }
                 }
                 Some(MatchRange::ByteRange(match_start, match_end)) => {
-                    let match_start_char = item_text[..match_start].len();
-                    let diff = item_text[match_start..match_end].len();
+                    let match_start_char = item_text[..match_start].chars().count();
+                    let diff = item_text[match_start..match_end].chars().count();
+                    (match_start_char, match_start_char + diff)
+                }
+                Some(MatchRange::Groups(ref groups)) => {
+                    let (match_start, match_end) = groups[0];
+                    let match_start_char = item_text[..match_start].chars().count();
+                    let diff = item_text[match_start..match_end].chars().count();
                     (match_start_char, match_start_char + diff)
                 }
                 None => (0, 0),
@@ -519,7 +803,7 @@ impl Selection {
 
             LinePrinter::builder()
                 .row(row)
-                .col(2)
+                .col(col)
                 .tabstop(self.tabstop)
                 .container_width(container_width)
                 .shift(shift)
@@ -529,7 +813,7 @@ impl Selection {
         } else {
             LinePrinter::builder()
                 .row(row)
-                .col(2)
+                .col(col)
                 .tabstop(self.tabstop)
                 .container_width(container_width)
                 .text_width(display_content.stripped().width_cjk())
@@ -542,6 +826,48 @@ impl Selection {
 
         Ok(())
     }
+
+    // print each line of the content on its own row from column `col`, the highlights are kept as
+    // the attributes of the characters
+    #[allow(clippy::too_many_arguments)]
+    fn draw_lines(
+        &self,
+        canvas: &mut dyn Canvas,
+        row: usize,
+        rows: usize,
+        content: AnsiString,
+        default_attr: Attr,
+        col: usize,
+        container_width: usize,
+    ) {
+        let line_printer = |line_no: usize, line: &str| {
+            let text_width = accumulate_text_width(line, self.tabstop).last().copied().unwrap_or(0);
+            LinePrinter::builder()
+                .row(row + line_no)
+                .col(col)
+                .tabstop(self.tabstop)
+                .container_width(container_width)
+                .text_width(text_width)
+                .hscroll_offset(self.hscroll_offset)
+                .build()
+        };
+
+        let mut lines = content.stripped().split('\n');
+        let mut line_no = 0;
+        let mut printer = line_printer(line_no, lines.next().unwrap_or(""));
+        for (ch, attr) in content.iter() {
+            if ch != '\n' {
+                printer.print_char(canvas, ch, default_attr.extend(attr), false);
+                continue;
+            }
+
+            line_no += 1;
+            if line_no >= rows {
+                break;
+            }
+            printer = line_printer(line_no, lines.next().unwrap_or(""));
+        }
+    }
 }
 
 impl Draw for Selection {
@@ -549,6 +875,12 @@ impl Draw for Selection {
         let (_screen_width, screen_height) = canvas.size()?;
         canvas.clear()?;
 
+        // only the items sorted already are drawn, the others once they are
+        self.sort_screen();
+        if self.multi_line > 1 {
+            return self.draw_blocks(canvas);
+        }
+
         let item_idx_lower = self.item_cursor;
         let max_upper = self.item_cursor + screen_height;
         let item_idx_upper = min(max_upper, self.items.len());
@@ -556,6 +888,9 @@ impl Draw for Selection {
         clear_canvas(canvas)?;
 
         for item_idx in item_idx_lower..item_idx_upper {
+            let Some(item) = self.items.get_sorted(item_idx) else {
+                break;
+            };
             let line_cursor = item_idx - item_idx_lower;
             let line_no = if self.reverse {
                 // top down
@@ -569,12 +904,49 @@ impl Draw for Selection {
             let label = if line_cursor == self.line_cursor { ">" } else { " " };
             let _next_col = canvas.print_with_attr(line_no, 0, label, self.theme.cursor()).unwrap();
 
-            let item = self
-                .items
-                .get(item_idx)
-                .unwrap_or_else(|| panic!("model:draw_items: failed to get item at {}", item_idx));
+            let _ = self.draw_item(canvas, line_no, &item, line_cursor == self.line_cursor, 1);
+        }
 
-            let _ = self.draw_item(canvas, line_no, &item, line_cursor == self.line_cursor);
+        Ok(())
+    }
+}
+
+impl Selection {
+    // draw the items as blocks of rows, the last one is cut at the screen end
+    fn draw_blocks(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
+        let (_screen_width, screen_height) = canvas.size()?;
+        clear_canvas(canvas)?;
+
+        let mut used = 0;
+        let mut item_idx = self.item_cursor;
+        while used < screen_height && item_idx < self.items.len() {
+            let Some(item) = self.items.get_sorted(item_idx) else {
+                break;
+            };
+            let rows = min(self.rows_of(&item), screen_height - used);
+            let line_no = if self.reverse {
+                // top down
+                used
+            } else {
+                // bottom up
+                screen_height - used - rows
+            };
+
+            // print the cursor label
+            let is_current = item_idx - self.item_cursor == self.line_cursor;
+            for block_row in 0..rows {
+                let label = match (is_current, block_row) {
+                    (false, _) => " ",
+                    (true, 0) => ">",
+                    (true, _) => "│",
+                };
+                let _ = canvas.print_with_attr(line_no + block_row, 0, label, self.theme.cursor());
+            }
+
+            let _ = self.draw_item(canvas, line_no, &item, is_current, rows);
+
+            used += rows;
+            item_idx += 1;
         }
 
         Ok(())
@@ -600,3 +972,135 @@ impl Widget<Event> for Selection {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::{ItemRef, RankBuilder, SortKey};
+
+    fn matched_items(items: &[Arc<dyn SkimItem>], rank_builder: &RankBuilder) -> Vec<MatchedItem> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| MatchedItem {
+                item: ItemRef::from(item),
+                rank: rank_builder.build_rank(0, 0, 0, &item.text(), idx),
+                matched_range: None,
+                item_idx: idx as u32,
+            })
+            .collect()
+    }
+
+    fn texts(selection: &Selection) -> Vec<String> {
+        selection
+            .items
+            .iter()
+            .map(|item| item.upgrade_infallible().text().to_string())
+            .collect()
+    }
+
+    // items of 1, 2, 3, 3 and 1 rows, at most 3 rows each, on a screen of 4 rows
+    fn blocks(reverse: bool) -> (Vec<Arc<dyn SkimItem>>, Selection) {
+        let items: Vec<Arc<dyn SkimItem>> = vec![
+            Arc::new("1"),
+            Arc::new("2\n2"),
+            Arc::new("3\n3\n3"),
+            Arc::new("4\n4\n4\n4"),
+            Arc::new("5\n"),
+        ];
+        let mut selection = Selection::new();
+        selection.multi_line = 3;
+        selection.reverse = reverse;
+        selection.height.store(4, Ordering::Relaxed);
+        selection.append_sorted_items(matched_items(&items, &RankBuilder::default()));
+        (items, selection)
+    }
+
+    fn cursor(selection: &Selection) -> (usize, usize) {
+        (selection.item_cursor, selection.line_cursor)
+    }
+
+    #[test]
+    fn test_item_rows() {
+        let (_items, mut selection) = blocks(false);
+        let rows: Vec<usize> = (0..5).map(|idx| selection.item_rows(idx)).collect();
+        assert_eq!(rows, [1, 2, 3, 3, 1]);
+        assert_eq!(selection.rows_by_item.borrow().len(), 5);
+        // out of the list
+        assert_eq!(selection.item_rows(5), 1);
+
+        selection.multi_line = 1;
+        assert_eq!(selection.item_rows(2), 1);
+    }
+
+    #[test]
+    fn test_move_block_cursor() {
+        let (_items, mut selection) = blocks(false);
+        selection.move_block_cursor(1);
+        assert_eq!(cursor(&selection), (0, 1));
+        // the first items are scrolled off so that the block of the cursor fits
+        selection.move_block_cursor(1);
+        assert_eq!(cursor(&selection), (2, 0));
+
+        // at the bottom of the list
+        selection.move_block_cursor(10);
+        assert_eq!(cursor(&selection), (3, 1));
+        selection.move_block_cursor(1);
+        assert_eq!(cursor(&selection), (3, 1));
+
+        // at the top of the list
+        selection.move_block_cursor(-10);
+        assert_eq!(cursor(&selection), (0, 0));
+        selection.move_block_cursor(-1);
+        assert_eq!(cursor(&selection), (0, 0));
+
+        // moving up goes to the previous items with reverse
+        let (_items, mut selection) = blocks(true);
+        selection.move_block_cursor(2);
+        selection.act_move_line_cursor(1);
+        assert_eq!(cursor(&selection), (1, 0));
+        selection.act_move_line_cursor(-1);
+        assert_eq!(cursor(&selection), (2, 0));
+    }
+
+    #[test]
+    fn test_rows_to_items() {
+        let (_items, mut selection) = blocks(false);
+        assert_eq!(selection.rows_to_items(0), 0);
+        // the items of 2 then 3 rows
+        assert_eq!(selection.rows_to_items(3), 1);
+        assert_eq!(selection.rows_to_items(5), 2);
+        assert_eq!(selection.rows_to_items(6), 2);
+        // at least one item, even at the top of the list
+        assert_eq!(selection.rows_to_items(1), 1);
+        assert_eq!(selection.rows_to_items(-3), -1);
+
+        selection.move_block_cursor(4);
+        assert_eq!(selection.rows_to_items(-4), -1);
+        assert_eq!(selection.rows_to_items(-7), -2);
+
+        // the rows go the other way with reverse
+        let (_items, mut selection) = blocks(true);
+        selection.move_block_cursor(4);
+        assert_eq!(selection.rows_to_items(4), 1);
+        assert_eq!(selection.rows_to_items(-4), -1);
+    }
+
+    #[test]
+    fn test_rerank() {
+        let items: Vec<Arc<dyn SkimItem>> = vec![Arc::new("a 2"), Arc::new("b 3"), Arc::new("c 1")];
+        let rank_builder = RankBuilder::default().sort_keys(vec![SortKey::parse("2", &[]).unwrap()]);
+        let mut selection = Selection::new();
+        selection.append_sorted_items(matched_items(&items, &rank_builder));
+        assert_eq!(texts(&selection), ["c 1", "a 2", "b 3"]);
+
+        // by the score, then the input order
+        assert!(rank_builder.toggle_sort());
+        selection.rerank(|item| rank_builder.update_sort(item));
+        assert_eq!(texts(&selection), ["a 2", "b 3", "c 1"]);
+
+        assert!(rank_builder.toggle_sort());
+        selection.rerank(|item| rank_builder.update_sort(item));
+        assert_eq!(texts(&selection), ["c 1", "a 2", "b 3"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! The shell that runs the commands of the sources, of `execute` and of the preview, e.g.
+//! `--with-shell "sh -c"`. The fields injected into the commands are quoted for that shell.
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use crate::util::escape_single_quote;
+
+const DEFAULT_SHELL: &str = "sh";
+
+/// How the text injected into a command is quoted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quoting {
+    /// `'it'\''s'`, nothing is special between single quotes
+    #[default]
+    Posix,
+    /// `'it\'s'`, `\'` and `\\` are escapes between single quotes
+    Fish,
+}
+
+impl Quoting {
+    /// the text as a single word of the shell, e.g. `it's` => `'it'\''s'`
+    pub fn quote(self, text: &str) -> String {
+        match self {
+            Quoting::Posix => format!("'{}'", escape_single_quote(text)),
+            Quoting::Fish => {
+                let mut quoted = String::with_capacity(text.len() + 2);
+                quoted.push('\'');
+                for ch in text.chars() {
+                    match ch {
+                        '\'' => quoted.push_str("\\'"),
+                        '\\' => quoted.push_str("\\\\"),
+                        '\0' => quoted.push_str("\\0"),
+                        _ => quoted.push(ch),
+                    }
+                }
+                quoted.push('\'');
+                quoted
+            }
+        }
+    }
+}
+
+/// The program and the flags that a command is appended to, e.g. `bash -c`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Shell {
+    program: String,
+    args: Vec<String>,
+    quoting: Quoting,
+}
+
+impl Default for Shell {
+    /// `$SHELL -c`, or `sh -c` if `$SHELL` is not set
+    fn default() -> Self {
+        let program = env::var("SHELL")
+            .ok()
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| DEFAULT_SHELL.to_string());
+        Self::new(program, vec!["-c".to_string()])
+    }
+}
+
+impl Shell {
+    /// parse the words of e.g. "sh -c" or "env fish -c", separated by whitespace. The quoting is
+    /// that of fish if the shell is fish, POSIX otherwise. `None` if there is no program.
+    pub fn parse(with_shell: &str) -> Option<Self> {
+        let mut words = with_shell.split_whitespace().map(str::to_string);
+        let program = words.next()?;
+        Some(Self::new(program, words.collect()))
+    }
+
+    fn new(program: String, args: Vec<String>) -> Self {
+        // the shell run by `env` is the first word that is not a flag
+        let shell = match file_name(&program) {
+            "env" => args.iter().map(String::as_str).find(|arg| !arg.starts_with('-')),
+            _ => Some(program.as_str()),
+        };
+        let quoting = match shell.map(file_name) {
+            Some("fish") => Quoting::Fish,
+            _ => Quoting::Posix,
+        };
+
+        Self { program, args, quoting }
+    }
+
+    pub fn quoting(&self) -> Quoting {
+        self.quoting
+    }
+
+    /// the program and the arguments that run `cmd` with the shell
+    pub fn argv(&self, cmd: &str) -> Vec<String> {
+        let mut argv = Vec::with_capacity(self.args.len() + 2);
+        argv.push(self.program.clone());
+        argv.extend(self.args.iter().cloned());
+        argv.push(cmd.to_string());
+        argv
+    }
+
+    /// the command that runs `cmd` with the shell
+    pub fn command(&self, cmd: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).arg(cmd);
+        command
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shell() {
+        let shell = Shell::parse("  bash   -c ").unwrap();
+        assert_eq!(shell.program, "bash");
+        assert_eq!(shell.args, ["-c"]);
+        assert_eq!(shell.quoting(), Quoting::Posix);
+
+        assert_eq!(Shell::parse("/usr/bin/fish -c").unwrap().quoting(), Quoting::Fish);
+        assert_eq!(Shell::parse("env -i fish -c").unwrap().quoting(), Quoting::Fish);
+        assert_eq!(Shell::parse("env -i sh -c").unwrap().quoting(), Quoting::Posix);
+        assert_eq!(Shell::parse(" "), None);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(Quoting::Posix.quote(r"it's a\b"), r"'it'\''s a\b'");
+        assert_eq!(Quoting::Fish.quote(r"it's a\b"), r"'it\'s a\\b'");
+        assert_eq!(Quoting::Fish.quote("a\0"), r"'a\0'");
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let output = Shell::parse("sh -c").unwrap().command("echo $0").output().unwrap();
+        assert_eq!(output.stdout, b"sh\n");
+    }
+}
@@ -1,5 +1,6 @@
 ///! Handle the color theme
 use crate::options::SkimOptions;
+use std::cmp::max;
 use std::sync::LazyLock;
 use tuikit::prelude::*;
 
@@ -12,10 +13,13 @@ pub static DEFAULT_THEME:  LazyLock<ColorTheme> = LazyLock::new(|| ColorTheme::d
 /// +----------------+
 /// | >selected line |  --> selected & normal(fg/bg) & matched
 /// |> current line  |  --> cursor & current & current_match
+/// |  regex (group)  |  --> group1..group4, the capture groups of a regex
 /// |  normal line   |
 /// |\ 8/10          |  --> spinner & info
 /// |> query         |  --> prompt & query
 /// +----------------+
+///
+/// The label of the source of an item (see `--source`) is printed before it in the `source` color.
 /// </pre>
 #[rustfmt::skip]
 #[derive(Copy, Clone, Debug)]
@@ -32,6 +36,7 @@ pub struct ColorTheme {
     current_match:        Color,
     current_match_bg:     Color,
     current_match_effect: Effect,
+    groups:               [Color; 4],
     query_fg:             Color,
     query_bg:             Color,
     query_effect:         Effect,
@@ -42,6 +47,7 @@ pub struct ColorTheme {
     selected:             Color,
     header:               Color,
     border:               Color,
+    source:               Color,
 }
 
 #[rustfmt::skip]
@@ -70,6 +76,7 @@ impl ColorTheme {
             current_match:        Color::Default,
             current_match_bg:     Color::Default,
             current_match_effect: Effect::empty(),
+            groups:               [Color::Default; 4],
             query_fg:             Color::Default,
             query_bg:             Color::Default,
             query_effect:         Effect::empty(),
@@ -80,6 +87,7 @@ impl ColorTheme {
             selected:             Color::Default,
             header:               Color::Default,
             border:               Color::Default,
+            source:               Color::Default,
         }
     }
 
@@ -100,6 +108,7 @@ impl ColorTheme {
             current_bg:       Color::BLACK,
             current_match:    Color::GREEN,
             current_match_bg: Color::BLACK,
+            groups:           [Color::YELLOW, Color::MAGENTA, Color::CYAN, Color::RED],
             spinner:          Color::GREEN,
             info:             Color::WHITE,
             prompt:           Color::BLUE,
@@ -107,6 +116,7 @@ impl ColorTheme {
             selected:         Color::MAGENTA,
             header:           Color::CYAN,
             border:           Color::LIGHT_BLACK,
+            source:           Color::BLUE,
             ..ColorTheme::empty()
         }
     }
@@ -119,6 +129,7 @@ impl ColorTheme {
             current_bg:       Color::AnsiValue(236),
             current_match:    Color::AnsiValue(151),
             current_match_bg: Color::AnsiValue(236),
+            groups:           [Color::AnsiValue(180), Color::AnsiValue(139), Color::AnsiValue(110), Color::AnsiValue(174)],
             spinner:          Color::AnsiValue(148),
             info:             Color::AnsiValue(144),
             prompt:           Color::AnsiValue(110),
@@ -126,6 +137,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(109),
             border:           Color::AnsiValue(59),
+            source:           Color::AnsiValue(103),
             ..ColorTheme::empty()
         }
     }
@@ -138,6 +150,7 @@ impl ColorTheme {
             current_bg:       Color::AnsiValue(236),
             current_match:    Color::AnsiValue(234),
             current_match_bg: Color::AnsiValue(186),
+            groups:           [Color::AnsiValue(88), Color::AnsiValue(54), Color::AnsiValue(18), Color::AnsiValue(22)],
             spinner:          Color::AnsiValue(148),
             info:             Color::AnsiValue(144),
             prompt:           Color::AnsiValue(110),
@@ -145,6 +158,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(109),
             border:           Color::AnsiValue(59),
+            source:           Color::AnsiValue(103),
             ..ColorTheme::empty()
         }
     }
@@ -157,6 +171,7 @@ impl ColorTheme {
             current_bg:       Color::AnsiValue(251),
             current_match:    Color::AnsiValue(66),
             current_match_bg: Color::AnsiValue(251),
+            groups:           [Color::AnsiValue(130), Color::AnsiValue(90), Color::AnsiValue(25), Color::AnsiValue(160)],
             spinner:          Color::AnsiValue(65),
             info:             Color::AnsiValue(101),
             prompt:           Color::AnsiValue(25),
@@ -164,6 +179,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(31),
             border:           Color::AnsiValue(145),
+            source:           Color::AnsiValue(60),
             ..ColorTheme::empty()
         }
     }
@@ -206,6 +222,10 @@ impl ColorTheme {
                 "current_bg" | "bg+"    => theme.current_bg       = new_color,
                 "current_match" | "hl+" => theme.current_match    = new_color,
                 "current_match_bg"      => theme.current_match_bg = new_color,
+                "group1"                => theme.groups[0]        = new_color,
+                "group2"                => theme.groups[1]        = new_color,
+                "group3"                => theme.groups[2]        = new_color,
+                "group4"                => theme.groups[3]        = new_color,
                 "query"                 => theme.query_fg         = new_color,
                 "query_bg"              => theme.query_bg         = new_color,
                 "spinner"               => theme.spinner          = new_color,
@@ -215,6 +235,7 @@ impl ColorTheme {
                 "selected" | "marker"   => theme.selected         = new_color,
                 "header"                => theme.header           = new_color,
                 "border"                => theme.border           = new_color,
+                "source"                => theme.source           = new_color,
                 _ => {}
             }
         }
@@ -253,6 +274,28 @@ impl ColorTheme {
         }
     }
 
+    /// the `group`th capture group of a regex (from 1), the colors are reused after group4
+    pub fn matched_group(&self, group: usize) -> Attr {
+        Attr {
+            fg: self.group_color(group, self.matched),
+            ..self.matched()
+        }
+    }
+
+    pub fn current_match_group(&self, group: usize) -> Attr {
+        Attr {
+            fg: self.group_color(group, self.current_match),
+            ..self.current_match()
+        }
+    }
+
+    fn group_color(&self, group: usize, default: Color) -> Color {
+        match self.groups[(max(group, 1) - 1) % self.groups.len()] {
+            Color::Default => default,
+            color => color,
+        }
+    }
+
     pub fn query(&self) -> Attr {
         Attr {
             fg: self.query_fg,
@@ -316,4 +359,11 @@ impl ColorTheme {
             effect: Effect::empty(),
         }
     }
+
+    pub fn source(&self) -> Attr {
+        Attr {
+            fg: self.source,
+            ..Attr::default()
+        }
+    }
 }
@@ -0,0 +1,197 @@
+//! Romanized "shadow" text of the items, so that a query typed on a Latin keyboard is able to
+//! match items written in Cyrillic or Chinese (`--translit=ru,pinyin`).
+//!
+//! The shadow is computed once, when the item is read. Every char of the shadow remembers the
+//! char of the original text it came from, so that the matches found in the shadow can be
+//! highlighted in the original text.
+use bitflags::bitflags;
+use pinyin::ToPinyin;
+
+use crate::MatchRange;
+
+bitflags! {
+    /// The scripts to romanize
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Translit: u8 {
+        /// Russian Cyrillic, e.g. "Москва" => "Moskva"
+        const RU = 0b0000_0001;
+        /// Chinese characters, as pinyin without tones, e.g. "中文" => "zhongwen"
+        const PINYIN = 0b0000_0010;
+    }
+}
+
+impl Translit {
+    /// parse a comma separated list of scripts, e.g. "ru,pinyin", unknown scripts are ignored
+    pub fn parse(names: &str) -> Self {
+        names
+            .split(',')
+            .fold(Translit::empty(), |translit, name| match name.trim() {
+                "ru" => translit | Translit::RU,
+                "pinyin" => translit | Translit::PINYIN,
+                _ => translit,
+            })
+    }
+
+    /// whether some chars of `text` would be romanized
+    pub fn applies_to(self, text: &str) -> bool {
+        !self.is_empty() && !text.is_ascii() && text.chars().any(|ch| self.romanize(ch).is_some())
+    }
+
+    /// the romanized text, `None` if no char of `text` is romanized
+    pub fn shadow(self, text: &str) -> Option<ShadowText> {
+        if !self.applies_to(text) {
+            return None;
+        }
+
+        let mut shadow = String::with_capacity(text.len());
+        let mut origins = Vec::with_capacity(text.len());
+        for (char_idx, ch) in text.chars().enumerate() {
+            match self.romanize(ch) {
+                Some(latin) => {
+                    for (idx, latin_ch) in latin.chars().enumerate() {
+                        if idx == 0 && ch.is_uppercase() {
+                            shadow.push(latin_ch.to_ascii_uppercase());
+                        } else {
+                            shadow.push(latin_ch);
+                        }
+                        origins.push(char_idx as u32);
+                    }
+                }
+                None => {
+                    shadow.push(ch);
+                    origins.push(char_idx as u32);
+                }
+            }
+        }
+
+        Some(ShadowText {
+            text: shadow.into_boxed_str(),
+            origins: origins.into_boxed_slice(),
+            ranges: None,
+        })
+    }
+
+    // the lowercase latin spelling of `ch`
+    fn romanize(self, ch: char) -> Option<&'static str> {
+        let ru = || ch.to_lowercase().next().and_then(romanize_ru);
+        let pinyin = || ch.to_pinyin().map(|pinyin| pinyin.plain());
+        self.contains(Translit::RU)
+            .then(ru)
+            .flatten()
+            .or_else(|| self.contains(Translit::PINYIN).then(pinyin).flatten())
+    }
+}
+
+#[rustfmt::skip]
+fn romanize_ru(ch: char) -> Option<&'static str> {
+    let latin = match ch {
+        'а' => "a",  'б' => "b",  'в' => "v",  'г' => "g",    'д' => "d",  'е' => "e",  'ё' => "e",
+        'ж' => "zh", 'з' => "z",  'и' => "i",  'й' => "y",    'к' => "k",  'л' => "l",  'м' => "m",
+        'н' => "n",  'о' => "o",  'п' => "p",  'р' => "r",    'с' => "s",  'т' => "t",  'у' => "u",
+        'ф' => "f",  'х' => "kh", 'ц' => "ts", 'ч' => "ch",   'ш' => "sh", 'щ' => "shch",
+        'ъ' => "",   'ы' => "y",  'ь' => "",   'э' => "e",    'ю' => "yu", 'я' => "ya",
+        _ => return None,
+    };
+    Some(latin)
+}
+
+//------------------------------------------------------------------------------
+/// The romanized text of an item, see `Translit`
+#[derive(Debug)]
+pub struct ShadowText {
+    text: Box<str>,
+    // index of the original char every char of `text` came from
+    origins: Box<[u32]>,
+    // the matching ranges of the original text, in `text`
+    ranges: Option<Box<[(usize, usize)]>>,
+}
+
+impl ShadowText {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        self.ranges.as_deref()
+    }
+
+    /// limit the matching to the shadow of the given byte ranges of the original text
+    pub fn with_matching_ranges(mut self, orig_text: &str, ranges: &[(usize, usize)]) -> Self {
+        let shadow_byte = |orig_byte: usize| {
+            let char_idx = orig_text[..orig_byte.min(orig_text.len())].chars().count() as u32;
+            self.text
+                .char_indices()
+                .zip(self.origins.iter())
+                .find(|&(_, &origin)| origin >= char_idx)
+                .map_or(self.text.len(), |((byte_idx, _), _)| byte_idx)
+        };
+
+        let ranges = ranges
+            .iter()
+            .map(|&(start, end)| (shadow_byte(start), shadow_byte(end)))
+            .collect();
+        self.ranges = Some(ranges);
+        self
+    }
+
+    /// the indices of the original chars covered by a match of the shadow text
+    pub fn orig_char_indices(&self, matched_range: &MatchRange) -> Vec<usize> {
+        let mut indices: Vec<usize> = match matched_range {
+            MatchRange::ByteRange(start, end) => self.orig_chars_of_bytes(*start, *end),
+            MatchRange::Groups(groups) => self.orig_chars_of_bytes(groups[0].0, groups[0].1),
+            MatchRange::Chars(chars) => chars
+                .iter()
+                .filter_map(|&idx| self.origins.get(idx))
+                .map(|&origin| origin as usize)
+                .collect(),
+        };
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    fn orig_chars_of_bytes(&self, start: usize, end: usize) -> Vec<usize> {
+        self.text
+            .char_indices()
+            .zip(self.origins.iter())
+            .filter(|&((byte_idx, _), _)| start <= byte_idx && byte_idx < end)
+            .map(|(_, &origin)| origin as usize)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow() {
+        let translit = Translit::parse("ru, pinyin,klingon");
+        assert_eq!(translit, Translit::RU | Translit::PINYIN);
+
+        assert!(translit.shadow("plain ascii").is_none());
+        assert!(Translit::RU.shadow("中文").is_none());
+        assert_eq!(translit.shadow("Москва-Щука").unwrap().text(), "Moskva-Shchuka");
+        assert_eq!(translit.shadow("中文 docs").unwrap().text(), "zhongwen docs");
+        assert_eq!(Translit::PINYIN.shadow("Москва 中").unwrap().text(), "Москва zhong");
+    }
+
+    #[test]
+    fn test_shadow_mapping() {
+        let shadow = Translit::RU.shadow("Жук.txt").unwrap();
+        assert_eq!(shadow.text(), "Zhuk.txt");
+        // "zhu" => "Жу"
+        assert_eq!(shadow.orig_char_indices(&MatchRange::ByteRange(0, 3)), [0, 1]);
+        assert_eq!(
+            shadow.orig_char_indices(&MatchRange::Chars([1, 3, 5].into())),
+            [0, 2, 4]
+        );
+
+        // the second field "Жук" of "1 Жук" (bytes 2..8)
+        let shadow = Translit::RU
+            .shadow("1 Жук")
+            .unwrap()
+            .with_matching_ranges("1 Жук", &[(2, 8)]);
+        assert_eq!(shadow.matching_ranges(), Some(&[(2, 6)][..]));
+    }
+}
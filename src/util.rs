@@ -7,8 +7,9 @@ use std::sync::LazyLock;
 use tuikit::prelude::*;
 use unicode_width::UnicodeWidthChar;
 
-use crate::field::get_string_by_range;
 use crate::AnsiString;
+use crate::field::{FieldRange, get_string_by_range, index_field_names};
+use crate::shell::Quoting;
 
 static RE_ESCAPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"['\U{00}]").unwrap());
 static RE_NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[+|-]?\d+").unwrap());
@@ -312,14 +313,24 @@ pub struct InjectContext<'a> {
     pub delimiter: &'a Regex,
     pub current_index: usize,
     pub current_selection: &'a str,
+    // the text the fields of the current selection are taken from, see `SkimItem::field_text`
+    pub current_fields: &'a str,
     pub indices: &'a [usize],
     pub selections: &'a [Box<str>],
     pub query: &'a str,
     pub cmd_query: &'a str,
+    // the whole match and the capture groups of the current selection, in regex mode
+    pub captures: &'a [Box<str>],
+    // the label of the source of the current selection, see `SkimItem::source`
+    pub current_source: &'a str,
+    // how the injected text is quoted, that of the shell running the command
+    pub quoting: Quoting,
 }
 
-static RE_ITEMS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\?(\{ *-?[0-9.+]*? *})").unwrap());
-static RE_FIELDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\?(\{ *-?[0-9.,cq+n]*? *})").unwrap());
+static RE_ITEMS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\?(\{ *(?:src|r[0-9]+|-?[0-9.+]*?) *})").unwrap());
+static RE_FIELDS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\\?(\{ *(?:src|r[0-9]+|-?[0-9.,cq+n]*?) *})").unwrap());
+static RE_NAMES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\?\{ *(\+?)([^\s{}+]+?) *}").unwrap());
 
 /// Check if a command depends on item
 /// e.g. contains `{}`, `{1..}`, `{+}`
@@ -327,6 +338,28 @@ pub fn depends_on_items(cmd: &str) -> bool {
     RE_ITEMS.is_match(cmd)
 }
 
+/// replace the names of the fields in placeholders by their indices, e.g. `{user}` => `{1}` and
+/// `{+user..}` => `{+1..}` if `user` is the first name. The placeholders such as `{q}` are kept.
+pub fn inject_field_names<'a>(cmd: &'a str, field_names: &[String]) -> Cow<'a, str> {
+    if field_names.is_empty() {
+        return Cow::Borrowed(cmd);
+    }
+
+    let field_names: Vec<&str> = field_names.iter().map(String::as_str).collect();
+    RE_NAMES.replace_all(cmd, |caps: &Captures| {
+        let range = &caps[2];
+        let indexed = index_field_names(range, &field_names);
+        if caps[0].starts_with('\\')
+            || indexed == range
+            || RE_FIELDS.is_match(&caps[0])
+            || FieldRange::from_str(&indexed).is_none()
+        {
+            return caps[0].to_string();
+        }
+        format!("{{{}{}}}", &caps[1], indexed)
+    })
+}
+
 /// inject the fields into commands
 /// cmd: `echo {1..}`, text: `a,b,c`, delimiter: `,`
 /// => `echo b,c`
@@ -336,6 +369,9 @@ pub fn depends_on_items(cmd: &str) -> bool {
 /// * `{+}` for all selections
 /// * `{q}` for query
 /// * `{cq}` for command query
+/// * `{r1}`, etc. for the capture groups of the regex in the current selection, `{r0}` for the
+///   whole match
+/// * `{src}` for the label of the source of the current selection
 pub fn inject_command<'a>(cmd: &'a str, context: InjectContext<'a>) -> Cow<'a, str> {
     RE_FIELDS.replace_all(cmd, |caps: &Captures| {
         // \{...
@@ -343,55 +379,120 @@ pub fn inject_command<'a>(cmd: &'a str, context: InjectContext<'a>) -> Cow<'a, s
             return caps[0].to_string();
         }
 
-        // {1..} and other variant
-        let range = &caps[1];
-        assert!(range.len() >= 2);
-        let range = &range[1..range.len() - 1];
-        let range = range.trim();
-
-        if range.starts_with('+') {
-            let current_selection = vec![context.current_selection.into()];
-            let selections = if context.selections.is_empty() {
-                current_selection.as_slice()
-            } else {
-                context.selections
-            };
-            let current_index = vec![context.current_index];
-            let indices = if context.indices.is_empty() {
-                &current_index
-            } else {
-                context.indices
-            };
-
-            return selections
-                .iter()
-                .zip(indices.iter())
-                .map(|(s, i)| {
-                    let rest = &range[1..];
-                    let index_str = format!("{}", i);
-                    let replacement = match rest {
-                        "" => s,
-                        "n" => index_str.as_str(),
-                        _ => get_string_by_range(context.delimiter, s, rest).unwrap_or(""),
-                    };
-                    format!("'{}'", escape_single_quote(replacement))
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
+        placeholder_values(&caps[1], &context)
+            .iter()
+            .map(|value| context.quoting.quote(value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// split a command that is run without a shell into its arguments, at the whitespace outside of
+/// quotes. The fields are injected afterwards, see `inject_argv`. The single or double quotes
+/// around the words are removed, e.g. `nvim "+call cursor({2}, 1)" {1}` =>
+/// [`nvim`, `+call cursor({2}, 1)`, `{1}`]
+pub fn split_argv(cmd: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    // whether an argument is started, it may be empty, e.g. `''`
+    let mut in_arg = false;
+    let mut quote = None;
+    for ch in cmd.chars() {
+        match quote {
+            Some(closing) if ch == closing => quote = None,
+            Some(_) => arg.push(ch),
+            None if ch == '\'' || ch == '"' => {
+                quote = Some(ch);
+                in_arg = true;
+            }
+            None if ch.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            None => {
+                arg.push(ch);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    args
+}
+
+/// inject the fields into the arguments of a command run without a shell, as is, without any
+/// quotes. An argument that is only a `{+..}` placeholder becomes one argument per selection,
+/// the values are separated by spaces otherwise. `\{...}` is kept without the backslash.
+pub fn inject_argv(args: &[String], context: InjectContext) -> Vec<String> {
+    let mut argv = Vec::with_capacity(args.len());
+    for arg in args {
+        if let Some(caps) = RE_FIELDS.captures(arg) {
+            let placeholder = &caps[1];
+            if caps[0].len() == arg.len() && placeholder[1..].trim_start().starts_with('+') {
+                argv.extend(placeholder_values(placeholder, &context));
+                continue;
+            }
         }
 
-        let index_str = format!("{}", context.current_index);
-        let replacement = match range {
-            "" => context.current_selection,
-            x if x.starts_with('+') => unreachable!(),
-            "n" => &index_str,
-            "q" => context.query,
-            "cq" => context.cmd_query,
-            _ => get_string_by_range(context.delimiter, context.current_selection, range).unwrap_or(""),
+        let injected = RE_FIELDS.replace_all(arg, |caps: &Captures| match caps[0].strip_prefix('\\') {
+            Some(placeholder) => placeholder.to_string(),
+            None => placeholder_values(&caps[1], &context).join(" "),
+        });
+        argv.push(injected.into_owned());
+    }
+    argv
+}
+
+// the values of a placeholder such as `{1..}`, one per selection for `{+..}`
+fn placeholder_values(placeholder: &str, context: &InjectContext) -> Vec<String> {
+    // {1..} and other variant
+    assert!(placeholder.len() >= 2);
+    let range = placeholder[1..placeholder.len() - 1].trim();
+
+    if let Some(rest) = range.strip_prefix('+') {
+        let current_selection = vec![context.current_selection.into()];
+        let selections = if context.selections.is_empty() {
+            current_selection.as_slice()
+        } else {
+            context.selections
+        };
+        let current_index = vec![context.current_index];
+        let indices = if context.indices.is_empty() {
+            &current_index
+        } else {
+            context.indices
         };
 
-        format!("'{}'", escape_single_quote(replacement))
-    })
+        return selections
+            .iter()
+            .zip(indices.iter())
+            .map(|(s, i)| match rest {
+                "" => s.to_string(),
+                "n" => format!("{}", i),
+                _ => get_string_by_range(context.delimiter, s, rest)
+                    .unwrap_or("")
+                    .to_string(),
+            })
+            .collect();
+    }
+
+    let replacement = match range {
+        "" => context.current_selection,
+        "n" => return vec![format!("{}", context.current_index)],
+        "q" => context.query,
+        "cq" => context.cmd_query,
+        "src" => context.current_source,
+        x if x.starts_with('r') => x[1..]
+            .parse::<usize>()
+            .ok()
+            .and_then(|group| context.captures.get(group))
+            .map_or("", |capture| capture),
+        _ => get_string_by_range(context.delimiter, context.current_fields, range).unwrap_or(""),
+    };
+    vec![replacement.to_string()]
 }
 
 pub fn str_lines(string: &str) -> Vec<&str> {
@@ -436,10 +537,14 @@ mod tests {
             current_index: 0,
             delimiter: &delimiter,
             current_selection,
+            current_fields: current_selection,
             selections: &selections,
             indices: &[0, 1],
             query,
             cmd_query,
+            captures: &[Box::from("b,c"), Box::from("c")],
+            current_source: "recent",
+            quoting: Quoting::Posix,
         };
 
         assert_eq!("'a,b,c'", inject_command("{}", default_context));
@@ -463,6 +568,95 @@ mod tests {
         assert_eq!("'a' 'x'", inject_command("{+1}", default_context));
         assert_eq!("'b' 'y'", inject_command("{+2}", default_context));
         assert_eq!("'0' '1'", inject_command("{+n}", default_context));
+
+        assert_eq!("'b,c'", inject_command("{r0}", default_context));
+        assert_eq!("'c'", inject_command("{r1}", default_context));
+        assert_eq!("''", inject_command("{r2}", default_context));
+        assert!(depends_on_items("echo {r1}"));
+        // not placeholders, the captures are those of the current selection only
+        assert_eq!(
+            "{r} {rn} {cr} {+r1}",
+            inject_command("{r} {rn} {cr} {+r1}", default_context)
+        );
+        assert!(!depends_on_items("echo {r} {+r1}"));
+
+        assert_eq!("'recent'", inject_command("{src}", default_context));
+        assert_eq!("\\{src}", inject_command("\\{src}", default_context));
+        assert!(depends_on_items("echo {src}"));
+
+        let fish_context = InjectContext {
+            current_selection: r"it's a\b",
+            quoting: Quoting::Fish,
+            ..default_context
+        };
+        assert_eq!(r"'it\'s a\\b'", inject_command("{}", fish_context));
+    }
+
+    #[test]
+    fn test_split_argv() {
+        assert_eq!(
+            split_argv(r#"  nvim "+call cursor({2}, 1)"  {1} '' a"b"'c' "#),
+            ["nvim", "+call cursor({2}, 1)", "{1}", "", "abc"]
+        );
+        assert!(split_argv("  ").is_empty());
+    }
+
+    #[test]
+    fn test_inject_argv() {
+        let delimiter = Regex::new(r",").unwrap();
+        let selections = vec![Box::from("it's,a b"), Box::from("x,$y")];
+        let context = InjectContext {
+            current_index: 0,
+            delimiter: &delimiter,
+            current_selection: "it's,a b",
+            current_fields: "it's,a b",
+            selections: &selections,
+            indices: &[0, 1],
+            query: "q",
+            cmd_query: "",
+            captures: &[],
+            current_source: "",
+            quoting: Quoting::Posix,
+        };
+
+        let args = split_argv(r"cmd {} --field={2} {+1} \{q} {q}{n} '{+2}'");
+        assert_eq!(
+            inject_argv(&args, context),
+            ["cmd", "it's,a b", "--field=a b", "it's", "x", "{q}", "q0", "a b", "$y"]
+        );
+        assert_eq!(inject_argv(&split_argv("echo pre{+1}"), context), ["echo", "preit's x"]);
+    }
+
+    #[test]
+    fn test_inject_field_names() {
+        let field_names = vec!["user".to_string(), "q".to_string(), "status".to_string()];
+        assert_eq!(
+            "{1} {+3} {1..3}",
+            inject_field_names("{user} {+status} {user..status}", &field_names)
+        );
+        assert_eq!(
+            "{q} {2} ${HOME} \\{user}",
+            inject_field_names("{q} {2} ${HOME} \\{user}", &field_names)
+        );
+
+        let delimiter = Regex::new("\t").unwrap();
+        let context = InjectContext {
+            current_index: 0,
+            delimiter: &delimiter,
+            current_selection: r#"{"user": "bob", "status": "ok"}"#,
+            current_fields: "bob\tok",
+            indices: &[],
+            selections: &[],
+            query: "",
+            cmd_query: "",
+            captures: &[],
+            current_source: "",
+            quoting: Quoting::Posix,
+        };
+        assert_eq!(
+            "'ok' '{\"user\": \"bob\", \"status\": \"ok\"}'",
+            inject_command("{2} {}", context)
+        );
     }
 
     #[test]